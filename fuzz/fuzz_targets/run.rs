@@ -0,0 +1,83 @@
+// Run an arbitrary ROM with an arbitrary key sequence in every mode
+//  Input: <mode> <n> <keys: n frames x u16 (LE; bit N = key N)> <ROM>
+//  Asserts that the interpreter does not panic, that RAM stays within the size of the
+//  mode, and that the screen always matches its dimensions. Compiled blocks are checked
+//  against the interpreter as they run.
+
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate xchip;
+
+use xchip::{Clock, Execution, Interpreter, Keypad, Mode};
+
+// Frames to run at most; each frame is 8 instructions (as in the frontend)
+const FRAMES: usize = 120;
+
+// RNG seed; fixed so every input reproduces its run
+const SEED: u32 = 0x2545F491;
+
+const MODES: [Mode; 6] = [Mode::Chip8,
+                          Mode::HiResChip8,
+                          Mode::Chip8x,
+                          Mode::Chip10,
+                          Mode::SuperChip,
+                          Mode::XoChip];
+
+struct FuzzKeypad {
+    keys: u16,
+}
+
+impl Keypad for FuzzKeypad {
+    fn key_state(&mut self, key: u8) -> bool {
+        (self.keys >> (key & 0xF)) & 1 != 0
+    }
+}
+
+fn check(interpreter: &Interpreter, mode: Mode) {
+    assert_eq!(interpreter.memory_size(), mode.memory_size());
+
+    let (screen, width, height) = interpreter.screen();
+    assert_eq!(screen.len(), width * height);
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+
+    let mode = MODES[data[0] as usize % MODES.len()];
+    let key_frames = data[1] as usize;
+
+    let (keys, rom) = data[2..].split_at((key_frames * 2).min(data.len() - 2));
+
+    let mut interpreter: Interpreter = Default::default();
+    interpreter.set_clock(Clock::External);
+    interpreter.set_seed(Some(SEED));
+    interpreter.insert_rom_bytes(rom, mode);
+    interpreter.set_execution(Execution::Differential);
+    interpreter.reset();
+    check(&interpreter, mode);
+
+    let mut keypad = FuzzKeypad { keys: 0 };
+
+    for frame in 0..FRAMES {
+        if let Some(state) = keys.chunks(2).nth(frame) {
+            keypad.keys = state[0] as u16 | (*state.get(1).unwrap_or(&0) as u16) << 8;
+        }
+
+        interpreter.run(&mut keypad, 8);
+
+        interpreter.tick();
+        check(&interpreter, mode);
+
+        let (framebuffer, width, height) = interpreter.screen_as_framebuffer();
+        assert_eq!(framebuffer.len(), width * height);
+
+        // An unhandled opcode pauses the interpreter; there is nothing left to run
+        if interpreter.is_paused() {
+            break;
+        }
+    }
+});
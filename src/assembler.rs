@@ -0,0 +1,1414 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error;
+use std::fmt::{self, Write};
+
+use interpreter::Mode;
+
+// Assembly error (with the source line it occurred on)
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+// Output of the assembler
+pub struct Assembly {
+    // Address of the first byte of `rom`
+    pub origin: usize,
+
+    // Binary ROM
+    pub rom: Vec<u8>,
+
+    // Labels (and their addresses)
+    pub symbols: BTreeMap<String, usize>,
+}
+
+impl Assembly {
+    // One `<address> <name>` line per label (ordered by address)
+    pub fn symbol_map(&self) -> String {
+        let mut symbols: Vec<(&String, &usize)> = self.symbols.iter().collect();
+        symbols.sort_by_key(|&(name, address)| (*address, name.clone()));
+
+        let mut out = String::new();
+        for (name, address) in symbols {
+            let _ = writeln!(out, "0x{:04X} {}", address, name);
+        }
+
+        out
+    }
+}
+
+// Assemble Octo source into a ROM for the given mode
+pub fn assemble(source: &str, mode: Mode) -> Result<Assembly> {
+    let mut assembler = Assembler::new(tokenize(source), mode);
+    assembler.run()?;
+    assembler.finish()
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+// Split source into whitespace separated tokens; `#` starts a comment
+//  Braces and parentheses are always tokens on their own.
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let code = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+
+        let mut current = String::new();
+        for ch in code.chars() {
+            if ch.is_whitespace() || "{}()".contains(ch) {
+                if !current.is_empty() {
+                    tokens.push(Token {
+                        text: current.clone(),
+                        line: line_number,
+                    });
+
+                    current.clear();
+                }
+
+                if !ch.is_whitespace() {
+                    tokens.push(Token {
+                        text: ch.to_string(),
+                        line: line_number,
+                    });
+                }
+            } else {
+                current.push(ch);
+            }
+        }
+
+        if !current.is_empty() {
+            tokens.push(Token {
+                text: current,
+                line: line_number,
+            });
+        }
+    }
+
+    tokens
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = if text.starts_with('-') {
+        (true, &text[1..])
+    } else {
+        (false, text)
+    };
+
+    let value = if digits.starts_with("0x") || digits.starts_with("0X") {
+        i64::from_str_radix(&digits[2..], 16).ok()
+    } else if digits.starts_with("0b") || digits.starts_with("0B") {
+        i64::from_str_radix(&digits[2..], 2).ok()
+    } else if !digits.is_empty() && digits.chars().all(|ch| ch.is_digit(10)) {
+        digits.parse().ok()
+    } else {
+        None
+    };
+
+    value.map(|value| if negative { -value } else { value })
+}
+
+fn parse_register(text: &str) -> Option<u8> {
+    if text.len() == 2 && (text.starts_with('v') || text.starts_with('V')) {
+        u8::from_str_radix(&text[1..], 16).ok()
+    } else {
+        None
+    }
+}
+
+// How an unresolved (forward) reference is patched once its label is defined
+#[derive(Clone, Copy)]
+enum Fixup {
+    // Lower 12 bits of an instruction
+    U12,
+
+    // Full 16-bit value (XO-CHIP `i := long`)
+    U16,
+
+    // `:unpack` (lower nibble of the first byte at +1, low byte at +3)
+    Unpack,
+}
+
+// Comparison of an `if` / `while` condition
+#[derive(Clone, Copy, PartialEq)]
+enum Compare {
+    Equal,
+    NotEqual,
+    Key,
+    NotKey,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+}
+
+impl Compare {
+    fn negate(self) -> Self {
+        match self {
+            Compare::Equal => Compare::NotEqual,
+            Compare::NotEqual => Compare::Equal,
+            Compare::Key => Compare::NotKey,
+            Compare::NotKey => Compare::Key,
+            Compare::Less => Compare::GreaterEqual,
+            Compare::GreaterEqual => Compare::Less,
+            Compare::Greater => Compare::LessEqual,
+            Compare::LessEqual => Compare::Greater,
+        }
+    }
+}
+
+// Right-hand side of a condition
+#[derive(Clone, Copy)]
+enum Operand {
+    Register(u8),
+    Byte(u8),
+    None,
+}
+
+#[derive(Clone, Copy)]
+struct Condition {
+    x: u8,
+    compare: Compare,
+    operand: Operand,
+}
+
+struct Macro {
+    args: Vec<String>,
+    body: Vec<Token>,
+}
+
+// Open structured control flow construct
+enum Block {
+    // `if ... begin`; address of the jump to patch at `else` / `end`
+    If { jump: usize },
+
+    // `else`; address of the jump (over the else block) to patch at `end`
+    Else { jump: usize },
+
+    // `loop`; address of the loop head and jumps (from `while`) to patch at `again`
+    Loop { head: usize, breaks: Vec<usize> },
+}
+
+struct Assembler {
+    mode: Mode,
+
+    tokens: Vec<Token>,
+    position: usize,
+
+    // Line of the most recently consumed token (for errors)
+    line: usize,
+
+    origin: usize,
+    here: usize,
+    rom: Vec<u8>,
+
+    labels: HashMap<String, usize>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+
+    // Unresolved references: (address, kind, label, line)
+    fixups: Vec<(usize, Fixup, String, usize)>,
+
+    blocks: Vec<Block>,
+}
+
+impl Assembler {
+    fn new(tokens: Vec<Token>, mode: Mode) -> Self {
+        let origin = mode.entry();
+
+        Assembler {
+            mode: mode,
+            tokens: tokens,
+            position: 0,
+            line: 1,
+            origin: origin,
+            here: origin,
+            rom: Vec::new(),
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    fn error<T, S: Into<String>>(&self, message: S) -> Result<T> {
+        Err(Error {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    // Tokens
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(|token| &*token.text)
+    }
+
+    fn next(&mut self) -> Result<String> {
+        match self.tokens.get(self.position).cloned() {
+            Some(token) => {
+                self.position += 1;
+                self.line = token.line;
+
+                Ok(token.text)
+            }
+
+            None => self.error("unexpected end of input"),
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<()> {
+        let token = self.next()?;
+        if token != text {
+            return self.error(format!("expected `{}`, found `{}`", text, token));
+        }
+
+        Ok(())
+    }
+
+    // Tokens up to the matching `}` (the opening `{` must be next)
+    fn braced(&mut self) -> Result<Vec<Token>> {
+        self.expect("{")?;
+
+        let mut depth = 1;
+        let mut body = Vec::new();
+
+        loop {
+            let token = match self.tokens.get(self.position).cloned() {
+                Some(token) => token,
+                None => return self.error("unterminated `{`"),
+            };
+
+            self.position += 1;
+            self.line = token.line;
+
+            if token.text == "{" {
+                depth += 1;
+            } else if token.text == "}" {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(body);
+                }
+            }
+
+            body.push(token);
+        }
+    }
+
+    // Output
+
+    fn emit(&mut self, byte: u8) -> Result<()> {
+        if self.here < self.origin {
+            return self.error(format!("address ${:X} is before the start of the ROM", self.here));
+        }
+
+        let offset = self.here - self.origin;
+        if offset >= self.rom.len() {
+            self.rom.resize(offset + 1, 0);
+        }
+
+        self.rom[offset] = byte;
+        self.here += 1;
+
+        Ok(())
+    }
+
+    fn emit_op(&mut self, op: u16) -> Result<()> {
+        self.emit((op >> 8) as u8)?;
+        self.emit(op as u8)
+    }
+
+    // Point the jump at `address` to `target` (which must fit in 12 bits)
+    fn patch_jump(&mut self, address: usize, target: usize) -> Result<()> {
+        if target > 0xFFF {
+            return self.error(format!("address ${:X} does not fit in 12 bits", target));
+        }
+
+        self.patch(address, Fixup::U12, target);
+
+        Ok(())
+    }
+
+    fn patch(&mut self, address: usize, fixup: Fixup, value: usize) {
+        let offset = address - self.origin;
+
+        match fixup {
+            Fixup::U12 => {
+                self.rom[offset] = (self.rom[offset] & 0xF0) | (((value >> 8) & 0xF) as u8);
+                self.rom[offset + 1] = value as u8;
+            }
+
+            Fixup::U16 => {
+                self.rom[offset] = (value >> 8) as u8;
+                self.rom[offset + 1] = value as u8;
+            }
+
+            Fixup::Unpack => {
+                self.rom[offset + 1] |= ((value >> 8) & 0xF) as u8;
+                self.rom[offset + 3] = value as u8;
+            }
+        }
+    }
+
+    // Values
+
+    fn register(&mut self) -> Result<u8> {
+        let token = self.next()?;
+        match self.as_register(&token) {
+            Some(x) => Ok(x),
+            None => self.error(format!("expected a register, found `{}`", token)),
+        }
+    }
+
+    fn as_register(&self, token: &str) -> Option<u8> {
+        parse_register(token).or_else(|| self.aliases.get(token).cloned())
+    }
+
+    // Resolve a value that must be known now
+    fn value(&mut self, token: &str) -> Result<i64> {
+        if let Some(value) = parse_number(token) {
+            return Ok(value);
+        }
+
+        if let Some(&value) = self.constants.get(token) {
+            return Ok(value.floor() as i64);
+        }
+
+        if let Some(&address) = self.labels.get(token) {
+            return Ok(address as i64);
+        }
+
+        self.error(format!("undefined name `{}`", token))
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let token = self.next()?;
+        let value = self.value(&token)?;
+
+        if value < -128 || value > 255 {
+            return self.error(format!("value {} does not fit in a byte", value));
+        }
+
+        Ok(value as u8)
+    }
+
+    fn nibble(&mut self) -> Result<u8> {
+        let token = self.next()?;
+        let value = self.value(&token)?;
+
+        if value < 0 || value > 15 {
+            return self.error(format!("value {} does not fit in a nibble", value));
+        }
+
+        Ok(value as u8)
+    }
+
+    // Emit `op | address`; forward references are patched when the label is defined
+    fn emit_address(&mut self, op: u16, token: &str) -> Result<()> {
+        let address = self.here;
+
+        match self.resolve_address(token, address, Fixup::U12)? {
+            Some(value) if value > 0xFFF => {
+                self.error(format!("address ${:X} does not fit in 12 bits", value))
+            }
+
+            Some(value) => self.emit_op(op | (value as u16)),
+            None => self.emit_op(op),
+        }
+    }
+
+    // Returns `None` (and records a fixup at `address`) for forward references
+    fn resolve_address(&mut self,
+                       token: &str,
+                       address: usize,
+                       fixup: Fixup)
+                       -> Result<Option<usize>> {
+        if parse_number(token).is_some() || self.constants.contains_key(token) ||
+           self.labels.contains_key(token) {
+            let value = self.value(token)?;
+            if value < 0 {
+                return self.error(format!("invalid address {}", value));
+            }
+
+            return Ok(Some(value as usize));
+        }
+
+        if self.as_register(token).is_some() || token.starts_with(':') {
+            return self.error(format!("expected an address, found `{}`", token));
+        }
+
+        self.fixups.push((address, fixup, token.to_string(), self.line));
+
+        Ok(None)
+    }
+
+    fn require(&self, super_chip: bool, instruction: &str) -> Result<()> {
+        let supported = if super_chip {
+            self.mode == Mode::SuperChip || self.mode == Mode::XoChip
+        } else {
+            self.mode == Mode::XoChip
+        };
+
+        if !supported {
+            return self.error(format!("`{}` is not supported in {:?} mode",
+                                      instruction,
+                                      self.mode));
+        }
+
+        Ok(())
+    }
+
+    // Statements
+
+    fn run(&mut self) -> Result<()> {
+        while self.position < self.tokens.len() {
+            self.statement()?;
+        }
+
+        if !self.blocks.is_empty() {
+            return self.error("unterminated `begin` or `loop`");
+        }
+
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<()> {
+        let token = self.next()?;
+
+        // Raw byte
+        if let Some(value) = parse_number(&token) {
+            if value < -128 || value > 255 {
+                return self.error(format!("value {} does not fit in a byte", value));
+            }
+
+            return self.emit(value as u8);
+        }
+
+        if let Some(x) = self.as_register(&token) {
+            return self.assignment(x);
+        }
+
+        if self.macros.contains_key(&token) {
+            return self.expand(&token);
+        }
+
+        match &*token {
+            ":" => {
+                let name = self.next()?;
+                self.define_label(name)
+            }
+
+            ":const" => {
+                let name = self.next()?;
+                let token = self.next()?;
+                let value = self.value(&token)?;
+                self.constants.insert(name, value as f64);
+
+                Ok(())
+            }
+
+            ":alias" => {
+                let name = self.next()?;
+                let x = self.register()?;
+                self.aliases.insert(name, x);
+
+                Ok(())
+            }
+
+            ":calc" => {
+                let name = self.next()?;
+                let body = self.braced()?;
+                let value = self.calc(&body)?;
+                self.constants.insert(name, value);
+
+                Ok(())
+            }
+
+            ":byte" => {
+                if self.peek() == Some("{") {
+                    let body = self.braced()?;
+                    let value = self.calc(&body)?;
+
+                    self.emit(value.floor() as i64 as u8)
+                } else {
+                    let value = self.byte()?;
+                    self.emit(value)
+                }
+            }
+
+            ":org" => {
+                let token = self.next()?;
+                let value = self.value(&token)?;
+                if value < self.origin as i64 {
+                    return self.error(format!("`:org` ${:X} is before the start of the ROM",
+                                              value));
+                }
+
+                if value > self.mode.memory_size() as i64 {
+                    return self.error(format!("`:org` ${:X} is past the end of memory", value));
+                }
+
+                self.here = value as usize;
+
+                Ok(())
+            }
+
+            ":macro" => self.define_macro(),
+
+            ":call" => {
+                let token = self.next()?;
+                self.emit_address(0x2000, &token)
+            }
+
+            ":unpack" => {
+                // v0 := (nibble << 4) | address[11..8]; v1 := address[7..0]
+                let nibble = self.nibble()?;
+                let token = self.next()?;
+                let address = self.here;
+
+                let value = self.resolve_address(&token, address, Fixup::Unpack)?.unwrap_or(0);
+
+                self.emit_op(0x6000 | ((nibble as u16) << 4) | (((value >> 8) & 0xF) as u16))?;
+                self.emit_op(0x6100 | ((value & 0xFF) as u16))
+            }
+
+            ":breakpoint" => {
+                // Breakpoints are set through the debugger
+                self.next()?;
+
+                Ok(())
+            }
+
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+
+                Ok(())
+            }
+
+            ":assert" => {
+                // Optional message; then an expression that must be non-zero
+                if self.peek() != Some("{") {
+                    self.next()?;
+                }
+
+                let body = self.braced()?;
+                if self.calc(&body)? == 0.0 {
+                    return self.error("assertion failed");
+                }
+
+                Ok(())
+            }
+
+            "return" | ";" => self.emit_op(0x00EE),
+            "clear" => self.emit_op(0x00E0),
+
+            "bcd" => {
+                let x = self.register()? as u16;
+                self.emit_op(0xF033 | (x << 8))
+            }
+
+            "save" | "load" => {
+                let x = self.register()? as u16;
+
+                if self.peek() == Some("-") {
+                    // XO-CHIP: save / load vx - vy
+                    self.require(false, &token)?;
+                    self.next()?;
+
+                    let y = self.register()? as u16;
+                    let n = if token == "save" { 0x2 } else { 0x3 };
+
+                    self.emit_op(0x5000 | (x << 8) | (y << 4) | n)
+                } else {
+                    let nn = if token == "save" { 0x55 } else { 0x65 };
+                    self.emit_op(0xF000 | (x << 8) | nn)
+                }
+            }
+
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.nibble()? as u16;
+
+                self.emit_op(0xD000 | (x << 8) | (y << 4) | n)
+            }
+
+            "jump" => {
+                let token = self.next()?;
+                self.emit_address(0x1000, &token)
+            }
+
+            "jump0" => {
+                let token = self.next()?;
+                self.emit_address(0xB000, &token)
+            }
+
+            "native" => {
+                let token = self.next()?;
+                self.emit_address(0x0000, &token)
+            }
+
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+
+                let x = self.register()? as u16;
+                let nn = if token == "delay" { 0x15 } else { 0x18 };
+
+                self.emit_op(0xF000 | (x << 8) | nn)
+            }
+
+            "i" => self.index(),
+
+            "if" => self.conditional(),
+
+            "else" => {
+                match self.blocks.pop() {
+                    Some(Block::If { jump }) => {
+                        // Jump over the else block at the end of the if block
+                        let end = self.here;
+                        self.emit_op(0x1000)?;
+
+                        let here = self.here;
+                        self.patch_jump(jump, here)?;
+                        self.blocks.push(Block::Else { jump: end });
+
+                        Ok(())
+                    }
+
+                    _ => self.error("`else` without `if ... begin`"),
+                }
+            }
+
+            "end" => {
+                match self.blocks.pop() {
+                    Some(Block::If { jump }) |
+                    Some(Block::Else { jump }) => {
+                        let here = self.here;
+                        self.patch_jump(jump, here)
+                    }
+
+                    _ => self.error("`end` without `if ... begin`"),
+                }
+            }
+
+            "loop" => {
+                self.blocks.push(Block::Loop {
+                    head: self.here,
+                    breaks: Vec::new(),
+                });
+
+                Ok(())
+            }
+
+            "while" => {
+                let condition = self.condition()?;
+                self.skip_unless(condition)?;
+
+                let jump = self.here;
+                self.emit_op(0x1000)?;
+
+                // `while` may be nested inside `if ... begin` blocks within the loop
+                let index = self.blocks.iter().rposition(|block| match *block {
+                    Block::Loop { .. } => true,
+                    _ => false,
+                });
+
+                match index {
+                    Some(index) => {
+                        if let Block::Loop { ref mut breaks, .. } = self.blocks[index] {
+                            breaks.push(jump);
+                        }
+
+                        Ok(())
+                    }
+
+                    None => self.error("`while` outside of `loop`"),
+                }
+            }
+
+            "again" => {
+                match self.blocks.pop() {
+                    Some(Block::Loop { head, breaks }) => {
+                        let jump = self.here;
+                        self.emit_op(0x1000)?;
+                        self.patch_jump(jump, head)?;
+
+                        let here = self.here;
+                        for jump in breaks {
+                            self.patch_jump(jump, here)?;
+                        }
+
+                        Ok(())
+                    }
+
+                    _ => self.error("`again` without `loop`"),
+                }
+            }
+
+            // SUPER-CHIP
+            "hires" | "lores" | "scroll-right" | "scroll-left" | "exit" => {
+                self.require(true, &token)?;
+
+                self.emit_op(match &*token {
+                    "hires" => 0x00FE,
+                    "lores" => 0x00FF,
+                    "scroll-right" => 0x00FB,
+                    "scroll-left" => 0x00FC,
+                    _ => 0x00FD,
+                })
+            }
+
+            "scroll-down" => {
+                self.require(true, &token)?;
+
+                let n = self.nibble()? as u16;
+                self.emit_op(0x00C0 | n)
+            }
+
+            "saveflags" | "loadflags" => {
+                self.require(true, &token)?;
+
+                let x = self.register()? as u16;
+                let nn = if token == "saveflags" { 0x75 } else { 0x85 };
+
+                self.emit_op(0xF000 | (x << 8) | nn)
+            }
+
+            // XO-CHIP
+            "scroll-up" => {
+                self.require(false, &token)?;
+
+                let n = self.nibble()? as u16;
+                self.emit_op(0x00D0 | n)
+            }
+
+            "plane" => {
+                self.require(false, &token)?;
+
+                let n = self.nibble()? as u16;
+                self.emit_op(0xF001 | (n << 8))
+            }
+
+            "audio" => {
+                self.require(false, &token)?;
+                self.emit_op(0xF002)
+            }
+
+            "pitch" => {
+                self.require(false, &token)?;
+                self.expect(":=")?;
+
+                let x = self.register()? as u16;
+                self.emit_op(0xF03A | (x << 8))
+            }
+
+            _ if token.starts_with(':') => self.error(format!("unknown directive `{}`", token)),
+
+            // Anything else is a call to a (possibly not yet defined) label
+            _ => self.emit_address(0x2000, &token),
+        }
+    }
+
+    fn define_label(&mut self, name: String) -> Result<()> {
+        if self.labels.contains_key(&name) {
+            return self.error(format!("label `{}` is already defined", name));
+        }
+
+        let here = self.here;
+        self.labels.insert(name, here);
+
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<()> {
+        let name = self.next()?;
+
+        let mut args = Vec::new();
+        while self.peek().map_or(false, |token| token != "{") {
+            args.push(self.next()?);
+        }
+
+        let body = self.braced()?;
+        self.macros.insert(name,
+                           Macro {
+                               args: args,
+                               body: body,
+                           });
+
+        Ok(())
+    }
+
+    // Replace a macro invocation with its body (arguments substituted)
+    fn expand(&mut self, name: &str) -> Result<()> {
+        let count = self.macros[name].args.len();
+
+        let mut values = Vec::new();
+        for _ in 0..count {
+            values.push(self.next()?);
+        }
+
+        let line = self.line;
+        let expansion: Vec<Token> = {
+            let definition = &self.macros[name];
+
+            definition.body
+                .iter()
+                .map(|token| {
+                    let text = match definition.args.iter().position(|arg| *arg == token.text) {
+                        Some(index) => values[index].clone(),
+                        None => token.text.clone(),
+                    };
+
+                    Token {
+                        text: text,
+                        line: line,
+                    }
+                })
+                .collect()
+        };
+
+        let position = self.position;
+        let tail = self.tokens.split_off(position);
+        self.tokens.extend(expansion);
+        self.tokens.extend(tail);
+
+        Ok(())
+    }
+
+    // `vx := ...`, `vx += ...`, etc.
+    fn assignment(&mut self, x: u8) -> Result<()> {
+        let x16 = x as u16;
+        let operator = self.next()?;
+        let token = self.next()?;
+
+        if let Some(y) = self.as_register(&token) {
+            let n = match &*operator {
+                ":=" => 0x0,
+                "|=" => 0x1,
+                "&=" => 0x2,
+                "^=" => 0x3,
+                "+=" => 0x4,
+                "-=" => 0x5,
+                ">>=" => 0x6,
+                "=-" => 0x7,
+                "<<=" => 0xE,
+                _ => return self.error(format!("unknown operator `{}`", operator)),
+            };
+
+            return self.emit_op(0x8000 | (x16 << 8) | ((y as u16) << 4) | n);
+        }
+
+        match (&*operator, &*token) {
+            (":=", "random") => {
+                let nn = self.byte()? as u16;
+                self.emit_op(0xC000 | (x16 << 8) | nn)
+            }
+
+            (":=", "delay") => self.emit_op(0xF007 | (x16 << 8)),
+            (":=", "key") => self.emit_op(0xF00A | (x16 << 8)),
+
+            (":=", _) | ("+=", _) | ("-=", _) => {
+                let value = self.value(&token)?;
+                if value < -128 || value > 255 {
+                    return self.error(format!("value {} does not fit in a byte", value));
+                }
+
+                let nn = (value as u8) as u16;
+                match &*operator {
+                    ":=" => self.emit_op(0x6000 | (x16 << 8) | nn),
+                    "+=" => self.emit_op(0x7000 | (x16 << 8) | nn),
+                    _ => self.emit_op(0x7000 | (x16 << 8) | ((nn as u8).wrapping_neg() as u16)),
+                }
+            }
+
+            _ => self.error(format!("invalid operands for `{}`", operator)),
+        }
+    }
+
+    // `i := ...`, `i += vx`
+    fn index(&mut self) -> Result<()> {
+        let operator = self.next()?;
+        let token = self.next()?;
+
+        match (&*operator, &*token) {
+            ("+=", _) => {
+                match self.as_register(&token) {
+                    Some(x) => self.emit_op(0xF01E | ((x as u16) << 8)),
+                    None => self.error("expected a register"),
+                }
+            }
+
+            (":=", "hex") => {
+                let x = self.register()? as u16;
+                self.emit_op(0xF029 | (x << 8))
+            }
+
+            (":=", "bighex") => {
+                self.require(true, "bighex")?;
+
+                let x = self.register()? as u16;
+                self.emit_op(0xF030 | (x << 8))
+            }
+
+            (":=", "long") => {
+                self.require(false, "long")?;
+
+                let token = self.next()?;
+                self.emit_op(0xF000)?;
+
+                let address = self.here;
+                let value = self.resolve_address(&token, address, Fixup::U16)?;
+                match value {
+                    Some(value) if value > 0xFFFF => {
+                        self.error(format!("address ${:X} does not fit in 16 bits", value))
+                    }
+
+                    value => self.emit_op(value.unwrap_or(0) as u16),
+                }
+            }
+
+            (":=", _) => self.emit_address(0xA000, &token),
+
+            _ => self.error(format!("unknown operator `{}`", operator)),
+        }
+    }
+
+    // Conditions
+
+    fn condition(&mut self) -> Result<Condition> {
+        let x = self.register()?;
+        let operator = self.next()?;
+
+        let compare = match &*operator {
+            "==" => Compare::Equal,
+            "!=" => Compare::NotEqual,
+            "key" => Compare::Key,
+            "-key" => Compare::NotKey,
+            "<" => Compare::Less,
+            ">" => Compare::Greater,
+            "<=" => Compare::LessEqual,
+            ">=" => Compare::GreaterEqual,
+            _ => return self.error(format!("unknown comparison `{}`", operator)),
+        };
+
+        let operand = if compare == Compare::Key || compare == Compare::NotKey {
+            Operand::None
+        } else {
+            let token = self.next()?;
+            match self.as_register(&token) {
+                Some(y) => Operand::Register(y),
+                None => {
+                    let value = self.value(&token)?;
+                    if value < -128 || value > 255 {
+                        return self.error(format!("value {} does not fit in a byte", value));
+                    }
+
+                    Operand::Byte(value as u8)
+                }
+            }
+        };
+
+        Ok(Condition {
+            x: x,
+            compare: compare,
+            operand: operand,
+        })
+    }
+
+    // Emit code that skips the next instruction unless `condition` holds
+    fn skip_unless(&mut self, condition: Condition) -> Result<()> {
+        let x = condition.x as u16;
+
+        match (condition.compare, condition.operand) {
+            (Compare::Equal, Operand::Byte(nn)) => self.emit_op(0x4000 | (x << 8) | nn as u16),
+            (Compare::NotEqual, Operand::Byte(nn)) => self.emit_op(0x3000 | (x << 8) | nn as u16),
+            (Compare::Equal, Operand::Register(y)) => {
+                self.emit_op(0x9000 | (x << 8) | ((y as u16) << 4))
+            }
+            (Compare::NotEqual, Operand::Register(y)) => {
+                self.emit_op(0x5000 | (x << 8) | ((y as u16) << 4))
+            }
+            (Compare::Key, _) => self.emit_op(0xE0A1 | (x << 8)),
+            (Compare::NotKey, _) => self.emit_op(0xE09E | (x << 8)),
+
+            (compare, operand) => {
+                // Ordered comparisons are done through VF:
+                //  VF := y; VF := Vx - VF (for < and >=) or VF := VF - Vx (for > and <=)
+                //  VF is then the "no borrow" flag
+                match operand {
+                    Operand::Register(y) => self.emit_op(0x8F00 | ((y as u16) << 4))?,
+                    Operand::Byte(nn) => self.emit_op(0x6F00 | nn as u16)?,
+                    Operand::None => return self.error("missing operand"),
+                }
+
+                match compare {
+                    Compare::Less | Compare::GreaterEqual => self.emit_op(0x8F07 | (x << 4))?,
+                    _ => self.emit_op(0x8F05 | (x << 4))?,
+                }
+
+                match compare {
+                    Compare::Less | Compare::Greater => self.emit_op(0x4F00),
+                    _ => self.emit_op(0x4F01),
+                }
+            }
+        }
+    }
+
+    // `if <condition> then <statement>` or `if <condition> begin ... [else ...] end`
+    fn conditional(&mut self) -> Result<()> {
+        let condition = self.condition()?;
+
+        match &*self.next()? {
+            "then" => self.skip_unless(condition),
+
+            "begin" => {
+                let negated = Condition { compare: condition.compare.negate(), ..condition };
+                self.skip_unless(negated)?;
+
+                let jump = self.here;
+                self.emit_op(0x1000)?;
+                self.blocks.push(Block::If { jump: jump });
+
+                Ok(())
+            }
+
+            token => self.error(format!("expected `then` or `begin`, found `{}`", token)),
+        }
+    }
+
+    // Compile-time expressions (`:calc`, `:byte { }`, `:assert`)
+    //  As in Octo, binary operators have no precedence and are evaluated right to left.
+    fn calc(&mut self, tokens: &[Token]) -> Result<f64> {
+        let mut position = 0;
+        let value = self.calc_expression(tokens, &mut position)?;
+
+        if position != tokens.len() {
+            return self.error(format!("unexpected `{}` in expression", tokens[position].text));
+        }
+
+        Ok(value)
+    }
+
+    fn calc_expression(&mut self, tokens: &[Token], position: &mut usize) -> Result<f64> {
+        let left = self.calc_term(tokens, position)?;
+
+        let operator = match tokens.get(*position) {
+            Some(token) if token.text != ")" => token.text.clone(),
+            _ => return Ok(left),
+        };
+
+        *position += 1;
+        let right = self.calc_expression(tokens, position)?;
+
+        Ok(match &*operator {
+            "+" => left + right,
+            "-" => left - right,
+            "*" => left * right,
+            "/" => left / right,
+            "%" => left % right,
+            "&" => ((left as i64) & (right as i64)) as f64,
+            "|" => ((left as i64) | (right as i64)) as f64,
+            "^" => ((left as i64) ^ (right as i64)) as f64,
+            "<<" => ((left as i64) << (right as i64)) as f64,
+            ">>" => ((left as i64) >> (right as i64)) as f64,
+            "pow" => left.powf(right),
+            "min" => left.min(right),
+            "max" => left.max(right),
+            "<" => (left < right) as i64 as f64,
+            ">" => (left > right) as i64 as f64,
+            "<=" => (left <= right) as i64 as f64,
+            ">=" => (left >= right) as i64 as f64,
+            "==" => (left == right) as i64 as f64,
+            "!=" => (left != right) as i64 as f64,
+            _ => return self.error(format!("unknown operator `{}` in expression", operator)),
+        })
+    }
+
+    fn calc_term(&mut self, tokens: &[Token], position: &mut usize) -> Result<f64> {
+        let token = match tokens.get(*position) {
+            Some(token) => token.text.clone(),
+            None => return self.error("unexpected end of expression"),
+        };
+
+        *position += 1;
+
+        if token == "(" {
+            let value = self.calc_expression(tokens, position)?;
+            if tokens.get(*position).map(|token| &*token.text) != Some(")") {
+                return self.error("expected `)` in expression");
+            }
+
+            *position += 1;
+
+            return Ok(value);
+        }
+
+        let unary: Option<fn(f64) -> f64> = match &*token {
+            "-" => Some(|value| -value),
+            "~" => Some(|value| !(value as i64) as f64),
+            "!" => Some(|value| (value == 0.0) as i64 as f64),
+            "sin" => Some(f64::sin),
+            "cos" => Some(f64::cos),
+            "tan" => Some(f64::tan),
+            "exp" => Some(f64::exp),
+            "log" => Some(f64::ln),
+            "abs" => Some(f64::abs),
+            "sqrt" => Some(f64::sqrt),
+            "sign" => Some(f64::signum),
+            "ceil" => Some(f64::ceil),
+            "floor" => Some(f64::floor),
+            _ => None,
+        };
+
+        if let Some(unary) = unary {
+            let value = self.calc_term(tokens, position)?;
+            return Ok(unary(value));
+        }
+
+        if token == "@" {
+            // Byte already assembled at an address
+            let address = self.calc_term(tokens, position)? as usize;
+            let value = address.checked_sub(self.origin)
+                .and_then(|offset| self.rom.get(offset).cloned())
+                .unwrap_or(0);
+
+            return Ok(value as f64);
+        }
+
+        match &*token {
+            "HERE" => Ok(self.here as f64),
+            "PI" => Ok(::std::f64::consts::PI),
+            "E" => Ok(::std::f64::consts::E),
+            _ => {
+                if let Some(&value) = self.constants.get(&token) {
+                    return Ok(value);
+                }
+
+                if let Some(x) = self.as_register(&token) {
+                    // Registers evaluate to their index
+                    return Ok(x as f64);
+                }
+
+                self.value(&token).map(|value| value as f64)
+            }
+        }
+    }
+
+    // Resolve forward references
+    fn finish(mut self) -> Result<Assembly> {
+        let fixups = ::std::mem::replace(&mut self.fixups, Vec::new());
+
+        for (address, fixup, name, line) in fixups {
+            let value = match self.labels.get(&name) {
+                Some(&value) => value,
+                None => {
+                    return Err(Error {
+                        line: line,
+                        message: format!("undefined name `{}`", name),
+                    })
+                }
+            };
+
+            if let Fixup::U12 = fixup {
+                if value > 0xFFF {
+                    return Err(Error {
+                        line: line,
+                        message: format!("address ${:X} does not fit in 12 bits", value),
+                    });
+                }
+            }
+
+            self.patch(address, fixup, value);
+        }
+
+        Ok(Assembly {
+            origin: self.origin,
+            rom: self.rom,
+            symbols: self.labels.into_iter().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use interpreter::Mode;
+    use test_host::{self, FakeHost};
+    use super::{assemble, Error};
+
+    fn rom(source: &str) -> Vec<u8> {
+        assemble(source, Mode::Chip8).unwrap().rom
+    }
+
+    fn error(source: &str, mode: Mode) -> Error {
+        match assemble(source, mode) {
+            Ok(_) => panic!("expected an error"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn if_then() {
+        // SNE V1, 2; LD V0, 1
+        assert_eq!(rom("if v1 == 2 then v0 := 1"), [0x41, 0x02, 0x60, 0x01]);
+        assert_eq!(rom("if v1 != v2 then v0 := 1"), [0x51, 0x20, 0x60, 0x01]);
+    }
+
+    #[test]
+    fn if_else() {
+        let source = "if v1 == 2 begin
+                          v0 := 1
+                      else
+                          v0 := 2
+                      end";
+
+        // $200 SE V1, 2; $202 JP $208; $204 LD V0, 1; $206 JP $20A; $208 LD V0, 2
+        assert_eq!(rom(source),
+                   [0x31, 0x02, 0x12, 0x08, 0x60, 0x01, 0x12, 0x0A, 0x60, 0x02]);
+    }
+
+    #[test]
+    fn loop_while() {
+        let source = "loop
+                          v0 += 1
+                          while v0 != 5
+                          v1 += 1
+                      again";
+
+        // $200 ADD V0, 1; $202 SE V0, 5; $204 JP $20A; $206 ADD V1, 1; $208 JP $200
+        assert_eq!(rom(source),
+                   [0x70, 0x01, 0x30, 0x05, 0x12, 0x0A, 0x71, 0x01, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn ordered_comparisons() {
+        // VF holds the result of the comparison (through `VF := VF - Vx` or `Vx - VF`)
+        //  so check the semantics by running each comparison.
+        for &(a, b) in &[(1u8, 2u8), (2, 2), (3, 2), (0, 255), (255, 0)] {
+            for &(compare, expect) in &[("<", a < b), (">", a > b), ("<=", a <= b),
+                                        (">=", a >= b)] {
+                for operand in &["v2", &format!("{}", b)] {
+                    let source = format!("v1 := {} v2 := {} v3 := 0
+                                          if v1 {} {} then v3 := 1",
+                                         a,
+                                         b,
+                                         compare,
+                                         operand);
+
+                    let rom = rom(&source);
+                    let mut interpreter = test_host::interpreter(Mode::Chip8, &rom, None);
+                    let mut host = FakeHost::default();
+                    for _ in 0..rom.len() / 2 {
+                        interpreter.run_next(&mut host);
+                    }
+
+                    assert_eq!(interpreter.registers().v[3] == 1,
+                               expect,
+                               "{} {} {}",
+                               a,
+                               compare,
+                               operand);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn macros() {
+        let source = ":macro twice register amount {
+                          register += amount
+                          register += amount
+                      }
+                      twice v3 2
+                      twice v4 5";
+
+        assert_eq!(rom(source),
+                   [0x73, 0x02, 0x73, 0x02, 0x74, 0x05, 0x74, 0x05]);
+    }
+
+    #[test]
+    fn calc() {
+        // Operators have no precedence and are evaluated right to left
+        assert_eq!(rom(":calc n { 1 + 2 * 3 } v0 := n"), [0x60, 0x07]);
+
+        // Labels and `HERE` are addresses
+        assert_eq!(rom(": start :calc n { HERE - start } v0 := n"), [0x60, 0x00]);
+        assert_eq!(rom("v0 := 1 : next :calc n { next + 2 } jump n"),
+                   [0x60, 0x01, 0x12, 0x04]);
+    }
+
+    #[test]
+    fn forward_references() {
+        let assembly = assemble("jump main
+                                 : data 0xAA 0x55
+                                 : main i := data sub
+                                 : sub return",
+                                Mode::Chip8)
+            .unwrap();
+
+        // $200 JP $204; $202 data; $204 LD I, $202; $206 CALL $208; $208 RET
+        assert_eq!(assembly.rom,
+                   [0x12, 0x04, 0xAA, 0x55, 0xA2, 0x02, 0x22, 0x08, 0x00, 0xEE]);
+        assert_eq!(assembly.symbols["main"], 0x204);
+        assert_eq!(assembly.symbol_map(), "0x0202 data\n0x0204 main\n0x0208 sub\n");
+    }
+
+    #[test]
+    fn errors() {
+        let err = error("v0 := 1\n\njump nowhere\n", Mode::Chip8);
+        assert_eq!((err.line, &*err.message), (3, "undefined name `nowhere`"));
+
+        let err = error("clear\nv0 := 300", Mode::Chip8);
+        assert_eq!(err.line, 2);
+
+        let err = error("clear\n\n\nhires", Mode::Chip8);
+        assert_eq!(err.line, 4);
+
+        let err = error("loop\nv0 += 1", Mode::Chip8);
+        assert_eq!(err.message, "unterminated `begin` or `loop`");
+
+        let err = error("clear\nagain", Mode::Chip8);
+        assert_eq!((err.line, &*err.message), (2, "`again` without `loop`"));
+    }
+
+    #[test]
+    fn org() {
+        assert_eq!(rom(":org 0x204 v0 := 1"), [0x00, 0x00, 0x00, 0x00, 0x60, 0x01]);
+
+        let err = error("clear\n:org 0x100", Mode::Chip8);
+        assert_eq!(err.line, 2);
+
+        let err = error(":org 0x1001", Mode::Chip8);
+        assert_eq!(err.message, "`:org` $1001 is past the end of memory");
+
+        // A loop past $FFF (XO-CHIP memory) cannot be jumped to
+        let err = error(":org 0x1000\nloop\nv0 += 1\nagain", Mode::XoChip);
+        assert_eq!((err.line, &*err.message), (4, "address $1000 does not fit in 12 bits"));
+    }
+}
@@ -0,0 +1,81 @@
+// Static recompiler
+//  Recompiles a ROM into a Rust module (see `xchip::recompile`). Build the module into a
+//  program and pass its `PROGRAM` to `Interpreter::set_recompiled` (with the execution
+//  set to compile) to run the ROM natively.
+
+extern crate xchip;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+use xchip::Mode;
+
+const USAGE: &'static str = "usage: xchip-recompile [options] <rom>
+
+options:
+    -m, --mode <mode>       interpreter mode (chip-8, super-chip, xo-chip, ...);
+                            determined from the file extension by default
+    -o, --output <file>     write the Rust module to <file> instead of stdout
+";
+
+fn fail(message: &str) -> ! {
+    let _ = writeln!(io::stderr(), "error: {}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn main() {
+    let mut rom_filename = None;
+    let mut mode = None;
+    let mut output = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().unwrap_or_else(|| fail(&format!("missing value for `{}`", arg)))
+        };
+
+        match &*arg {
+            "-m" | "--mode" => {
+                let name = value();
+                mode = Some(Mode::from_name(&name)
+                    .unwrap_or_else(|| fail(&format!("unknown mode `{}`", name))));
+            }
+
+            "-o" | "--output" => output = Some(value()),
+
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                process::exit(0);
+            }
+
+            _ if arg.starts_with('-') => fail(&format!("unknown option `{}`", arg)),
+            _ => rom_filename = Some(arg.clone()),
+        }
+    }
+
+    let rom_filename = rom_filename.unwrap_or_else(|| fail("no ROM given"));
+
+    let mut rom = Vec::new();
+    if let Err(err) = File::open(&rom_filename).and_then(|mut file| file.read_to_end(&mut rom)) {
+        let _ = writeln!(io::stderr(), "error: {}: {}", rom_filename, err);
+        process::exit(1);
+    }
+
+    let mode = mode.unwrap_or_else(|| Mode::from_file(&rom_filename));
+    let source = xchip::recompile(&rom, mode);
+
+    let result = match output {
+        Some(ref filename) => {
+            File::create(filename).and_then(|mut file| file.write_all(source.as_bytes()))
+        }
+
+        None => io::stdout().write_all(source.as_bytes()),
+    };
+
+    if let Err(err) = result {
+        let _ = writeln!(io::stderr(), "error: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,336 @@
+// Headless runner for automated ROM testing
+//  Loads a ROM, optionally plays a scripted key sequence, runs for a number of frames (or
+//  until the program settles into a loop), and then dumps the screen (to stdout
+//  or a file) and registers (to stderr).
+
+extern crate xchip;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+use xchip::{Clock, Execution, Interpreter, Keypad, Mode, Palette, Registers, RngAlgorithm,
+            ScreenshotFormat};
+
+const USAGE: &'static str = "usage: xchip-run [options] <rom>
+
+options:
+    -m, --mode <mode>       interpreter mode (chip-8, super-chip, xo-chip, ...);
+                            determined from the file extension by default
+    -f, --frames <n>        run at most <n> frames (default: 600)
+        --ipf <n>           instructions per frame (default: 8)
+        --exec <execution>  interpret, compile (straight-line code into blocks), or
+                            differential (compile, checked against the interpreter)
+                            (default: interpret)
+    -p, --press <f>:<k>[:<n>]
+                            press hex key <k> at frame <f> for <n> frames (default: 1)
+        --seed <n>          seed the random number generator (default: random)
+        --rng <algorithm>   random number generator: xorshift or vip (default: xorshift)
+        --palette <palette> display colors (for png dumps): classic, amber, green, lcd,
+                            or a list of hex colors (eg. 000000,FFFFFF)
+    -d, --dump <format>     screen dump format: ascii, pbm, png, or hash (default: ascii)
+    -o, --output <file>     write the screen dump to <file> instead of stdout
+";
+
+// Format of the screen dump
+#[derive(PartialEq)]
+enum Dump {
+    Ascii,
+    Pbm,
+    Png,
+    Hash,
+}
+
+// A scripted key press
+struct Press {
+    frame: usize,
+    key: u8,
+    frames: usize,
+}
+
+struct Options {
+    rom: String,
+    mode: Option<Mode>,
+    frames: usize,
+    ipf: usize,
+    execution: Execution,
+    presses: Vec<Press>,
+    seed: Option<u32>,
+    rng: RngAlgorithm,
+    palette: Option<Palette>,
+    dump: Dump,
+    output: Option<String>,
+}
+
+// Keypad driven by the key script
+#[derive(Default)]
+struct ScriptedKeypad {
+    keys: [bool; 16],
+}
+
+impl Keypad for ScriptedKeypad {
+    fn key_state(&mut self, key: u8) -> bool {
+        self.keys[(key & 0xF) as usize]
+    }
+}
+
+fn fail(message: &str) -> ! {
+    let _ = writeln!(io::stderr(), "error: {}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn parse_number(text: &str) -> usize {
+    let value = if text.starts_with("0x") {
+        usize::from_str_radix(&text[2..], 16)
+    } else {
+        text.parse()
+    };
+
+    value.unwrap_or_else(|_| fail(&format!("invalid number `{}`", text)))
+}
+
+fn parse_press(text: &str) -> Press {
+    let parts: Vec<&str> = text.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        fail(&format!("invalid key press `{}`", text));
+    }
+
+    let key = match u8::from_str_radix(parts[1], 16) {
+        Ok(key) if key <= 0xF => key,
+        _ => fail(&format!("invalid key `{}`", parts[1])),
+    };
+
+    Press {
+        frame: parse_number(parts[0]),
+        key: key,
+        frames: parts.get(2).map_or(1, |frames| parse_number(frames)),
+    }
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        rom: String::new(),
+        mode: None,
+        frames: 600,
+        ipf: 8,
+        execution: Execution::Interpret,
+        presses: Vec::new(),
+        seed: None,
+        rng: RngAlgorithm::Xorshift,
+        palette: None,
+        dump: Dump::Ascii,
+        output: None,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().unwrap_or_else(|| fail(&format!("missing value for `{}`", arg)))
+        };
+
+        match &*arg {
+            "-m" | "--mode" => {
+                let name = value();
+                options.mode = Some(Mode::from_name(&name)
+                    .unwrap_or_else(|| fail(&format!("unknown mode `{}`", name))));
+            }
+
+            "-f" | "--frames" => options.frames = parse_number(&value()),
+            "--ipf" => options.ipf = parse_number(&value()),
+
+            "--exec" => {
+                options.execution = match &*value() {
+                    "interpret" => Execution::Interpret,
+                    "compile" => Execution::Compile,
+                    "differential" => Execution::Differential,
+                    other => fail(&format!("unknown execution `{}`", other)),
+                };
+            }
+
+            "-p" | "--press" => options.presses.push(parse_press(&value())),
+            "--seed" => options.seed = Some(parse_number(&value()) as u32),
+
+            "--rng" => {
+                options.rng = match &*value() {
+                    "xorshift" => RngAlgorithm::Xorshift,
+                    "vip" => RngAlgorithm::Vip,
+                    other => fail(&format!("unknown random number generator `{}`", other)),
+                };
+            }
+
+            "--palette" => {
+                let text = value();
+                options.palette = Some(Palette::parse(&text)
+                    .unwrap_or_else(|| fail(&format!("invalid palette `{}`", text))));
+            }
+
+            "-d" | "--dump" => {
+                options.dump = match &*value() {
+                    "ascii" => Dump::Ascii,
+                    "pbm" => Dump::Pbm,
+                    "png" => Dump::Png,
+                    "hash" => Dump::Hash,
+                    other => fail(&format!("unknown dump format `{}`", other)),
+                };
+            }
+
+            "-o" | "--output" => options.output = Some(value()),
+
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                process::exit(0);
+            }
+
+            _ if arg.starts_with('-') => fail(&format!("unknown option `{}`", arg)),
+            _ => options.rom = arg.clone(),
+        }
+    }
+
+    if options.rom.is_empty() {
+        fail("no ROM given");
+    }
+
+    options
+}
+
+fn dump_screen(out: &mut Write, dump: &Dump, interpreter: &mut Interpreter) -> io::Result<()> {
+    let (screen, width, height) = interpreter.screen();
+
+    match *dump {
+        Dump::Ascii => {
+            for row in screen.chunks(width) {
+                let line: String = row.iter().map(|&dot| if dot { '#' } else { '.' }).collect();
+                writeln!(out, "{}", line)?;
+            }
+
+            Ok(())
+        }
+
+        Dump::Pbm => {
+            // Plain (ASCII) PBM; 1 is black (an "on" dot)
+            writeln!(out, "P1\n{} {}", width, height)?;
+            for row in screen.chunks(width) {
+                let line: Vec<&str> = row.iter().map(|&dot| if dot { "1" } else { "0" }).collect();
+                writeln!(out, "{}", line.join(" "))?;
+            }
+
+            Ok(())
+        }
+
+        Dump::Png => interpreter.screenshot(out, ScreenshotFormat::Png, 1),
+
+        Dump::Hash => {
+            // 64-bit FNV-1a of the dimensions and dots
+            let mut hash = 0xcbf29ce484222325u64;
+            let dimensions = [width as u8,
+                              (width >> 8) as u8,
+                              height as u8,
+                              (height >> 8) as u8];
+            let dots = screen.iter().map(|&dot| dot as u8);
+
+            for b in dimensions.iter().cloned().chain(dots) {
+                hash ^= b as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+
+            writeln!(out, "{:016x}", hash)
+        }
+    }
+}
+
+// Registers, memory, and screen (used to detect when the program has settled)
+//  The RNG state is left out; `--rng vip` advances it on every tick, even while the program
+//  spins in place.
+fn machine_state(interpreter: &Interpreter) -> (Registers, Vec<u8>, Vec<bool>) {
+    let memory = (0..interpreter.memory_size()).map(|a| interpreter.read_memory(a)).collect();
+    let (screen, _, _) = interpreter.screen();
+
+    (interpreter.registers(), memory, screen)
+}
+
+fn main() {
+    let options = parse_args();
+
+    let mut rom = Vec::new();
+    if let Err(err) = File::open(&options.rom).and_then(|mut file| file.read_to_end(&mut rom)) {
+        let _ = writeln!(io::stderr(), "error: {}: {}", options.rom, err);
+        process::exit(1);
+    }
+
+    let mode = options.mode.unwrap_or_else(|| Mode::from_file(&options.rom));
+
+    let mut interpreter: Interpreter = Default::default();
+    interpreter.set_clock(Clock::External);
+    interpreter.insert_rom_bytes(&rom, mode);
+    interpreter.set_rng(options.rng);
+    interpreter.set_seed(options.seed);
+    interpreter.set_palette(options.palette);
+    interpreter.set_execution(options.execution);
+    interpreter.reset();
+
+    let mut keypad: ScriptedKeypad = Default::default();
+    let last_press = options.presses
+        .iter()
+        .map(|press| press.frame + press.frames)
+        .max()
+        .unwrap_or(0);
+
+    let mut previous = None;
+    let mut frames = 0;
+
+    while frames < options.frames {
+        for (key, state) in keypad.keys.iter_mut().enumerate() {
+            *state = options.presses.iter().any(|press| {
+                press.key as usize == key && frames >= press.frame &&
+                frames < press.frame + press.frames
+            });
+        }
+
+        interpreter.run(&mut keypad, options.ipf);
+
+        interpreter.tick();
+        frames += 1;
+
+        // Stop once the machine state no longer changes from frame to frame (eg. the
+        // program is spinning on `JP` to itself) and no more input is scripted
+        let state = machine_state(&interpreter);
+        if frames >= last_press && previous.as_ref() == Some(&state) {
+            break;
+        }
+
+        previous = Some(state);
+    }
+
+    let result = match options.output {
+        Some(ref filename) => {
+            File::create(filename)
+                .and_then(|mut file| dump_screen(&mut file, &options.dump, &mut interpreter))
+        }
+
+        None => dump_screen(&mut io::stdout(), &options.dump, &mut interpreter),
+    };
+
+    if let Err(err) = result {
+        let _ = writeln!(io::stderr(), "error: {}", err);
+        process::exit(1);
+    }
+
+    let registers = interpreter.registers();
+    let v: Vec<String> = registers.v
+        .iter()
+        .enumerate()
+        .map(|(x, v)| format!("V{:X}={:02X}", x, v))
+        .collect();
+
+    // The screen dump may be on stdout; keep the registers off it
+    let _ = writeln!(io::stderr(), "frames={}", frames);
+    let _ = writeln!(io::stderr(), "{}", v.join(" "));
+    let _ = writeln!(io::stderr(),
+                     "I={:04X} PC={:04X} SP={:02X} DT={:02X} ST={:02X}",
+                     registers.i,
+                     registers.pc,
+                     registers.sp,
+                     registers.dt,
+                     registers.st);
+}
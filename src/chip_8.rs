@@ -1,15 +1,7 @@
-use std::vec::Vec;
-use opcode::Opcode;
+use opcode::Instruction;
 use mmu::Mmu;
 use interpreter::{Runtime, Context};
-use rand::random;
-use axal;
-use axal::Key;
-
-// CHIP-8 hex keyboard -> modern keyboard
-const KEYBOARD_MAP: [Key; 0x10] = [Key::X, Key::Num1, Key::Num2, Key::Num3, Key::Q, Key::W,
-                                   Key::E, Key::A, Key::S, Key::D, Key::Z, Key::C, Key::Num4,
-                                   Key::R, Key::F, Key::V];
+use keypad::Keypad;
 
 #[derive(Default)]
 pub struct Chip8 {
@@ -17,59 +9,57 @@ pub struct Chip8 {
 
 impl Runtime for Chip8 {
     fn execute(&mut self,
-               r: &mut axal::Runtime,
+               k: &mut Keypad,
                c: &mut Context,
                m: &mut Mmu,
-               opcode: Opcode)
+               instruction: Instruction)
                -> bool {
-        match opcode.unwrap() {
+        match instruction {
             // CLS
-            (0x0, 0x0, 0xE, 0x0) => {
+            Instruction::Cls => {
                 // Clear the screen
-                for dot in &mut c.screen {
-                    *dot = false;
-                }
+                c.display.clear();
             }
 
             // RET
-            (0x0, 0x0, 0xE, 0xE) => {
+            Instruction::Ret => {
                 // Return from a subroutine
                 c.pc = c.stack_pop(m) as usize;
             }
 
             // JP u12
-            (0x1, ..) => {
+            Instruction::Jp(address) => {
                 // Jump to u12
-                c.pc = opcode.extract_u12() as usize;
+                c.pc = address as usize;
             }
 
             // CALL u12
-            (0x2, ..) => {
+            Instruction::Call(address) => {
                 // Call subroutine at u12
                 let pc = c.pc;
                 c.stack_push(m, pc as u16);
 
-                c.pc = opcode.extract_u12() as usize;
+                c.pc = address as usize;
             }
 
             // SE Vx, u8
-            (0x3, x, ..) => {
+            Instruction::SeImm(x, byte) => {
                 // Skip next instruction if Vx == u8
-                if c.v[x as usize] == opcode.extract_u8() {
+                if c.v[x as usize] == byte {
                     c.pc = c.pc.wrapping_add(2);
                 }
             }
 
             // SNE Vx, u8
-            (0x4, x, ..) => {
+            Instruction::SneImm(x, byte) => {
                 // Skip next instruction if Vx != u8
-                if c.v[x as usize] != opcode.extract_u8() {
+                if c.v[x as usize] != byte {
                     c.pc = c.pc.wrapping_add(2);
                 }
             }
 
             // SE Vx, Vy
-            (0x5, x, y, _) => {
+            Instruction::Se(x, y) => {
                 // Skip next instruction if Vx == Vy
                 if c.v[x as usize] == c.v[y as usize] {
                     c.pc = c.pc.wrapping_add(2);
@@ -77,43 +67,43 @@ impl Runtime for Chip8 {
             }
 
             // LD Vx, u8
-            (0x6, x, ..) => {
+            Instruction::LdImm(x, byte) => {
                 // Set Vx = u8
-                c.v[x as usize] = opcode.extract_u8();
+                c.v[x as usize] = byte;
             }
 
             // ADD Vx, u8
-            (0x7, x, ..) => {
+            Instruction::AddImm(x, byte) => {
                 // Set Vx = Vx + u8
-                c.v[x as usize] = c.v[x as usize].wrapping_add(opcode.extract_u8());
+                c.v[x as usize] = c.v[x as usize].wrapping_add(byte);
             }
 
             // LD Vx, Vy
-            (0x8, x, y, 0x0) => {
+            Instruction::Ld(x, y) => {
                 // Set Vx = Vy
                 c.v[x as usize] = c.v[y as usize];
             }
 
             // OR Vx, Vy
-            (0x8, x, y, 0x1) => {
+            Instruction::Or(x, y) => {
                 // Set Vx = Vx OR Vy
                 c.v[x as usize] |= c.v[y as usize];
             }
 
             // AND Vx, Vy
-            (0x8, x, y, 0x2) => {
+            Instruction::And(x, y) => {
                 // Set Vx = Vx AND Vy
                 c.v[x as usize] &= c.v[y as usize];
             }
 
             // XOR Vx, Vy
-            (0x8, x, y, 0x3) => {
+            Instruction::Xor(x, y) => {
                 // Set Vx = Vx XOR Vy
                 c.v[x as usize] ^= c.v[y as usize];
             }
 
             // ADD Vx, Vy
-            (0x8, x, y, 0x4) => {
+            Instruction::Add(x, y) => {
                 // Set Vx = Vx + Vy; Set VF = <carry>
                 let vx = c.v[x as usize] as u16;
                 let vy = c.v[y as usize] as u16;
@@ -124,41 +114,48 @@ impl Runtime for Chip8 {
             }
 
             // SUB Vx, Vy
-            (0x8, x, y, 0x5) => {
+            Instruction::Sub(x, y) => {
                 // Set Vx = Vx - Vy; Set VF = !<borrow>
+                // NOTE: VF is written last so the flag wins when X is F
                 let vx = c.v[x as usize];
                 let vy = c.v[y as usize];
 
-                c.v[0xF] = (!(vy > vx)) as u8;
                 c.v[x as usize] = vx.wrapping_sub(vy);
+                c.v[0xF] = (vy <= vx) as u8;
             }
 
-            // SHR Vx
-            (0x8, x, _, 0x6) => {
-                // Set Vx = Vx SHR 1; Set VF = Vx BIT 1
-                c.v[0xF] = c.v[x as usize] & 1;
-                c.v[x as usize] >>= 1;
+            // SHR Vx, Vy
+            Instruction::Shr(x, y) => {
+                // Set Vx = Vy SHR 1; Set VF = Vy BIT 0
+                //  With the shift quirk, Vx is shifted in place (Vy is ignored).
+                let r = c.v[if c.quirks.shift_vx { x } else { y } as usize];
+
+                c.v[x as usize] = r >> 1;
+                c.v[0xF] = r & 1;
             }
 
             // SUBN Vx, Vy
-            (0x8, x, y, 0x7) => {
+            Instruction::Subn(x, y) => {
                 // Set Vx = Vy - Vx; Set VF = !<borrow>
                 let vx = c.v[x as usize];
                 let vy = c.v[y as usize];
 
-                c.v[0xF] = (!(vx > vy)) as u8;
                 c.v[x as usize] = vy.wrapping_sub(vx);
+                c.v[0xF] = (vx <= vy) as u8;
             }
 
             // SHL Vx, Vy
-            (0x8, x, y, 0xE) => {
+            Instruction::Shl(x, y) => {
                 // Set Vx = Vy SHL 1; Set VF = Vy BIT 7
-                c.v[0xF] = c.v[y as usize] >> 7;
-                c.v[x as usize] = c.v[y as usize] << 1;
+                //  With the shift quirk, Vx is shifted in place (Vy is ignored).
+                let r = c.v[if c.quirks.shift_vx { x } else { y } as usize];
+
+                c.v[x as usize] = r << 1;
+                c.v[0xF] = r >> 7;
             }
 
             // SNE Vx, Vy
-            (0x9, x, y, 0) => {
+            Instruction::Sne(x, y) => {
                 // Skip next instruction if Vx != Vy
                 if c.v[x as usize] != c.v[y as usize] {
                     c.pc = c.pc.wrapping_add(2);
@@ -166,25 +163,28 @@ impl Runtime for Chip8 {
             }
 
             // LD I, u12
-            (0xA, ..) => {
+            Instruction::LdI(address) => {
                 // Set I = u12
-                c.i = opcode.extract_u12() as usize;
+                c.i = address as usize;
             }
 
             // JP V0, u12
-            (0xB, ..) => {
+            Instruction::JpV0(x, address) => {
                 // Jump to u12 + V0
-                c.pc = (opcode.extract_u12().wrapping_add(c.v[0] as u16)) as usize;
+                //  With the jump quirk, this is `JP Vx, u12` (jump to u12 + Vx).
+                let v = c.v[if c.quirks.jump_vx { x as usize } else { 0 }];
+
+                c.pc = (address.wrapping_add(v as u16)) as usize;
             }
 
             // RND Vx, u8
-            (0xC, x, ..) => {
+            Instruction::Rnd(x, byte) => {
                 // Set Vx = <random u8> AND u8
-                c.v[x as usize] = random::<u8>() & opcode.extract_u8();
+                c.v[x as usize] = c.rng.next_u8() & byte;
             }
 
             // SHOW Vx, Vy, u4
-            (0xD, x, y, n) => {
+            Instruction::Show(x, y, n) => {
                 // Display n-byte sprite starting in memory at I at (Vx, Vy)
                 // Set VF = <collision>
 
@@ -195,83 +195,73 @@ impl Runtime for Chip8 {
                 c.v[0xF] = 0;
 
                 for i in 0..(n as usize) {
-                    let sy = (y + i) % c.screen_height;
-
-                    for j in 0..8 {
-                        let sx = (x + j) % c.screen_width;
-
-                        // Get VRAM offset
-                        let offset = sy * c.screen_width + sx;
-
-                        // Get _current_ dot in the screen
-                        let dot = &mut c.screen[offset];
-                        let was_set = *dot;
+                    // XOR the row of the sprite (from memory) onto the screen
+                    let row = m.read(c.i + i) as u128;
 
-                        // Read memory to get the _set_ value
-                        let dot_set = (m.read(c.i + i) >> (7 - j)) & 1;
-
-                        // XOR to determine the new state of the dot
-                        *dot = ((*dot as u8) ^ dot_set) != 0;
-
-                        // VF is set to indicate the transition 1 -> 0
-                        c.v[0xF] |= (was_set && !*dot) as u8;
+                    // VF is set to indicate the transition 1 -> 0
+                    if c.display.xor(0, x, y + i, row, 8) {
+                        c.v[0xF] = 1;
                     }
                 }
             }
 
             // SKP Vx
-            (0xE, x, 0x9, 0xE) => {
+            Instruction::Skp(x) => {
                 // Skip next instruction if key with the value of Vx is pressed
-                if r.input_keyboard_state(0, KEYBOARD_MAP[c.v[x as usize] as usize]) {
+                if k.key_state(c.v[x as usize]) {
                     c.pc = c.pc.wrapping_add(2);
                 }
             }
 
             // SKNP Vx
-            (0xE, x, 0xA, 0x1) => {
+            Instruction::Sknp(x) => {
                 // Skip next instruction if key with the value of Vx is not pressed
-                if !r.input_keyboard_state(0, KEYBOARD_MAP[c.v[x as usize] as usize]) {
+                if !k.key_state(c.v[x as usize]) {
                     c.pc = c.pc.wrapping_add(2);
                 }
             }
 
             // LD Vx, DT
-            (0xF, x, 0x0, 0x7) => {
+            Instruction::LdVxDt(x) => {
                 // Set Vx = DT
                 c.v[x as usize] = c.dt;
             }
 
             // LD DT, Vx
-            (0xF, x, 0x1, 0x5) => {
+            Instruction::LdDtVx(x) => {
                 // Set DT = Vx
                 c.dt = c.v[x as usize];
             }
 
             // LD ST, Vx
-            (0xF, x, 0x1, 0x8) => {
+            Instruction::LdStVx(x) => {
                 // Set ST = Vx
                 c.st = c.v[x as usize];
             }
 
             // ADD I, Vx
-            (0xF, x, 0x1, 0xE) => {
-                // Set I = I + Vx
+            Instruction::AddI(x) => {
+                // Set I = I + Vx (I spans all of RAM; 12 bits, or 16 on XO-CHIP)
                 let r: u32 = c.i as u32 + c.v[x as usize] as u32;
+                let mask = (m.size() - 1) as u32;
 
-                c.i = (r & 0xFFF) as usize;
+                c.i = (r & mask) as usize;
 
                 // If buffer overflow, register > VF must be set to 1, otherwise 0.
-                c.v[0xF] = (r > 0xFFF) as u8;
+                //  This is a quirk (that is enabled by default).
+                if c.quirks.add_i_vf {
+                    c.v[0xF] = (r > mask) as u8;
+                }
             }
 
             // LD [I], FONT Vx
-            (0xF, x, 0x2, 0x9) => {
+            Instruction::LdFont(x) => {
                 // Set I = location of sprite for digit Vx.
-                c.i = (c.v[x as usize] * 5) as usize;
+                c.i = ((c.v[x as usize] & 0xF) as usize) * 5;
             }
 
             // LD [I], BCD Vx
-            (0xF, x, 0x3, 0x3) => {
+            Instruction::LdBcd(x) => {
                 // Store BCD representation of Vx in memory locations I, I+1, and I+2.
                 let r = c.v[x as usize];
                 let i = c.i;
@@ -282,29 +272,35 @@ impl Runtime for Chip8 {
             }
 
             // LD [I], Vx
-            (0xF, x, 0x5, 0x5) => {
+            Instruction::Store(x) => {
                 // Store registers V0 through Vx in memory starting at location I.
+                //  I is left pointing past Vx unless the load/store quirk is enabled.
                 for j in 0..(x + 1) {
                     let r = c.v[j as usize];
 
-                    m.write(c.i, r);
+                    m.write(c.i + j as usize, r);
+                }
 
-                    c.i += 1;
+                if !c.quirks.load_store_keep_i {
+                    c.i += x as usize + 1;
                 }
             }
 
             // LD Vx, [I]
-            (0xF, x, 0x6, 0x5) => {
+            Instruction::Load(x) => {
                 // Read registers V0 through Vx from memory starting at location I.
+                //  I is left pointing past Vx unless the load/store quirk is enabled.
                 for j in 0..(x + 1) {
-                    c.v[j as usize] = m.read(c.i);
+                    c.v[j as usize] = m.read(c.i + j as usize);
+                }
 
-                    c.i += 1;
+                if !c.quirks.load_store_keep_i {
+                    c.i += x as usize + 1;
                 }
             }
 
             _ => {
-                // Unhandled operation
+                // Unhandled instruction
                 return false;
             }
         }
@@ -312,3 +308,514 @@ impl Runtime for Chip8 {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use interpreter::{Context, Interpreter, Mode, Runtime};
+    use mmu::Mmu;
+    use opcode::Instruction;
+    use quirks::Quirks;
+    use test_host::{self, FakeHost};
+    use super::Chip8;
+
+    // Every quirk flipped from its default
+    fn alternate() -> Quirks {
+        Quirks {
+            shift_vx: true,
+            load_store_keep_i: true,
+            jump_vx: true,
+            add_i_vf: false,
+        }
+    }
+
+    // Load `code` at $200, set V (from `v`) and I, and run `steps` instructions
+    fn run(quirks: Quirks,
+           v: &[(usize, u8)],
+           i: usize,
+           code: &[u8],
+           steps: usize,
+           host: &mut FakeHost)
+           -> Interpreter {
+        let mut interpreter = test_host::interpreter(Mode::Chip8, code, Some(quirks));
+
+        let mut registers = interpreter.registers();
+        for &(x, value) in v {
+            registers.v[x] = value;
+        }
+
+        registers.i = i;
+        interpreter.set_registers(registers);
+
+        for _ in 0..steps {
+            interpreter.run_next(host);
+        }
+
+        interpreter
+    }
+
+    // Run a _single_ opcode
+    fn exec(quirks: Quirks, v: &[(usize, u8)], i: usize, opcode: u16) -> Interpreter {
+        let code = [(opcode >> 8) as u8, opcode as u8];
+
+        run(quirks, v, i, &code, 1, &mut Default::default())
+    }
+
+    struct Case {
+        opcode: u16,
+
+        // V registers before
+        v: &'static [(usize, u8)],
+
+        // V registers expected after (with the default quirks)
+        expect: &'static [(usize, u8)],
+
+        // V registers expected after with the alternate quirks (if different)
+        alternate: Option<&'static [(usize, u8)]>,
+    }
+
+    const REGISTER_CASES: &'static [Case] = &[// LD Vx, u8
+                                              Case {
+                                                  opcode: 0x6A42,
+                                                  v: &[],
+                                                  expect: &[(0xA, 0x42)],
+                                                  alternate: None,
+                                              },
+                                              // ADD Vx, u8 (wraps; VF is unaffected)
+                                              Case {
+                                                  opcode: 0x7AFF,
+                                                  v: &[(0xA, 0x02), (0xF, 0x07)],
+                                                  expect: &[(0xA, 0x01), (0xF, 0x07)],
+                                                  alternate: None,
+                                              },
+                                              // LD Vx, Vy
+                                              Case {
+                                                  opcode: 0x8AB0,
+                                                  v: &[(0xB, 0x09)],
+                                                  expect: &[(0xA, 0x09), (0xB, 0x09)],
+                                                  alternate: None,
+                                              },
+                                              // OR Vx, Vy
+                                              Case {
+                                                  opcode: 0x8AB1,
+                                                  v: &[(0xA, 0x0C), (0xB, 0x0A)],
+                                                  expect: &[(0xA, 0x0E)],
+                                                  alternate: None,
+                                              },
+                                              // AND Vx, Vy
+                                              Case {
+                                                  opcode: 0x8AB2,
+                                                  v: &[(0xA, 0x0C), (0xB, 0x0A)],
+                                                  expect: &[(0xA, 0x08)],
+                                                  alternate: None,
+                                              },
+                                              // XOR Vx, Vy
+                                              Case {
+                                                  opcode: 0x8AB3,
+                                                  v: &[(0xA, 0x0C), (0xB, 0x0A)],
+                                                  expect: &[(0xA, 0x06)],
+                                                  alternate: None,
+                                              },
+                                              // ADD Vx, Vy
+                                              Case {
+                                                  opcode: 0x8AB4,
+                                                  v: &[(0xA, 0x10), (0xB, 0x20), (0xF, 0x01)],
+                                                  expect: &[(0xA, 0x30), (0xF, 0x00)],
+                                                  alternate: None,
+                                              },
+                                              Case {
+                                                  opcode: 0x8AB4,
+                                                  v: &[(0xA, 0xFF), (0xB, 0x02)],
+                                                  expect: &[(0xA, 0x01), (0xF, 0x01)],
+                                                  alternate: None,
+                                              },
+                                              Case {
+                                                  opcode: 0x8FB4,
+                                                  v: &[(0xF, 0xFF), (0xB, 0x02)],
+                                                  expect: &[(0xF, 0x01)],
+                                                  alternate: None,
+                                              },
+                                              Case {
+                                                  opcode: 0x8FB4,
+                                                  v: &[(0xF, 0x01), (0xB, 0x02)],
+                                                  expect: &[(0xF, 0x00)],
+                                                  alternate: None,
+                                              },
+                                              Case {
+                                                  opcode: 0x8AF4,
+                                                  v: &[(0xA, 0xFF), (0xF, 0x02)],
+                                                  expect: &[(0xA, 0x01), (0xF, 0x01)],
+                                                  alternate: None,
+                                              },
+                                              // SUB Vx, Vy
+                                              Case {
+                                                  opcode: 0x8AB5,
+                                                  v: &[(0xA, 0x30), (0xB, 0x10)],
+                                                  expect: &[(0xA, 0x20), (0xF, 0x01)],
+                                                  alternate: None,
+                                              },
+                                              Case {
+                                                  opcode: 0x8AB5,
+                                                  v: &[(0xA, 0x05), (0xB, 0x05)],
+                                                  expect: &[(0xA, 0x00), (0xF, 0x01)],
+                                                  alternate: None,
+                                              },
+                                              Case {
+                                                  opcode: 0x8AB5,
+                                                  v: &[(0xA, 0x10), (0xB, 0x30), (0xF, 0x01)],
+                                                  expect: &[(0xA, 0xE0), (0xF, 0x00)],
+                                                  alternate: None,
+                                              },
+                                              Case {
+                                                  opcode: 0x8FB5,
+                                                  v: &[(0xF, 0x10), (0xB, 0x30)],
+                                                  expect: &[(0xF, 0x00)],
+                                                  alternate: None,
+                                              },
+                                              Case {
+                                                  opcode: 0x8FB5,
+                                                  v: &[(0xF, 0x30), (0xB, 0x10)],
+                                                  expect: &[(0xF, 0x01)],
+                                                  alternate: None,
+                                              },
+                                              // SHR Vx, Vy
+                                              Case {
+                                                  opcode: 0x8AB6,
+                                                  v: &[(0xA, 0x81), (0xB, 0x06)],
+                                                  expect: &[(0xA, 0x03), (0xB, 0x06), (0xF, 0x00)],
+                                                  alternate: Some(&[(0xA, 0x40),
+                                                                    (0xB, 0x06),
+                                                                    (0xF, 0x01)]),
+                                              },
+                                              Case {
+                                                  opcode: 0x8FB6,
+                                                  v: &[(0xF, 0x00), (0xB, 0x01)],
+                                                  expect: &[(0xF, 0x01)],
+                                                  alternate: Some(&[(0xF, 0x00)]),
+                                              },
+                                              // SUBN Vx, Vy
+                                              Case {
+                                                  opcode: 0x8AB7,
+                                                  v: &[(0xA, 0x10), (0xB, 0x30)],
+                                                  expect: &[(0xA, 0x20), (0xF, 0x01)],
+                                                  alternate: None,
+                                              },
+                                              Case {
+                                                  opcode: 0x8AB7,
+                                                  v: &[(0xA, 0x30), (0xB, 0x10), (0xF, 0x01)],
+                                                  expect: &[(0xA, 0xE0), (0xF, 0x00)],
+                                                  alternate: None,
+                                              },
+                                              Case {
+                                                  opcode: 0x8FB7,
+                                                  v: &[(0xF, 0x30), (0xB, 0x10)],
+                                                  expect: &[(0xF, 0x00)],
+                                                  alternate: None,
+                                              },
+                                              // SHL Vx, Vy
+                                              Case {
+                                                  opcode: 0x8ABE,
+                                                  v: &[(0xA, 0x01), (0xB, 0x81)],
+                                                  expect: &[(0xA, 0x02), (0xB, 0x81), (0xF, 0x01)],
+                                                  alternate: Some(&[(0xA, 0x02),
+                                                                    (0xB, 0x81),
+                                                                    (0xF, 0x00)]),
+                                              },
+                                              Case {
+                                                  opcode: 0x8FBE,
+                                                  v: &[(0xF, 0x00), (0xB, 0x80)],
+                                                  expect: &[(0xF, 0x01)],
+                                                  alternate: Some(&[(0xF, 0x00)]),
+                                              },
+                                              // RND Vx, u8 (an empty mask is always 0)
+                                              Case {
+                                                  opcode: 0xCA00,
+                                                  v: &[(0xA, 0xFF)],
+                                                  expect: &[(0xA, 0x00)],
+                                                  alternate: None,
+                                              }];
+
+    #[test]
+    fn registers() {
+        for (quirks, is_alternate) in vec![(Quirks::default(), false), (alternate(), true)] {
+            for case in REGISTER_CASES {
+                let interpreter = exec(quirks, case.v, 0, case.opcode);
+                let v = interpreter.registers().v;
+
+                let expect = match case.alternate {
+                    Some(alternate) if is_alternate => alternate,
+                    _ => case.expect,
+                };
+
+                for &(x, value) in expect {
+                    assert_eq!(v[x],
+                               value,
+                               "${:04X} with {:?}: V{:X} = ${:02X}, expected ${:02X}",
+                               case.opcode,
+                               quirks,
+                               x,
+                               v[x],
+                               value);
+                }
+
+                // Nothing else is touched (and it is not a jump)
+                for x in 0..16 {
+                    if !expect.iter().any(|&(y, _)| x == y) {
+                        let before = case.v.iter().find(|&&(y, _)| x == y).map_or(0, |&(_, v)| v);
+                        assert_eq!(v[x], before, "${:04X}: V{:X} changed", case.opcode, x);
+                    }
+                }
+
+                assert_eq!(interpreter.registers().pc, 0x202);
+            }
+        }
+    }
+
+    #[test]
+    fn skips() {
+        // (opcode, V before, skipped)
+        let cases: &[(u16, &[(usize, u8)], bool)] = &[// SE Vx, u8
+                                                      (0x3A42, &[(0xA, 0x42)], true),
+                                                      (0x3A42, &[(0xA, 0x41)], false),
+                                                      // SNE Vx, u8
+                                                      (0x4A42, &[(0xA, 0x42)], false),
+                                                      (0x4A42, &[(0xA, 0x41)], true),
+                                                      // SE Vx, Vy
+                                                      (0x5AB0, &[(0xA, 0x07), (0xB, 0x07)], true),
+                                                      (0x5AB0, &[(0xA, 0x07), (0xB, 0x08)], false),
+                                                      // SNE Vx, Vy
+                                                      (0x9AB0, &[(0xA, 0x07), (0xB, 0x07)], false),
+                                                      (0x9AB0, &[(0xA, 0x07), (0xB, 0x08)], true)];
+
+        for &(opcode, v, skipped) in cases {
+            let interpreter = exec(Default::default(), v, 0, opcode);
+            let pc = if skipped { 0x204 } else { 0x202 };
+
+            assert_eq!(interpreter.registers().pc, pc, "${:04X} with {:?}", opcode, v);
+        }
+    }
+
+    #[test]
+    fn jumps() {
+        // JP u12
+        assert_eq!(exec(Default::default(), &[], 0, 0x1345).registers().pc, 0x345);
+
+        // CALL u12; the return address is pushed onto the stack (in RAM)
+        let interpreter = exec(Default::default(), &[], 0, 0x2345);
+        let registers = interpreter.registers();
+
+        assert_eq!(registers.pc, 0x345);
+        assert_eq!(registers.sp, 1);
+        assert_eq!((interpreter.read_memory(0x102), interpreter.read_memory(0x103)),
+                   (0x02, 0x02));
+
+        // RET
+        let code = [0x22, 0x04, 0x00, 0x00, 0x00, 0xEE];
+        let interpreter = run(Default::default(), &[], 0, &code, 2, &mut Default::default());
+        let registers = interpreter.registers();
+
+        assert_eq!((registers.pc, registers.sp), (0x202, 0));
+
+        // JP V0, u12
+        let v = [(0x0, 0x04), (0x3, 0x08)];
+        assert_eq!(exec(Default::default(), &v, 0, 0xB300).registers().pc, 0x304);
+        assert_eq!(exec(alternate(), &v, 0, 0xB300).registers().pc, 0x308);
+    }
+
+    #[test]
+    fn index() {
+        // LD I, u12
+        assert_eq!(exec(Default::default(), &[], 0, 0xA123).registers().i, 0x123);
+
+        // LD I, FONT Vx
+        assert_eq!(exec(Default::default(), &[(0x5, 0xA)], 0, 0xF529).registers().i, 0x32);
+
+        // ADD I, Vx; (I, Vx, VF before) -> (I, VF after) with default and alternate quirks
+        let cases = [((0x010, 0x20, 0x01), (0x030, 0x00), (0x030, 0x01)),
+                     ((0xFFF, 0x02, 0x00), (0x001, 0x01), (0x001, 0x00)),
+                     ((0xFF0, 0x10, 0x00), (0x000, 0x01), (0x000, 0x00))];
+
+        for &((i, v, vf), expect, alternate_expect) in &cases {
+            for &(quirks, (ei, evf)) in &[(Quirks::default(), expect),
+                                         (alternate(), alternate_expect)] {
+                let registers = exec(quirks, &[(0x3, v), (0xF, vf)], i, 0xF31E).registers();
+
+                assert_eq!((registers.i, registers.v[0xF]),
+                           (ei, evf),
+                           "I = ${:03X} + ${:02X} with {:?}",
+                           i,
+                           v,
+                           quirks);
+            }
+        }
+
+        // ADD I, Vx on XO-CHIP (16-bit I)
+        let mut interpreter = test_host::interpreter(Mode::XoChip, &[0xF3, 0x1E, 0xF3, 0x1E], None);
+        let mut registers = interpreter.registers();
+        registers.v[0x3] = 0x20;
+        registers.i = 0xFFF0;
+        interpreter.set_registers(registers);
+
+        interpreter.run_next(&mut FakeHost::default());
+        assert_eq!((interpreter.registers().i, interpreter.registers().v[0xF]), (0x0010, 0x01));
+
+        registers.i = 0x0FF0;
+        interpreter.set_registers(registers);
+
+        interpreter.run_next(&mut FakeHost::default());
+        assert_eq!((interpreter.registers().i, interpreter.registers().v[0xF]), (0x1010, 0x00));
+
+        // ADD I, VF (VF is read before it is overwritten)
+        let registers = exec(Default::default(), &[(0xF, 0x20)], 0x010, 0xFF1E).registers();
+        assert_eq!((registers.i, registers.v[0xF]), (0x030, 0x00));
+    }
+
+    #[test]
+    fn bcd() {
+        for &(value, digits) in &[(0, [0, 0, 0]),
+                                  (7, [0, 0, 7]),
+                                  (42, [0, 4, 2]),
+                                  (123, [1, 2, 3]),
+                                  (255, [2, 5, 5])] {
+            let interpreter = exec(Default::default(), &[(0x4, value)], 0x300, 0xF433);
+
+            let bcd = [interpreter.read_memory(0x300),
+                       interpreter.read_memory(0x301),
+                       interpreter.read_memory(0x302)];
+
+            assert_eq!(bcd, digits, "BCD of {}", value);
+
+            // I is unchanged
+            assert_eq!(interpreter.registers().i, 0x300);
+        }
+    }
+
+    #[test]
+    fn load_store() {
+        let v = [(0x0, 0x10), (0x1, 0x11), (0x2, 0x12), (0x3, 0x13), (0x4, 0x14)];
+
+        for &(quirks, i) in &[(Quirks::default(), 0x304), (alternate(), 0x300)] {
+            // LD [I], Vx
+            let interpreter = exec(quirks, &v, 0x300, 0xF355);
+            let stored: Vec<u8> = (0x300..0x305).map(|a| interpreter.read_memory(a)).collect();
+
+            assert_eq!(stored, [0x10, 0x11, 0x12, 0x13, 0x00]);
+            assert_eq!(interpreter.registers().i, i);
+
+            // LD Vx, [I]
+            let code = [0xF3, 0x65, 0x00, 0x00, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E];
+            let interpreter = run(quirks, &v, 0x204, &code, 1, &mut Default::default());
+            let registers = interpreter.registers();
+
+            assert_eq!(registers.v[..5], [0x0A, 0x0B, 0x0C, 0x0D, 0x14]);
+            assert_eq!(registers.i, i - 0x300 + 0x204);
+        }
+    }
+
+    #[test]
+    fn timers_and_audio() {
+        // LD V0, 3; LD ST, V0; LD DT, V0; LD V1, DT; JP $208
+        let code = [0x60, 0x03, 0xF0, 0x18, 0xF0, 0x15, 0xF1, 0x07, 0x12, 0x08];
+        let mut host: FakeHost = Default::default();
+        let mut interpreter = test_host::interpreter(Mode::Chip8, &code, None);
+
+        for _ in 0..5 {
+            host.run_frame(&mut interpreter, 8);
+        }
+
+        let registers = interpreter.registers();
+        assert_eq!((registers.v[1], registers.dt, registers.st), (3, 0, 0));
+
+        // The buzzer sounds for 3 frames (ST is clocked at the end of each)
+        assert_eq!(host.audio, [true, true, false, false, false]);
+    }
+
+    #[test]
+    fn display() {
+        // CLS; LD I, FONT V0 ("0"); SHOW V1, V2, 5; SHOW V1, V2, 5; JP $208
+        let code = [0x00, 0xE0, 0xF0, 0x29, 0xD1, 0x25, 0xD1, 0x25, 0x12, 0x08];
+        let mut host: FakeHost = Default::default();
+
+        // Drawn wrapped around the bottom right corner
+        let v = [(0x1, 62), (0x2, 30)];
+        let mut interpreter = run(Default::default(), &v, 0, &code, 3, &mut host);
+
+        {
+            let (screen, width, height) = interpreter.screen();
+            let dot = |x: usize, y: usize| screen[y * width + x];
+
+            assert_eq!((width, height), (64, 32));
+
+            // Top row of "0" (0xF0) at y = 30
+            assert!(dot(62, 30) && dot(63, 30) && dot(0, 30) && dot(1, 30) && !dot(2, 30));
+
+            // Third row of "0" (0x90) wraps to y = 0
+            assert!(dot(62, 0) && !dot(63, 0) && !dot(0, 0) && dot(1, 0));
+
+            assert_eq!(screen.iter().filter(|&&dot| dot).count(), 14);
+        }
+
+        assert_eq!(interpreter.registers().v[0xF], 0);
+
+        // Drawing the same sprite again erases it and reports the collision
+        host.run_frame(&mut interpreter, 1);
+        assert_eq!(interpreter.registers().v[0xF], 1);
+
+        let (ref framebuffer, width, height) = host.frames[0];
+        assert_eq!((width, height), (64, 32));
+        assert!(framebuffer.iter().all(|&b| b == 0));
+
+        // CLS
+        let code = [0xD1, 0x25, 0x00, 0xE0];
+        let interpreter = run(Default::default(), &[], 0, &code, 1, &mut host);
+        assert!(interpreter.screen().0.iter().any(|&dot| dot));
+
+        let interpreter = run(Default::default(), &[], 0, &code, 2, &mut host);
+        assert!(interpreter.screen().0.iter().all(|&dot| !dot));
+    }
+
+    #[test]
+    fn keys() {
+        // (opcode, key pressed, skipped)
+        let cases = [(0xE59E, false, false),
+                     (0xE59E, true, true),
+                     (0xE5A1, false, true),
+                     (0xE5A1, true, false)];
+
+        for &(opcode, pressed, skipped) in &cases {
+            let mut host: FakeHost = Default::default();
+            if pressed {
+                host.press(0x7);
+            }
+
+            let code = [(opcode >> 8) as u8, opcode as u8];
+            let interpreter = run(Default::default(), &[(0x5, 0x7)], 0, &code, 1, &mut host);
+            let pc = if skipped { 0x204 } else { 0x202 };
+
+            assert_eq!(interpreter.registers().pc, pc, "${:04X}", opcode);
+            assert_eq!(host.polled, [0x7]);
+        }
+    }
+
+    #[test]
+    fn unhandled() {
+        let mut chip_8: Chip8 = Default::default();
+        let mut host: FakeHost = Default::default();
+        let mut c: Context = Default::default();
+        let mut m: Mmu = Default::default();
+
+        for &(hi, lo) in &[(0x01, 0x23), (0x5A, 0xB1), (0x8A, 0xB8), (0xE5, 0x00), (0xF0, 0xFF)] {
+            let instruction = Instruction::decode(Mode::Chip8, &[hi, lo]).unwrap();
+
+            assert!(!chip_8.execute(&mut host, &mut c, &mut m, instruction),
+                    "${:02X}{:02X}",
+                    hi,
+                    lo);
+        }
+
+        // The interpreter pauses at an unhandled opcode
+        let interpreter = exec(Default::default(), &[], 0, 0x0123);
+
+        assert!(interpreter.is_paused());
+        assert_eq!(interpreter.registers().pc, 0x200);
+    }
+}
@@ -1,120 +1,145 @@
 use std::vec::Vec;
 
 use chip_8;
-use opcode::Opcode;
+use opcode::Instruction;
 use mmu::Mmu;
-use interpreter::{Runtime, Context};
-use axal;
+use interpreter::{Mode, Runtime, Context};
+use pixel;
+use state;
+use keypad::Keypad;
+
+// Background colors, in the order `02A0` steps through them (blue, black, green, red)
+const BACKGROUNDS: [u8; 4] = [2, 0, 4, 1];
 
 #[derive(Default)]
 pub struct Chip8x {
     // CHIP-8X starts from the CHIP-8
     chip_8: chip_8::Chip8,
 
-    // Maps CHIP-8X color index to a R3_G3_B2 color
-    // TODO: Make this configurable
-    palette: Vec<u8>,
-
-    // CHIP-8X defines a background color (color when dots are off)
+    // CHIP-8X defines a background color (color index when dots are off)
     background_color: u8,
 
     // CHIP-8X defines a color "lens" that defines
-    // the colors of dots from the screen
+    // the colors (indices) of dots from the screen
     color_lens: Vec<u8>,
 }
 
-impl Runtime for Chip8x {
-    fn configure(&mut self, c: &mut Context) {
-        // Initialize palette
-        self.palette = vec![// Black
-                            0b000_000_00,
-
-                            // Red
-                            0b111_000_00,
-
-                            // Blue
-                            0b000_000_11,
-
-                            // Violet
-                            0b111_000_11,
-
-                            // Green
-                            0b000_111_00,
-
-                            // Yellow
-                            0b111_111_00,
-
-                            // Aqua
-                            0b000_111_11,
+impl Chip8x {
+    // Set the color of `rows` rows (from `top`) of the 8-dot zone `zone_x`
+    //  The zones are those of the 64x32 screen; rows past the bottom wrap around.
+    fn color_zone(&mut self, c: &mut Context, zone_x: usize, top: usize, rows: usize, color: u8) {
+        let width = c.display.width();
+        let height = c.display.height();
+
+        for y in top..(top + rows) {
+            let y = y % height;
+            for x in (zone_x * 8)..(zone_x * 8 + 8) {
+                self.color_lens[y * width + x] = color;
+            }
 
-                            // White
-                            0b111_111_11];
+            c.display.mark_dirty(y, y + 1);
+        }
+    }
+}
 
-        // Initialize the color lens
-        self.color_lens.resize(c.screen.len(), self.palette[7]);
+impl Runtime for Chip8x {
+    fn configure(&mut self, c: &mut Context) {
+        // Initialize the color lens (white)
+        self.color_lens.resize(c.display.width() * c.display.height(), 7);
     }
 
     fn reset(&mut self, c: &mut Context) {
         // Reset CHIP-8
         self.chip_8.reset(c);
 
-        // Reset background color
-        self.background_color = self.palette[0];
+        // Reset background color (black)
+        self.background_color = 0;
 
-        // Clear the color lens
+        // Clear the color lens (white)
         for c in &mut self.color_lens {
-            *c = self.palette[7];
+            *c = 7;
         }
 
         // Set PC to $300
         c.pc = 0x300;
     }
 
+    fn save_state(&self, w: &mut state::Writer) {
+        w.write_u8(self.background_color);
+        w.write_bytes(&self.color_lens);
+    }
+
+    fn load_state(&mut self, r: &mut state::Reader) {
+        self.background_color = r.read_u8();
+        self.color_lens = r.read_bytes().to_vec();
+    }
+
+    fn check_state(&self, c: &Context) -> bool {
+        // The color lens covers the display
+        self.color_lens.len() == c.display.width() * c.display.height()
+    }
+
     fn insert_rom(&mut self, m: &mut Mmu, buffer: &[u8]) {
         m.write_all(0x300, buffer);
     }
 
     fn update_framebuffer(&mut self, c: &mut Context) {
-        c.framebuffer.resize(c.screen.len(), 0);
-
-        for y in 0..c.screen_height {
-            let offset_y = y * c.screen_width;
-
-            for x in 0..c.screen_width {
-                let offset = offset_y + x;
+        let display = &c.display;
+        let width = display.width();
+        let row_len = width * c.pixel_format.bytes_per_pixel();
 
-                // Get dot from screen
-                let dot = c.screen[offset];
+        for y in 0..display.height() {
+            if !display.is_dirty(y) {
+                continue;
+            }
 
-                // Blit to framebuffer
-                c.framebuffer[offset] = if dot {
-                    self.color_lens[offset]
+            // Blit row onto framebuffer (through the color lens)
+            let indices = (0..width).map(|x| {
+                let color = if display.dot(x, y) {
+                    self.color_lens[y * width + x]
                 } else {
                     self.background_color
                 };
-            }
+
+                (color & 7) as usize
+            });
+
+            pixel::render(c.pixel_format,
+                          &mut c.framebuffer[y * row_len..(y + 1) * row_len],
+                          c.palette.colors(Mode::Chip8x),
+                          indices);
         }
     }
 
     fn execute(&mut self,
-               r: &mut axal::Runtime,
+               k: &mut Keypad,
                c: &mut Context,
                m: &mut Mmu,
-               opcode: Opcode)
+               instruction: Instruction)
                -> bool {
-        match opcode.unwrap() {
-            (0x0, 0x2, 0xA, 0x0) => {
-                println!("unimplemented: {}", opcode);
+        match instruction {
+            Instruction::BgStep => {
+                // Step the background color
+                let step = BACKGROUNDS.iter()
+                    .position(|&color| color == self.background_color)
+                    .unwrap_or(0);
+
+                self.background_color = BACKGROUNDS[(step + 1) % BACKGROUNDS.len()];
+                c.display.mark_all_dirty();
             }
 
-            (0x5, x, y, 0x1) => {
-                println!("unimplemented: {}", opcode);
+            Instruction::AddNibbles(x, y) => {
+                // Set Vx = Vx + Vy, adding each nibble separately (modulo 8, as colors)
+                let vx = c.v[x as usize] & 0x77;
+                let vy = c.v[y as usize] & 0x77;
+
+                c.v[x as usize] = (vx + vy) & 0x77;
             }
 
-            (0xB, x, y, 0x0) => {
+            Instruction::Col(x, y) => {
                 // Set foreground color of 1 or more 8x4 dot zones
                 let vx = c.v[x as usize];
-                let vx1 = c.v[(x + 1) as usize];
+                let vx1 = c.v[((x + 1) & 0xF) as usize];
                 let color = c.v[y as usize] & 0b1111;
 
                 // The lower 4 bits of `VX`/`V[X+1]` is the horizontal/vertical zone index (0-7).
@@ -122,23 +147,134 @@ impl Runtime for Chip8x {
                 let vert = (vx1 & 0b1111) as usize;
 
                 // The upper 4 bits of `VX`/`V[X+1]` is the horizontal/vertical size minus 1.
-                let width = (vx >> 4) + 1;
-                let height = (vx1 >> 4) + 1;
-
-                // TODO: Set color on the color lens
+                let width = (vx >> 4) as usize + 1;
+                let height = (vx1 >> 4) as usize + 1;
+
+                for zone_y in vert..(vert + height) {
+                    for zone_x in horz..(horz + width) {
+                        self.color_zone(c, zone_x % 8, (zone_y % 8) * 4, 4, color);
+                    }
+                }
             }
 
-            (0xB, x, y, n) => {
+            Instruction::ColRows(x, y, n) => {
                 // Set foreground color of 1 or more 8x1 dot zones
-                println!("unimplemented: {}", opcode);
+                //  `VX` is a dot column within the zone; `V[X+1]` is the first row.
+                let vx = c.v[x as usize] as usize;
+                let vx1 = c.v[((x + 1) & 0xF) as usize] as usize;
+                let color = c.v[y as usize] & 0b1111;
+
+                self.color_zone(c, (vx / 8) % 8, vx1 % 32, n as usize, color);
             }
 
             _ => {
                 // Unhandled by CHIP-8X
-                return self.chip_8.execute(r, c, m, opcode);
+                return self.chip_8.execute(k, c, m, instruction);
             }
         }
 
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use interpreter::{Interpreter, Mode};
+    use pixel::Region;
+    use test_host::{self, FakeHost};
+
+    // Color index (in the CHIP-8X palette) of the framebuffer pixel at (x, y)
+    fn color(interpreter: &mut Interpreter, x: usize, y: usize) -> usize {
+        let format = interpreter.pixel_format();
+        let colors: Vec<u32> = interpreter.palette()
+            .colors(Mode::Chip8x)
+            .iter()
+            .map(|&color| format.decode(&format.encode(color)))
+            .collect();
+
+        let (framebuffer, width, _) = interpreter.screen_as_framebuffer();
+        let offset = (y * width + x) * format.bytes_per_pixel();
+        let pixel = format.decode(&framebuffer[offset..offset + format.bytes_per_pixel()]);
+
+        colors.iter().position(|&color| color == pixel).unwrap()
+    }
+
+    fn run(interpreter: &mut Interpreter, steps: usize) {
+        for _ in 0..steps {
+            interpreter.run_next(&mut FakeHost::default());
+        }
+    }
+
+    #[test]
+    fn color_lens() {
+        // (at $300) LD V0, 0; LD V1, 0; LD V2, 2; COL V0, V2 (zone (0, 0) blue);
+        // LD V0, $18; LD V2, 1; COL V0, V2, 2 (rows 0-1 of the zone of x = $18 red);
+        // LD V5, $F; LD F, V5; DRW V0, V1, 5 (at ($18, 0)); LD V0, 0; DRW V0, V1, 5
+        let rom = [0x60, 0x00, 0x61, 0x00, 0x62, 0x02, 0xB0, 0x20, 0x60, 0x18, 0x62, 0x01,
+                   0xB0, 0x22, 0x65, 0x0F, 0xF5, 0x29, 0xD0, 0x15, 0x60, 0x00, 0xD0, 0x15];
+        let mut interpreter = test_host::interpreter(Mode::Chip8x, &rom, None);
+        run(&mut interpreter, 12);
+
+        // "F" is drawn from row 0 to 4; the lens is white outside the colored zones
+        assert_eq!(color(&mut interpreter, 0, 0), 2);
+        assert_eq!(color(&mut interpreter, 0, 3), 2);
+        assert_eq!(color(&mut interpreter, 0, 4), 7);
+        assert_eq!(color(&mut interpreter, 0x18, 1), 1);
+        assert_eq!(color(&mut interpreter, 0x18, 2), 7);
+
+        // Dots that are off show the background (black)
+        assert_eq!(color(&mut interpreter, 5, 0), 0);
+    }
+
+    #[test]
+    fn color_rows() {
+        // (at $300) LD V0, $38; LD V1, 4; LD V2, 4; COL V0, V2, 2 (rows 4-5 green);
+        // LD V1, 30; COL V0, V2, 4 (rows 30, 31, 0, and 1); LD I, $316; DRW V0, V1, 2;
+        // LD V1, 0; DRW V0, V1, 3; JP $314; (at $316) DB $FF, $FF, $FF
+        let rom = [0x60, 0x38, 0x61, 0x04, 0x62, 0x04, 0xB0, 0x22, 0x61, 0x1E, 0xB0, 0x24,
+                   0xA3, 0x16, 0xD0, 0x12, 0x61, 0x00, 0xD0, 0x13, 0x13, 0x14, 0xFF, 0xFF,
+                   0xFF];
+        let mut interpreter = test_host::interpreter(Mode::Chip8x, &rom, None);
+        run(&mut interpreter, 3);
+        interpreter.refresh_framebuffer();
+
+        // Only the colored rows are redrawn
+        run(&mut interpreter, 1);
+        assert_eq!(interpreter.refresh_framebuffer(),
+                   Some(Region {
+                       x: 0,
+                       y: 4,
+                       width: 64,
+                       height: 2,
+                   }));
+
+        // Rows past the bottom wrap around to the top
+        run(&mut interpreter, 6);
+        assert_eq!(color(&mut interpreter, 0x38, 30), 4);
+        assert_eq!(color(&mut interpreter, 0x3F, 31), 4);
+        assert_eq!(color(&mut interpreter, 0x38, 0), 4);
+        assert_eq!(color(&mut interpreter, 0x3F, 1), 4);
+        assert_eq!(color(&mut interpreter, 0x38, 2), 7);
+    }
+
+    #[test]
+    fn background_and_nibbles() {
+        // LD V3, $17; LD V4, $37; ADD V3, V4 (nibbles); BGSTEP; BGSTEP
+        let rom = [0x63, 0x17, 0x64, 0x37, 0x53, 0x41, 0x02, 0xA0, 0x02, 0xA0];
+        let mut interpreter = test_host::interpreter(Mode::Chip8x, &rom, None);
+        run(&mut interpreter, 3);
+
+        // Each nibble wraps at 8
+        assert_eq!(interpreter.registers().v[3], 0x46);
+
+        // Black, then green, then red
+        assert_eq!(color(&mut interpreter, 0, 0), 0);
+
+        run(&mut interpreter, 1);
+        assert_eq!(interpreter.refresh_framebuffer().map(|region| region.height), Some(32));
+        assert_eq!(color(&mut interpreter, 0, 0), 4);
+
+        run(&mut interpreter, 1);
+        assert_eq!(color(&mut interpreter, 0, 0), 1);
+    }
+}
@@ -0,0 +1,553 @@
+use std::boxed::Box;
+use std::collections::BTreeMap;
+use std::vec::Vec;
+
+use interpreter::{Context, Mode};
+use mmu::{self, Mmu};
+use opcode::Instruction;
+
+// Most instructions compiled into a block
+const MAX_BLOCK_LEN: usize = 64;
+
+// How `Interpreter::run` executes instructions
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Execution {
+    // One instruction at a time
+    Interpret,
+
+    // Straight-line code is compiled into blocks (of closures) that run without
+    // decoding or dispatch; anything else (eg. `DXYN`, key checks, timer reads) is
+    // left to the interpreter
+    Compile,
+
+    // As `Compile`, but every block is also run by the interpreter from the same state;
+    // panics if the machine states differ afterwards (for testing)
+    Differential,
+}
+
+impl Default for Execution {
+    fn default() -> Self {
+        Execution::Interpret
+    }
+}
+
+// Compiled instruction
+type Run = Box<Fn(&mut Context, &mut Mmu)>;
+
+// Block of a ROM recompiled to Rust (see `recompile`)
+//  `run` runs at most `budget` (at least 1) instructions of the block and returns the
+//  number run, as a compiled block would.
+#[derive(Clone, Copy)]
+pub struct RecompiledBlock {
+    pub address: usize,
+
+    // Code the block was recompiled from; the block is only used while RAM holds it
+    pub code: &'static [u8],
+
+    pub run: fn(&mut Context, &mut Mmu, usize) -> usize,
+}
+
+// ROM recompiled to Rust; a module generated by `recompile` exports one as `PROGRAM`
+#[derive(Clone, Copy)]
+pub struct Recompiled {
+    // Mode the ROM was decoded in; the blocks are only used in this mode
+    pub mode: Mode,
+
+    pub blocks: &'static [RecompiledBlock],
+}
+
+struct Op {
+    address: usize,
+    len: usize,
+
+    // True if the instruction writes to RAM (and so may modify the block)
+    writes: bool,
+
+    run: Run,
+}
+
+// Straight-line code (ending at a branch or an instruction that is not compiled)
+struct Block {
+    ops: Vec<Op>,
+
+    // Recompiled code run instead of `ops`
+    recompiled: Option<fn(&mut Context, &mut Mmu, usize) -> usize>,
+
+    // Code version (see `Mmu::code_version`) of every page the block was decoded from
+    pages: Vec<(usize, u64)>,
+}
+
+impl Block {
+    fn is_current(&self, m: &Mmu) -> bool {
+        self.pages.iter().all(|&(address, version)| m.code_version(address) == version)
+    }
+
+    // Run at most `budget` instructions; returns the number run
+    fn run(&self, c: &mut Context, m: &mut Mmu, budget: usize) -> usize {
+        if let Some(run) = self.recompiled {
+            return run(c, m, budget);
+        }
+
+        for (n, op) in self.ops.iter().enumerate() {
+            if n == budget {
+                return n;
+            }
+
+            // PC is incremented before the instruction executes (as by the interpreter)
+            c.pc = op.address + op.len;
+            (op.run)(c, m);
+
+            // Self-modifying code: leave the block once it is stale
+            if op.writes && !self.is_current(m) {
+                return n + 1;
+            }
+        }
+
+        self.ops.len()
+    }
+}
+
+// Cache of compiled blocks by address
+#[derive(Default)]
+pub struct Compiler {
+    blocks: BTreeMap<usize, Block>,
+
+    // Recompiled blocks by address (used in place of compiling)
+    recompiled: BTreeMap<usize, RecompiledBlock>,
+    recompiled_mode: Mode,
+}
+
+impl Compiler {
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+
+    pub fn set_recompiled(&mut self, recompiled: Option<Recompiled>) {
+        self.blocks.clear();
+        self.recompiled.clear();
+
+        if let Some(recompiled) = recompiled {
+            self.recompiled_mode = recompiled.mode;
+            for block in recompiled.blocks {
+                self.recompiled.insert(block.address, *block);
+            }
+        }
+    }
+
+    // Run (at most `budget` instructions of) the block at PC; returns the number of
+    // instructions run, which is 0 if the instruction at PC must be interpreted
+    pub fn run(&mut self, c: &mut Context, m: &mut Mmu, budget: usize) -> usize {
+        let pc = c.pc;
+
+        let current = self.blocks.get(&pc).map_or(false, |block| block.is_current(m));
+        if !current {
+            let block = self.recompiled_block(m, pc).unwrap_or_else(|| compile(m, pc));
+            self.blocks.insert(pc, block);
+        }
+
+        self.blocks[&pc].run(c, m, budget)
+    }
+
+    // The recompiled block at `address`, if RAM still holds the code it was recompiled from
+    fn recompiled_block(&self, m: &mut Mmu, address: usize) -> Option<Block> {
+        let recompiled = match self.recompiled.get(&address) {
+            Some(recompiled) if m.mode() == self.recompiled_mode => recompiled,
+            _ => return None,
+        };
+
+        let end = address + recompiled.code.len();
+        if end > m.size() || !m.matches(address, recompiled.code) {
+            return None;
+        }
+
+        // Writes to the code have to change its version (as if it had been fetched)
+        m.mark_code(address, end);
+
+        let mut pages = Vec::new();
+        for page in (address / mmu::PAGE_SIZE)..((end - 1) / mmu::PAGE_SIZE + 1) {
+            pages.push((page * mmu::PAGE_SIZE, m.code_version(page * mmu::PAGE_SIZE)));
+        }
+
+        Some(Block {
+            ops: Vec::new(),
+            recompiled: Some(recompiled.run),
+            pages: pages,
+        })
+    }
+}
+
+// Compile the block at `address`
+fn compile(m: &mut Mmu, address: usize) -> Block {
+    let mut ops = Vec::new();
+    let mut pages: Vec<(usize, u64)> = Vec::new();
+    let mut address = address;
+
+    while ops.len() < MAX_BLOCK_LEN {
+        let instruction = m.fetch(address);
+        let len = instruction.len();
+
+        // Instructions that wrap around the end of RAM are left to the interpreter
+        if address + len > m.size() {
+            break;
+        }
+
+        // The block depends on every page it was decoded from (including that of the
+        // instruction it stops at)
+        for a in address..address + len {
+            let page = a - a % mmu::PAGE_SIZE;
+            if !pages.iter().any(|&(p, _)| p == page) {
+                pages.push((page, m.code_version(page)));
+            }
+        }
+
+        let (run, kind) = match compile_instruction(instruction) {
+            Some(compiled) => compiled,
+            None => break,
+        };
+
+        ops.push(Op {
+            address: address,
+            len: len,
+            writes: kind == Kind::Write,
+            run: run,
+        });
+
+        if kind == Kind::Branch {
+            break;
+        }
+
+        address += len;
+    }
+
+    Block {
+        ops: ops,
+        recompiled: None,
+        pages: pages,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    // Continues with the next instruction
+    Next,
+
+    // Writes to RAM, then continues with the next instruction
+    Write,
+
+    // Sets PC; ends the block
+    Branch,
+}
+
+// How a compiled instruction continues; `None` if the instruction is not compiled (it
+// draws, reads keys or timers, or is runtime-specific)
+pub fn kind(instruction: Instruction) -> Option<Kind> {
+    match instruction {
+        Instruction::Ret | Instruction::Jp(_) | Instruction::Call(_) |
+        Instruction::SeImm(..) | Instruction::SneImm(..) | Instruction::Se(..) |
+        Instruction::Sne(..) | Instruction::JpV0(..) => Some(Kind::Branch),
+
+        Instruction::LdBcd(_) | Instruction::Store(_) => Some(Kind::Write),
+
+        Instruction::LdImm(..) | Instruction::AddImm(..) | Instruction::Ld(..) |
+        Instruction::Or(..) | Instruction::And(..) | Instruction::Xor(..) |
+        Instruction::Add(..) | Instruction::Sub(..) | Instruction::Shr(..) |
+        Instruction::Subn(..) | Instruction::Shl(..) | Instruction::Rnd(..) |
+        Instruction::LdDtVx(_) | Instruction::LdStVx(_) | Instruction::LdI(_) |
+        Instruction::AddI(_) | Instruction::LdFont(_) | Instruction::Load(_) => Some(Kind::Next),
+
+        _ => None,
+    }
+}
+
+// Compile an instruction (with the semantics of the CHIP-8 runtime); `None` if it has to
+// be interpreted (it draws, reads keys or timers, or is runtime-specific)
+fn compile_instruction(instruction: Instruction) -> Option<(Run, Kind)> {
+    let (x, y) = match instruction {
+        Instruction::Se(x, y) | Instruction::Sne(x, y) | Instruction::Ld(x, y) |
+        Instruction::Or(x, y) | Instruction::And(x, y) | Instruction::Xor(x, y) |
+        Instruction::Add(x, y) | Instruction::Sub(x, y) | Instruction::Shr(x, y) |
+        Instruction::Subn(x, y) | Instruction::Shl(x, y) => (x as usize, y as usize),
+
+        Instruction::SeImm(x, _) | Instruction::SneImm(x, _) | Instruction::LdImm(x, _) |
+        Instruction::AddImm(x, _) | Instruction::JpV0(x, _) | Instruction::Rnd(x, _) |
+        Instruction::LdDtVx(x) | Instruction::LdStVx(x) | Instruction::AddI(x) |
+        Instruction::LdFont(x) | Instruction::LdBcd(x) | Instruction::Store(x) |
+        Instruction::Load(x) => (x as usize, 0),
+
+        _ => (0, 0),
+    };
+
+    let kind = match kind(instruction) {
+        Some(kind) => kind,
+        None => return None,
+    };
+
+    let run: Run = match instruction {
+        // Branches
+        Instruction::Ret => Box::new(|c, m| c.pc = c.stack_pop(m) as usize),
+        Instruction::Jp(address) => Box::new(move |c, _| c.pc = address as usize),
+        Instruction::Call(address) => {
+            Box::new(move |c, m| {
+                let pc = c.pc;
+                c.stack_push(m, pc as u16);
+
+                c.pc = address as usize;
+            })
+        }
+        Instruction::SeImm(_, byte) => {
+            Box::new(move |c, _| if c.v[x] == byte {
+                c.pc = c.pc.wrapping_add(2);
+            })
+        }
+        Instruction::SneImm(_, byte) => {
+            Box::new(move |c, _| if c.v[x] != byte {
+                c.pc = c.pc.wrapping_add(2);
+            })
+        }
+        Instruction::Se(..) => {
+            Box::new(move |c, _| if c.v[x] == c.v[y] {
+                c.pc = c.pc.wrapping_add(2);
+            })
+        }
+        Instruction::Sne(..) => {
+            Box::new(move |c, _| if c.v[x] != c.v[y] {
+                c.pc = c.pc.wrapping_add(2);
+            })
+        }
+        Instruction::JpV0(_, address) => {
+            Box::new(move |c, _| {
+                let v = c.v[if c.quirks.jump_vx { x } else { 0 }];
+
+                c.pc = (address.wrapping_add(v as u16)) as usize;
+            })
+        }
+
+        // Registers
+        Instruction::LdImm(_, byte) => Box::new(move |c, _| c.v[x] = byte),
+        Instruction::AddImm(_, byte) => {
+            Box::new(move |c, _| c.v[x] = c.v[x].wrapping_add(byte))
+        }
+        Instruction::Ld(..) => Box::new(move |c, _| c.v[x] = c.v[y]),
+        Instruction::Or(..) => Box::new(move |c, _| c.v[x] |= c.v[y]),
+        Instruction::And(..) => Box::new(move |c, _| c.v[x] &= c.v[y]),
+        Instruction::Xor(..) => Box::new(move |c, _| c.v[x] ^= c.v[y]),
+        Instruction::Add(..) => {
+            Box::new(move |c, _| {
+                let r = c.v[x] as u16 + c.v[y] as u16;
+
+                c.v[x] = r as u8;
+                c.v[0xF] = (r > 0xFF) as u8;
+            })
+        }
+        Instruction::Sub(..) => {
+            Box::new(move |c, _| {
+                let (vx, vy) = (c.v[x], c.v[y]);
+
+                c.v[x] = vx.wrapping_sub(vy);
+                c.v[0xF] = (vy <= vx) as u8;
+            })
+        }
+        Instruction::Shr(..) => {
+            Box::new(move |c, _| {
+                let r = c.v[if c.quirks.shift_vx { x } else { y }];
+
+                c.v[x] = r >> 1;
+                c.v[0xF] = r & 1;
+            })
+        }
+        Instruction::Subn(..) => {
+            Box::new(move |c, _| {
+                let (vx, vy) = (c.v[x], c.v[y]);
+
+                c.v[x] = vy.wrapping_sub(vx);
+                c.v[0xF] = (vx <= vy) as u8;
+            })
+        }
+        Instruction::Shl(..) => {
+            Box::new(move |c, _| {
+                let r = c.v[if c.quirks.shift_vx { x } else { y }];
+
+                c.v[x] = r << 1;
+                c.v[0xF] = r >> 7;
+            })
+        }
+        Instruction::Rnd(_, byte) => Box::new(move |c, _| c.v[x] = c.rng.next_u8() & byte),
+        Instruction::LdDtVx(_) => Box::new(move |c, _| c.dt = c.v[x]),
+        Instruction::LdStVx(_) => Box::new(move |c, _| c.st = c.v[x]),
+
+        // Index and memory
+        Instruction::LdI(address) => Box::new(move |c, _| c.i = address as usize),
+        Instruction::AddI(_) => {
+            Box::new(move |c, m| {
+                let r = c.i as u32 + c.v[x] as u32;
+                let mask = (m.size() - 1) as u32;
+
+                c.i = (r & mask) as usize;
+
+                if c.quirks.add_i_vf {
+                    c.v[0xF] = (r > mask) as u8;
+                }
+            })
+        }
+        Instruction::LdFont(_) => Box::new(move |c, _| c.i = ((c.v[x] & 0xF) as usize) * 5),
+        Instruction::LdBcd(_) => {
+            Box::new(move |c, m| {
+                let (r, i) = (c.v[x], c.i);
+
+                m.write(i, r / 100);
+                m.write(i + 1, (r % 100) / 10);
+                m.write(i + 2, r % 10);
+            })
+        }
+        Instruction::Store(_) => {
+            Box::new(move |c, m| {
+                for j in 0..(x + 1) {
+                    m.write(c.i + j, c.v[j]);
+                }
+
+                if !c.quirks.load_store_keep_i {
+                    c.i += x + 1;
+                }
+            })
+        }
+        Instruction::Load(_) => {
+            Box::new(move |c, m| {
+                for j in 0..(x + 1) {
+                    c.v[j] = m.read(c.i + j);
+                }
+
+                if !c.quirks.load_store_keep_i {
+                    c.i += x + 1;
+                }
+            })
+        }
+
+        _ => return None,
+    };
+
+    Some((run, kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp;
+
+    use interpreter::{Context, Interpreter, Mode};
+    use mmu::Mmu;
+    use test_host::{self, FakeHost};
+    use super::{Execution, Recompiled, RecompiledBlock};
+
+    // Run `code` for `count` instructions in every execution mode; the machine state
+    // must come out the same
+    fn check(mode: Mode, code: &[u8], count: usize) -> Interpreter {
+        let mut reference = test_host::interpreter(mode, code, None);
+        reference.set_seed(Some(7));
+        reference.reset();
+
+        for _ in 0..count {
+            reference.run_next(&mut FakeHost::default());
+        }
+
+        for &execution in &[Execution::Compile, Execution::Differential] {
+            let mut interpreter = test_host::interpreter(mode, code, None);
+            interpreter.set_seed(Some(7));
+            interpreter.reset();
+            interpreter.set_execution(execution);
+
+            // Uneven budgets end blocks part of the way through
+            let (mut left, mut budget) = (count, 0);
+            while left > 0 {
+                budget = cmp::min(budget % 5 + 1, left);
+                interpreter.run(&mut FakeHost::default(), budget);
+
+                left -= budget;
+            }
+
+            assert_eq!(interpreter.checksum(), reference.checksum(), "{:?}", execution);
+        }
+
+        reference
+    }
+
+    #[test]
+    fn straight_line() {
+        // LD V0, 200; ADD V0, 100; LD V1, V0; SHL V2, V1; RND V3, $FF; LD I, $300;
+        // LD [I], V3; CALL $214; JP $200; (at $214) ADD V4, 1; RET
+        let code = [0x60, 0xC8, 0x70, 0x64, 0x81, 0x00, 0x82, 0x1E, 0xC3, 0xFF, 0xA3, 0x00,
+                    0xF3, 0x55, 0x22, 0x14, 0x12, 0x00, 0x00, 0x00, 0x74, 0x01, 0x00, 0xEE];
+
+        let interpreter = check(Mode::Chip8, &code, 200);
+        assert!(interpreter.registers().v[4] > 0);
+    }
+
+    #[test]
+    fn add_i() {
+        // LD I, $FF0; LD V3, $20; ADD I, V3; ADD I, V3; JP $208
+        //  I spans 12 bits, but 16 on XO-CHIP.
+        let code = [0xAF, 0xF0, 0x63, 0x20, 0xF3, 0x1E, 0xF3, 0x1E, 0x12, 0x08];
+
+        let registers = check(Mode::Chip8, &code, 4).registers();
+        assert_eq!((registers.i, registers.v[0xF]), (0x030, 0x00));
+
+        let registers = check(Mode::XoChip, &code, 4).registers();
+        assert_eq!((registers.i, registers.v[0xF]), (0x1030, 0x00));
+    }
+
+    #[test]
+    fn self_modifying() {
+        // LD V0, $12; LD V1, $00; LD I, $208; LD [I], V1 (overwrites the next
+        // instruction with `JP $200`); LD V2, 1 (never runs); LD V3, 1
+        let code = [0x60, 0x12, 0x61, 0x00, 0xA2, 0x08, 0xF1, 0x55, 0x62, 0x01, 0x63, 0x01];
+
+        let interpreter = check(Mode::Chip8, &code, 20);
+        assert_eq!(interpreter.registers().v[2], 0);
+    }
+
+    #[test]
+    fn recompiled() {
+        // LD V0, 1; LD [I], V0; JP $200
+        //  The recompiled block loads 2 instead, so it is visible when it runs.
+        const CODE: &'static [u8] = &[0x60, 0x01, 0xF0, 0x55, 0x12, 0x00];
+
+        fn block(c: &mut Context, m: &mut Mmu, _: usize) -> usize {
+            c.pc = 0x202;
+            c.v[0] = 2;
+            c.pc = 0x204;
+            m.write(c.i, c.v[0]);
+            c.i += 1;
+            c.pc = 0x200;
+            3
+        }
+
+        const BLOCKS: &'static [RecompiledBlock] = &[RecompiledBlock {
+                                                          address: 0x200,
+                                                          code: CODE,
+                                                          run: block,
+                                                      }];
+
+        let mut interpreter = test_host::interpreter(Mode::Chip8, CODE, None);
+        interpreter.set_execution(Execution::Compile);
+        interpreter.set_recompiled(Some(Recompiled {
+            mode: Mode::Chip8,
+            blocks: BLOCKS,
+        }));
+
+        interpreter.run(&mut FakeHost::default(), 3);
+        assert_eq!(interpreter.registers().v[0], 2);
+
+        // Once the code is overwritten, it is compiled as usual
+        interpreter.write_memory(0x201, 0x03);
+        interpreter.run(&mut FakeHost::default(), 3);
+        assert_eq!(interpreter.registers().v[0], 3);
+
+        // Not in another mode
+        interpreter.set_recompiled(Some(Recompiled {
+            mode: Mode::SuperChip,
+            blocks: BLOCKS,
+        }));
+
+        interpreter.write_memory(0x201, 0x01);
+        interpreter.run(&mut FakeHost::default(), 3);
+        assert_eq!(interpreter.registers().v[0], 1);
+    }
+}
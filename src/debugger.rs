@@ -0,0 +1,143 @@
+use std::collections::BTreeSet;
+use std::collections::btree_set;
+
+// Snapshot of the CPU-visible registers (for inspection and editing)
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Registers {
+    pub v: [u8; 16],
+    pub i: usize,
+    pub pc: usize,
+    pub sp: u8,
+    pub dt: u8,
+    pub st: u8,
+}
+
+// Condition (other than a breakpoint) that pauses execution
+enum Target {
+    // Pause before the next instruction
+    Step,
+
+    // Pause once execution returns to `pc` at the same stack depth (step over a `CALL`)
+    Return { pc: usize, sp: u8 },
+
+    // Pause once the current subroutine returns
+    Out { sp: u8 },
+
+    // Pause when `pc` is reached
+    Cursor { pc: usize },
+}
+
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+
+    // True if execution is suspended
+    paused: bool,
+
+    // The next instruction is executed unconditionally (used to move
+    // off of the instruction we are paused on)
+    skip: bool,
+
+    target: Option<Target>,
+}
+
+impl Debugger {
+    pub fn add_breakpoint(&mut self, pc: usize) {
+        self.breakpoints.insert(pc);
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: usize) {
+        self.breakpoints.remove(&pc);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> btree_set::Iter<usize> {
+        self.breakpoints.iter()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // True if `check` has nothing to do (no breakpoints, and running freely)
+    pub fn is_idle(&self) -> bool {
+        !self.paused && !self.skip && self.target.is_none() && self.breakpoints.is_empty()
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.target = None;
+    }
+
+    // Continue execution (until the next breakpoint)
+    pub fn resume(&mut self) {
+        self.resume_to(None);
+    }
+
+    // Execute a _single_ instruction
+    pub fn step_into(&mut self) {
+        self.resume_to(Some(Target::Step));
+    }
+
+    // Execute a _single_ instruction; a `CALL` is run until it returns to `pc`
+    pub fn step_over(&mut self, pc: usize, sp: u8, is_call: bool) {
+        if is_call {
+            self.resume_to(Some(Target::Return { pc: pc + 2, sp: sp }));
+        } else {
+            self.step_into();
+        }
+    }
+
+    // Run until the current subroutine returns
+    pub fn step_out(&mut self, sp: u8) {
+        self.resume_to(Some(Target::Out { sp: sp.wrapping_sub(1) }));
+    }
+
+    // Run until `pc` is reached
+    pub fn run_to(&mut self, pc: usize) {
+        self.resume_to(Some(Target::Cursor { pc: pc }));
+    }
+
+    fn target_reached(&self, pc: usize, sp: u8) -> bool {
+        match self.target {
+            Some(Target::Step) => true,
+            Some(Target::Return { pc: target_pc, sp: target_sp }) => {
+                pc == target_pc && sp == target_sp
+            }
+            Some(Target::Out { sp: target_sp }) => sp == target_sp,
+            Some(Target::Cursor { pc: target_pc }) => pc == target_pc,
+            None => false,
+        }
+    }
+
+    fn resume_to(&mut self, target: Option<Target>) {
+        self.paused = false;
+        self.skip = true;
+        self.target = target;
+    }
+
+    // Called before each instruction; returns true (and pauses) if
+    // execution should stop before the instruction at `pc`
+    pub fn check(&mut self, pc: usize, sp: u8) -> bool {
+        if self.paused {
+            return true;
+        }
+
+        if self.skip {
+            self.skip = false;
+
+            return false;
+        }
+
+        let hit = self.breakpoints.contains(&pc) || self.target_reached(pc, sp);
+
+        if hit {
+            self.pause();
+        }
+
+        hit
+    }
+}
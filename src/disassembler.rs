@@ -0,0 +1,424 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use interpreter::Mode;
+use opcode::Instruction;
+
+// Output syntax of the disassembly
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Syntax {
+    // Address, opcode, and mnemonic (as in the comments of the runtimes)
+    Listing,
+
+    // Source that can be re-assembled with Octo
+    Octo,
+}
+
+// How an instruction affects control flow
+#[derive(Clone, Copy, PartialEq)]
+enum Flow {
+    // Continues with the next instruction
+    Next,
+
+    // Conditionally skips the next instruction
+    Skip,
+
+    // Unconditionally jumps to the target
+    Jump,
+
+    // Calls the target and then continues with the next instruction
+    Call,
+
+    // Jump to a computed address (`JP V0, u12`); cannot be followed
+    Computed,
+
+    // Does not continue (`RET`, `EXIT`)
+    Stop,
+}
+
+impl Flow {
+    fn of(instruction: Instruction) -> Self {
+        use opcode::Instruction::*;
+
+        match instruction {
+            Ret | Exit => Flow::Stop,
+            Jp(_) => Flow::Jump,
+            Call(_) => Flow::Call,
+            SeImm(..) | SneImm(..) | Se(..) | Sne(..) | Skp(_) | Sknp(_) => Flow::Skip,
+            JpV0(..) => Flow::Computed,
+            _ => Flow::Next,
+        }
+    }
+}
+
+// Address operand (always the last operand) of an instruction
+fn target(instruction: Instruction) -> Option<usize> {
+    use opcode::Instruction::*;
+
+    match instruction {
+        Jp(address) | Call(address) | LdI(address) | JpV0(_, address) | LdILong(address) => {
+            Some(address as usize)
+        }
+
+        _ => None,
+    }
+}
+
+// Format in the given syntax; the target address is replaced by `label` when given
+fn format(instruction: Instruction, syntax: Syntax, label: Option<&str>) -> String {
+    match syntax {
+        Syntax::Listing => {
+            let text = instruction.to_string();
+
+            match label {
+                Some(label) if target(instruction).is_some() => {
+                    // The address is the last operand of the mnemonic
+                    let mnemonic = text.rsplitn(2, ' ').nth(1).unwrap_or("");
+                    format!("{} {}", mnemonic, label)
+                }
+
+                _ => text,
+            }
+        }
+
+        Syntax::Octo => {
+            let target = match (target(instruction), label) {
+                (Some(_), Some(label)) => label.to_string(),
+                (Some(address), None) => format!("0x{:03X}", address),
+                (None, _) => String::new(),
+            };
+
+            octo(instruction, &target)
+        }
+    }
+}
+
+// Octo statement for an instruction (`target` is the formatted address operand)
+fn octo(instruction: Instruction, target: &str) -> String {
+    use opcode::Instruction::*;
+
+    match instruction {
+        Cls => "clear".to_string(),
+        Ret => "return".to_string(),
+        Jp(_) => format!("jump {}", target),
+        Call(_) => format!(":call {}", target),
+        SeImm(x, nn) => format!("if v{:x} != 0x{:02X} then", x, nn),
+        SneImm(x, nn) => format!("if v{:x} == 0x{:02X} then", x, nn),
+        Se(x, y) => format!("if v{:x} != v{:x} then", x, y),
+        LdImm(x, nn) => format!("v{:x} := 0x{:02X}", x, nn),
+        AddImm(x, nn) => format!("v{:x} += 0x{:02X}", x, nn),
+        Ld(x, y) => format!("v{:x} := v{:x}", x, y),
+        Or(x, y) => format!("v{:x} |= v{:x}", x, y),
+        And(x, y) => format!("v{:x} &= v{:x}", x, y),
+        Xor(x, y) => format!("v{:x} ^= v{:x}", x, y),
+        Add(x, y) => format!("v{:x} += v{:x}", x, y),
+        Sub(x, y) => format!("v{:x} -= v{:x}", x, y),
+        Shr(x, y) => format!("v{:x} >>= v{:x}", x, y),
+        Subn(x, y) => format!("v{:x} =- v{:x}", x, y),
+        Shl(x, y) => format!("v{:x} <<= v{:x}", x, y),
+        Sne(x, y) => format!("if v{:x} == v{:x} then", x, y),
+        LdI(_) => format!("i := {}", target),
+        JpV0(..) => format!("jump0 {}", target),
+        Rnd(x, nn) => format!("v{:x} := random 0x{:02X}", x, nn),
+        Show(x, y, n) => format!("sprite v{:x} v{:x} {}", x, y, n),
+        Skp(x) => format!("if v{:x} -key then", x),
+        Sknp(x) => format!("if v{:x} key then", x),
+        LdVxDt(x) => format!("v{:x} := delay", x),
+        LdDtVx(x) => format!("delay := v{:x}", x),
+        LdStVx(x) => format!("buzzer := v{:x}", x),
+        AddI(x) => format!("i += v{:x}", x),
+        LdFont(x) => format!("i := hex v{:x}", x),
+        LdBcd(x) => format!("bcd v{:x}", x),
+        Store(x) => format!("save v{:x}", x),
+        Load(x) => format!("load v{:x}", x),
+
+        ScrollDown(n) => format!("scroll-down {}", n),
+        ScrollRight => "scroll-right".to_string(),
+        ScrollLeft => "scroll-left".to_string(),
+        Exit => "exit".to_string(),
+        Sed => "hires".to_string(),
+        Cld => "lores".to_string(),
+        Show16(x, y) => format!("sprite v{:x} v{:x} 0", x, y),
+        LdFont10(x) => format!("i := bighex v{:x}", x),
+        SaveFlags(x) => format!("saveflags v{:x}", x),
+        RestoreFlags(x) => format!("loadflags v{:x}", x),
+
+        ScrollUp(n) => format!("scroll-up {}", n),
+        SaveRange(x, y) => format!("save v{:x} - v{:x}", x, y),
+        RestoreRange(x, y) => format!("load v{:x} - v{:x}", x, y),
+        LdILong(_) => format!("i := long {}", target),
+        Plane(n) => format!("plane {}", n),
+        Audio => "audio".to_string(),
+        Pitch(x) => format!("pitch := v{:x}", x),
+
+        // Octo has no syntax for CHIP-8X (or unknown) instructions; emit their bytes
+        BgStep | AddNibbles(..) | Col(..) | ColRows(..) | Unknown(_) => {
+            let bytes: Vec<String> =
+                instruction.encode().iter().map(|b| format!("0x{:02X}", b)).collect();
+
+            bytes.join(" ")
+        }
+    }
+}
+
+// Disassembly of a complete ROM
+pub struct Disassembly {
+    // Address the ROM is loaded at
+    origin: usize,
+
+    // Decoded instructions (reached by following control flow) by address
+    code: BTreeMap<usize, Instruction>,
+
+    // Jump and call targets
+    labels: BTreeSet<usize>,
+}
+
+impl Disassembly {
+    // Follow control flow from the entry point of `mode` to separate code from data
+    pub fn new(rom: &[u8], mode: Mode) -> Self {
+        let origin = mode.entry();
+        let end = origin + rom.len();
+
+        let mut code = BTreeMap::new();
+        let mut labels = BTreeSet::new();
+        let mut pending = vec![origin];
+
+        while let Some(address) = pending.pop() {
+            if address < origin || address >= end || code.contains_key(&address) {
+                continue;
+            }
+
+            let instruction = match Instruction::decode(mode, &rom[(address - origin)..]) {
+                Some(Instruction::Unknown(_)) | None => continue,
+                Some(instruction) => instruction,
+            };
+
+            let next = address + instruction.len();
+
+            match Flow::of(instruction) {
+                Flow::Next => pending.push(next),
+
+                Flow::Skip => {
+                    pending.push(next);
+
+                    // XO-CHIP skips over the full 4-byte `F000 NNNN`
+                    let skip = if mode == Mode::XoChip && next + 1 < end &&
+                                  rom[next - origin] == 0xF0 &&
+                                  rom[next + 1 - origin] == 0x00 {
+                        4
+                    } else {
+                        2
+                    };
+
+                    pending.push(next + skip);
+                }
+
+                Flow::Jump => {
+                    if let Some(target) = target(instruction) {
+                        labels.insert(target);
+                        pending.push(target);
+                    }
+                }
+
+                Flow::Call => {
+                    if let Some(target) = target(instruction) {
+                        labels.insert(target);
+                        pending.push(target);
+                    }
+
+                    pending.push(next);
+                }
+
+                Flow::Computed => {
+                    // The base of a jump table is still worth a label
+                    if let Some(target) = target(instruction) {
+                        labels.insert(target);
+                    }
+                }
+
+                Flow::Stop => {}
+            }
+
+            code.insert(address, instruction);
+        }
+
+        // Labels can only be emitted for addresses inside the ROM
+        let labels = labels.into_iter().filter(|&a| a >= origin && a < end).collect();
+
+        Disassembly {
+            origin: origin,
+            code: code,
+            labels: labels,
+        }
+    }
+
+    fn label(&self, address: usize) -> Option<String> {
+        if self.labels.contains(&address) {
+            Some(format!("L{:04X}", address))
+        } else {
+            None
+        }
+    }
+
+    // Render the disassembly of `rom` (the same ROM the disassembly was made from)
+    pub fn format(&self, rom: &[u8], syntax: Syntax) -> String {
+        let mut out = String::new();
+        let end = self.origin + rom.len();
+
+        if syntax == Syntax::Octo && self.origin != 0x200 {
+            let _ = writeln!(out, ":org 0x{:03X}", self.origin);
+        }
+
+        let mut address = self.origin;
+        while address < end {
+            if let Some(label) = self.label(address) {
+                let _ = match syntax {
+                    Syntax::Listing => writeln!(out, "{}:", label),
+                    Syntax::Octo => writeln!(out, ": {}", label),
+                };
+            }
+
+            let offset = address - self.origin;
+
+            // Instructions that overlap a label (or the end of the ROM) are emitted as data
+            let instruction = match self.code.get(&address) {
+                Some(&instruction) if address + instruction.len() <= end &&
+                                      (1..instruction.len())
+                    .all(|i| !self.labels.contains(&(address + i))) => Some(instruction),
+                _ => None,
+            };
+
+            if let Some(instruction) = instruction {
+                let label = target(instruction).and_then(|target| self.label(target));
+                let text = format(instruction, syntax, label.as_ref().map(|s| &**s));
+
+                let _ = match syntax {
+                    Syntax::Listing => {
+                        let bytes: Vec<String> = rom[offset..(offset + instruction.len())]
+                            .iter()
+                            .map(|b| format!("{:02X}", b))
+                            .collect();
+
+                        writeln!(out, "  {:04X}  {:<8}  {}", address, bytes.concat(), text)
+                    }
+
+                    Syntax::Octo => writeln!(out, "  {}", text),
+                };
+
+                address += instruction.len();
+                continue;
+            }
+
+            // Data; runs until the next instruction or label (at most 8 bytes per line)
+            let mut len = 1;
+            while len < 8 && address + len < end && !self.code.contains_key(&(address + len)) &&
+                  !self.labels.contains(&(address + len)) {
+                len += 1;
+            }
+
+            let bytes = &rom[offset..(offset + len)];
+            let _ = match syntax {
+                Syntax::Listing => {
+                    let bytes: Vec<String> = bytes.iter().map(|b| format!("${:02X}", b)).collect();
+                    writeln!(out, "  {:04X}  {:<8}  DB {}", address, "", bytes.join(", "))
+                }
+
+                Syntax::Octo => {
+                    let bytes: Vec<String> = bytes.iter().map(|b| format!("0x{:02X}", b)).collect();
+                    writeln!(out, "  {}", bytes.join(" "))
+                }
+            };
+
+            address += len;
+        }
+
+        out
+    }
+}
+
+// Disassemble a complete ROM for the given mode
+pub fn disassemble(rom: &[u8], mode: Mode, syntax: Syntax) -> String {
+    Disassembly::new(rom, mode).format(rom, syntax)
+}
+
+#[cfg(test)]
+mod tests {
+    use assembler::assemble;
+    use interpreter::Mode;
+    use super::{Syntax, disassemble};
+
+    // (at $200) LD V0, 5; CALL $208; JP $204; DB $FF, $01; (at $208) LD I, $206;
+    // SE V0, 5; RET; RET; DB $12
+    const ROM: [u8; 17] = [0x60, 0x05, 0x22, 0x08, 0x12, 0x04, 0xFF, 0x01, 0xA2, 0x06, 0x30,
+                           0x05, 0x00, 0xEE, 0x00, 0xEE, 0x12];
+
+    #[test]
+    fn listing() {
+        assert_eq!(disassemble(&ROM, Mode::Chip8, Syntax::Listing),
+                   "  0200  6005      LD V0, $05\n\
+                    \x20 0202  2208      CALL L0208\n\
+                    L0204:\n\
+                    \x20 0204  1204      JP L0204\n\
+                    \x20 0206            DB $FF, $01\n\
+                    L0208:\n\
+                    \x20 0208  A206      LD I, $206\n\
+                    \x20 020A  3005      SE V0, $05\n\
+                    \x20 020C  00EE      RET\n\
+                    \x20 020E  00EE      RET\n\
+                    \x20 0210            DB $12\n");
+    }
+
+    #[test]
+    fn octo() {
+        let source = disassemble(&ROM, Mode::Chip8, Syntax::Octo);
+
+        assert_eq!(source,
+                   "  v0 := 0x05\n\
+                    \x20 :call L0208\n\
+                    : L0204\n\
+                    \x20 jump L0204\n\
+                    \x20 0xFF 0x01\n\
+                    : L0208\n\
+                    \x20 i := 0x206\n\
+                    \x20 if v0 != 0x05 then\n\
+                    \x20 return\n\
+                    \x20 return\n\
+                    \x20 0x12\n");
+
+        assert_eq!(assemble(&source, Mode::Chip8).unwrap().rom, &ROM[..]);
+
+        // CHIP-8X loads at $300; its instructions are not Octo's, so they are emitted as
+        // bytes
+        let rom = [0x02, 0xA0, 0xB1, 0x23, 0x13, 0x00];
+        let source = disassemble(&rom, Mode::Chip8x, Syntax::Octo);
+
+        assert!(source.starts_with(":org 0x300\n"));
+        assert_eq!(assemble(&source, Mode::Chip8x).unwrap().rom, &rom[..]);
+    }
+
+    #[test]
+    fn code_and_data() {
+        // JP $203; DB $00; (at $203, unaligned) LD V0, 1; JP $203
+        let rom = [0x12, 0x03, 0x00, 0x60, 0x01, 0x12, 0x03];
+
+        assert_eq!(disassemble(&rom, Mode::Chip8, Syntax::Listing),
+                   "  0200  1203      JP L0203\n\
+                    \x20 0202            DB $00\n\
+                    L0203:\n\
+                    \x20 0203  6001      LD V0, $01\n\
+                    \x20 0205  1203      JP L0203\n");
+
+        // Data is split at labels and every 8 bytes; a byte jumped over is data as well
+        let rom = [0x12, 0x0C, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0x12, 0x0F, 0x60, 0x12, 0x0C];
+
+        assert_eq!(disassemble(&rom, Mode::Chip8, Syntax::Listing),
+                   "  0200  120C      JP L020C\n\
+                    \x20 0202            DB $00, $01, $02, $03, $04, $05, $06, $07\n\
+                    \x20 020A            DB $08, $09\n\
+                    L020C:\n\
+                    \x20 020C  120F      JP L020F\n\
+                    \x20 020E            DB $60\n\
+                    L020F:\n\
+                    \x20 020F  120C      JP L020C\n");
+    }
+}
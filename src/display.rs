@@ -0,0 +1,302 @@
+use std::cmp;
+use std::vec::Vec;
+
+use state;
+
+// Widest (and tallest) display supported
+pub const MAX_SIZE: usize = 128;
+
+// Display memory
+//  Each row of dots is packed into a `u128` (the left-most dot is bit `width - 1`), with
+//  a set of rows per plane (XO-CHIP has 2 planes; other modes 1). Sprites are drawn a
+//  row at a time as a shifted XOR, with collisions found by AND.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Display {
+    width: usize,
+    height: usize,
+
+    // Rows of every plane
+    planes: Vec<Vec<u128>>,
+
+    // Rows changed since the framebuffer was last updated (bit N = row N)
+    dirty: u128,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display {
+            width: 64,
+            height: 32,
+            planes: vec![vec![0; 32]],
+            dirty: !0,
+        }
+    }
+}
+
+impl Display {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn planes(&self) -> usize {
+        self.planes.len()
+    }
+
+    // Resize to `width` x `height` dots (each at most `MAX_SIZE`) with `planes` planes;
+    // the display is cleared
+    pub fn resize(&mut self, width: usize, height: usize, planes: usize) {
+        self.width = cmp::min(cmp::max(width, 1), MAX_SIZE);
+        self.height = cmp::min(cmp::max(height, 1), MAX_SIZE);
+        self.planes = vec![vec![0; self.height]; cmp::max(planes, 1)];
+        self.dirty = !0;
+    }
+
+    pub fn clear(&mut self) {
+        for rows in &mut self.planes {
+            for (y, row) in rows.iter_mut().enumerate() {
+                if *row != 0 {
+                    *row = 0;
+                    self.dirty |= 1 << y;
+                }
+            }
+        }
+    }
+
+    // Whether row `y` changed since the framebuffer was last updated
+    pub fn is_dirty(&self, y: usize) -> bool {
+        (self.dirty >> (y % self.height)) & 1 != 0
+    }
+
+    // Changed rows (`top..bottom`), if any
+    pub fn dirty_rows(&self) -> Option<(usize, usize)> {
+        let dirty = self.dirty & all_rows(self.height);
+        if dirty == 0 {
+            return None;
+        }
+
+        Some((dirty.trailing_zeros() as usize, MAX_SIZE - dirty.leading_zeros() as usize))
+    }
+
+    // Mark rows `top..bottom` as changed (eg. when their colors change)
+    pub fn mark_dirty(&mut self, top: usize, bottom: usize) {
+        for y in top..cmp::min(bottom, self.height) {
+            self.dirty |= 1 << y;
+        }
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty = !0;
+    }
+
+    // Forget changes (once the framebuffer is updated)
+    pub fn clean(&mut self) {
+        self.dirty = 0;
+    }
+
+    // Mask of the bits used by a row
+    fn mask(&self) -> u128 {
+        if self.width == MAX_SIZE {
+            !0
+        } else {
+            (1 << self.width) - 1
+        }
+    }
+
+    pub fn row(&self, plane: usize, y: usize) -> u128 {
+        self.planes[plane][y % self.height]
+    }
+
+    // Color of a dot: bit N is set if the dot is set in plane N
+    pub fn color(&self, x: usize, y: usize) -> usize {
+        let bit = self.width - 1 - (x % self.width);
+
+        self.planes
+            .iter()
+            .enumerate()
+            .fold(0, |color, (plane, rows)| {
+                color | ((((rows[y % self.height] >> bit) & 1) as usize) << plane)
+            })
+    }
+
+    // Whether a dot is set (in any plane)
+    pub fn dot(&self, x: usize, y: usize) -> bool {
+        self.color(x, y) != 0
+    }
+
+    // XOR a sprite row of `len` dots (`bits`; MSB is the left-most dot) onto row `y` of
+    // `plane` at column `x`; dots past the right edge wrap around to the left
+    //  Returns true if a set dot was cleared (a collision).
+    pub fn xor(&mut self, plane: usize, x: usize, y: usize, bits: u128, len: usize) -> bool {
+        let len = cmp::min(len, self.width);
+        let x = x % self.width;
+
+        // Align the sprite to the left edge, then rotate it right (within the row) to `x`
+        let mask = self.mask();
+        let bits = if len < MAX_SIZE {
+            bits & ((1 << len) - 1)
+        } else {
+            bits
+        };
+
+        let bits = bits << (self.width - len);
+        let bits = if x == 0 {
+            bits
+        } else {
+            ((bits >> x) | (bits << (self.width - x))) & mask
+        };
+
+        let y = y % self.height;
+        let row = &mut self.planes[plane][y];
+        let collision = *row & bits != 0;
+        *row ^= bits;
+
+        if bits != 0 {
+            self.dirty |= 1 << y;
+        }
+
+        collision
+    }
+
+    // Scroll every plane `n` rows down (rows scrolled in are clear)
+    pub fn scroll_down(&mut self, n: usize) {
+        self.dirty = !0;
+
+        for rows in &mut self.planes {
+            for y in (0..rows.len()).rev() {
+                rows[y] = if y >= n { rows[y - n] } else { 0 };
+            }
+        }
+    }
+
+    // Scroll every plane `n` dots right (`n` < 0 scrolls left)
+    pub fn scroll_right(&mut self, n: isize) {
+        let mask = self.mask();
+        self.dirty = !0;
+
+        for rows in &mut self.planes {
+            for row in rows.iter_mut() {
+                *row = if n.abs() as usize >= self.width {
+                    0
+                } else if n >= 0 {
+                    *row >> n
+                } else {
+                    (*row << -n) & mask
+                };
+            }
+        }
+    }
+
+    // Every dot as a `bool` (set in any plane), row by row
+    pub fn to_dots(&self) -> Vec<bool> {
+        let mut dots = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                dots.push(self.dot(x, y));
+            }
+        }
+
+        dots
+    }
+
+    pub fn save_state(&self, w: &mut state::Writer) {
+        w.write_usize(self.width);
+        w.write_usize(self.height);
+        w.write_usize(self.planes.len());
+
+        for rows in &self.planes {
+            for &row in rows {
+                w.write_u64((row >> 64) as u64);
+                w.write_u64(row as u64);
+            }
+        }
+    }
+
+    pub fn load_state(&mut self, r: &mut state::Reader) {
+        let width = r.read_usize();
+        let height = r.read_usize();
+        let planes = cmp::min(r.read_usize(), 2);
+        self.resize(width, height, planes);
+
+        for rows in &mut self.planes {
+            for row in rows.iter_mut() {
+                *row = ((r.read_u64() as u128) << 64) | (r.read_u64() as u128);
+            }
+        }
+    }
+}
+
+// Bits of the rows of a display `height` rows tall
+fn all_rows(height: usize) -> u128 {
+    if height >= MAX_SIZE {
+        !0
+    } else {
+        (1 << height) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Display;
+
+    #[test]
+    fn xor() {
+        let mut display: Display = Default::default();
+
+        // Left edge, and wrapped around the right edge
+        assert!(!display.xor(0, 0, 0, 0b1111_0000, 8));
+        assert!(!display.xor(0, 60, 1, 0b1111_0011, 8));
+        assert!(display.dot(0, 0) && display.dot(3, 0) && !display.dot(4, 0));
+        assert!(display.dot(63, 1) && !display.dot(0, 1) && display.dot(2, 1));
+
+        // Clearing a set dot is a collision
+        assert!(display.xor(0, 3, 0, 0b1000_0000, 8));
+        assert!(!display.dot(3, 0));
+        assert!(!display.xor(0, 4, 0, 0b1000_0000, 8));
+
+        // Hires rows use all 128 bits
+        display.resize(128, 64, 2);
+        assert!(!display.xor(1, 120, 63, 0xFFFF, 16));
+        assert_eq!(display.row(1, 63), 0xFF << 120 | 0xFF);
+        assert_eq!((display.color(127, 63), display.color(8, 63)), (2, 0));
+    }
+
+    #[test]
+    fn scroll() {
+        let mut display: Display = Default::default();
+        display.xor(0, 4, 0, 0b1000_0001, 8);
+
+        display.scroll_down(2);
+        assert!(display.dot(4, 2) && display.dot(11, 2) && !display.dot(4, 0));
+
+        display.scroll_right(4);
+        assert!(display.dot(8, 2) && display.dot(15, 2));
+
+        display.scroll_right(-12);
+        assert!(!display.dot(60, 2) && display.dot(3, 2));
+    }
+
+    #[test]
+    fn dirty() {
+        let mut display: Display = Default::default();
+        assert_eq!(display.dirty_rows(), Some((0, 32)));
+
+        display.clean();
+        display.clear();
+        assert_eq!(display.dirty_rows(), None);
+
+        display.xor(0, 0, 3, 0xFF, 8);
+        display.xor(0, 0, 33, 0xFF, 8);
+        display.xor(0, 0, 9, 0x00, 8);
+        assert_eq!(display.dirty_rows(), Some((1, 4)));
+        assert!(display.is_dirty(1) && !display.is_dirty(2) && display.is_dirty(3));
+
+        display.clean();
+        display.clear();
+        assert_eq!(display.dirty_rows(), Some((1, 4)));
+        assert!(!display.is_dirty(2));
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+// Persistent storage for the SUPER-CHIP (8) and XO-CHIP (16) flag registers
+//  (the HP48 "RPL user flags" used by `FX75` / `FX85`). Flags are saved as raw bytes
+//  in `<save_dir>/<rom hash>.flags` so each ROM keeps its own high scores.
+
+fn path(dir: &Path, rom_hash: u64) -> PathBuf {
+    dir.join(format!("{:016x}.flags", rom_hash))
+}
+
+// Load saved flags into `flags`; flags are left untouched if nothing was saved
+pub fn load(dir: &Path, rom_hash: u64, flags: &mut [u8]) -> io::Result<()> {
+    let mut stream = match File::open(path(dir, rom_hash)) {
+        Ok(stream) => stream,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    let mut buffer = Vec::new();
+    stream.read_to_end(&mut buffer)?;
+
+    for (dst, src) in flags.iter_mut().zip(&buffer) {
+        *dst = *src;
+    }
+
+    Ok(())
+}
+
+pub fn save(dir: &Path, rom_hash: u64, flags: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let mut stream = File::create(path(dir, rom_hash))?;
+    stream.write_all(flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use interpreter::Mode;
+    use test_host::{self, FakeHost};
+
+    // Fresh (empty) save directory for a test
+    fn save_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("xchip-flags-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+
+        dir
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = save_dir("round-trip");
+
+        super::save(&dir, 0x1234, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+        let mut flags = [0; 8];
+        super::load(&dir, 0x1234, &mut flags).unwrap();
+        assert_eq!(flags, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // Flags are kept per ROM
+        let mut flags = [0xAA; 8];
+        super::load(&dir, 0x5678, &mut flags).unwrap();
+        assert_eq!(flags, [0xAA; 8]);
+
+        // A shorter save (SUPER-CHIP) only fills the first flags of a longer one (XO-CHIP)
+        let mut flags = [0xAA; 16];
+        super::load(&dir, 0x1234, &mut flags).unwrap();
+        assert_eq!(&flags[..9], &[1, 2, 3, 4, 5, 6, 7, 8, 0xAA]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Store V0..VF (= 1..16) in the flags, remove the ROM, and restore them after
+    // inserting it again; returns the restored registers
+    fn persist(mode: Mode, name: &str) -> [u8; 16] {
+        let dir = save_dir(name);
+
+        // LD V0, 1 .. LD VF, 16; SAVE VF; JP $222; RESTORE VF; JP $226
+        let mut rom = Vec::new();
+        for x in 0..16 {
+            rom.extend_from_slice(&[0x60 | x, x + 1]);
+        }
+
+        rom.extend_from_slice(&[0xFF, 0x75, 0x12, 0x22, 0xFF, 0x85, 0x12, 0x26]);
+
+        let mut host = FakeHost::default();
+        let mut interpreter = test_host::interpreter(mode, &[], None);
+        interpreter.set_save_dir(Some(dir.clone()));
+
+        interpreter.insert_rom_bytes(&rom, mode);
+        interpreter.reset();
+        for _ in 0..17 {
+            interpreter.run_next(&mut host);
+        }
+
+        interpreter.remove_rom();
+
+        interpreter.insert_rom_bytes(&rom, mode);
+        interpreter.reset();
+        let mut registers = interpreter.registers();
+        registers.pc = 0x224;
+        interpreter.set_registers(registers);
+        interpreter.run_next(&mut host);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        interpreter.registers().v
+    }
+
+    #[test]
+    fn super_chip() {
+        // The SUPER-CHIP has 8 flags (V0..V7 are restored; V7 included)
+        let v = persist(Mode::SuperChip, "super-chip");
+        assert_eq!(v, [1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn xo_chip() {
+        let v = persist(Mode::XoChip, "xo-chip");
+        assert_eq!(v, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]);
+    }
+}
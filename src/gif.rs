@@ -0,0 +1,503 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use pixel;
+
+// Records frames (one per 1/60 s) as an animated GIF
+//  Identical frames are merged into a single, longer GIF frame. As GIF delays are in
+//  1/100 s (and viewers slow down anything shorter than 2/100 s), frames that would be
+//  shown for less than 2/100 s are dropped in favor of the next one.
+pub struct Recorder {
+    out: Box<Write>,
+
+    // Each dot becomes a `scale` x `scale` block
+    scale: usize,
+
+    // Colors (0xRRGGBB) of the color table; the table size is a power of 2
+    colors: Vec<u32>,
+
+    // Format of the recorded framebuffers
+    format: pixel::Format,
+
+    // Framebuffer color -> color table index (nearest color); filled as colors are seen
+    index: HashMap<u32, u8>,
+
+    // Dimensions of the recording (from the first frame); frames with other
+    // dimensions are not recorded
+    width: usize,
+    height: usize,
+
+    // Frames recorded (including those merged or dropped)
+    frames: u64,
+
+    // The last frame (as color indices); it is written once its duration is known
+    pending: Option<(Vec<u8>, u64)>,
+}
+
+// Time (in 1/100 s) at the start of frame `frame`
+fn centiseconds(frame: u64) -> u64 {
+    (frame * 100 + 30) / 60
+}
+
+impl Recorder {
+    // Record framebuffers in `format` using `colors` (0xRRGGBB; at most 256); other
+    // colors are mapped to the nearest of them
+    pub fn new(out: Box<Write>,
+               mut colors: Vec<u32>,
+               format: pixel::Format,
+               scale: usize)
+               -> Self {
+        colors.truncate(256);
+
+        // Pad the color table to a power of 2 (of at least 2 entries)
+        let len = cmp::max(colors.len().next_power_of_two(), 2);
+        colors.resize(len, 0);
+
+        Recorder {
+            out: out,
+            scale: cmp::max(scale, 1),
+            colors: colors,
+            format: format,
+            index: HashMap::new(),
+            width: 0,
+            height: 0,
+            frames: 0,
+            pending: None,
+        }
+    }
+
+    // Record a frame (a framebuffer in the format given to `new`)
+    pub fn record(&mut self, framebuffer: &[u8], width: usize, height: usize) -> io::Result<()> {
+        if self.frames == 0 && self.pending.is_none() {
+            self.width = width;
+            self.height = height;
+            self.write_header()?;
+        } else if (width, height) != (self.width, self.height) {
+            self.frames += 1;
+            return Ok(());
+        }
+
+        let image: Vec<u8> = framebuffer.chunks(self.format.bytes_per_pixel())
+            .map(|pixel| self.color_index(self.format.decode(pixel)))
+            .collect();
+
+        let frame = self.frames;
+        self.frames += 1;
+
+        let start = match self.pending.take() {
+            Some((pending, start)) => {
+                let delay = centiseconds(frame) - centiseconds(start);
+
+                if pending == image {
+                    // Identical to the last frame; it is shown for longer
+                    self.pending = Some((pending, start));
+                    return Ok(());
+                } else if delay < 2 {
+                    // Not shown for long enough; replaced by this frame
+                    start
+                } else {
+                    self.write_frame(&pending, delay)?;
+                    frame
+                }
+            }
+
+            None => frame,
+        };
+
+        self.pending = Some((image, start));
+
+        Ok(())
+    }
+
+    // Color table index of (the color nearest to) `color`
+    fn color_index(&mut self, color: u32) -> u8 {
+        if let Some(&index) = self.index.get(&color) {
+            return index;
+        }
+
+        let channels = |color: u32| [(color >> 16) & 0xFF, (color >> 8) & 0xFF, color & 0xFF];
+        let a = channels(color);
+        let distance = |color: u32| {
+            let b = channels(color);
+
+            (0..3).map(|i| (a[i] as i32 - b[i] as i32).pow(2)).sum::<i32>()
+        };
+
+        let index = (0..self.colors.len())
+            .min_by_key(|&i| distance(self.colors[i]))
+            .unwrap_or(0) as u8;
+
+        self.index.insert(color, index);
+        index
+    }
+
+    // Write the last frame and end the GIF
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some((pending, start)) = self.pending.take() {
+            let delay = cmp::max(centiseconds(self.frames) - centiseconds(start), 2);
+            self.write_frame(&pending, delay)?;
+
+            // Trailer
+            self.out.write_all(&[0x3B])?;
+        }
+
+        self.out.flush()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let width = (self.width * self.scale) as u16;
+        let height = (self.height * self.scale) as u16;
+
+        // Size of the color table as 2^(N + 1)
+        let size = self.colors.len().trailing_zeros() as u8 - 1;
+
+        self.out.write_all(b"GIF89a")?;
+
+        // Logical screen: size, global color table (present, 8-bit color resolution),
+        // background color (0), and pixel aspect ratio (unspecified)
+        self.out.write_all(&[width as u8,
+                             (width >> 8) as u8,
+                             height as u8,
+                             (height >> 8) as u8,
+                             0x80 | 0x70 | size,
+                             0,
+                             0])?;
+
+        for &color in &self.colors {
+            self.out.write_all(&[(color >> 16) as u8, (color >> 8) as u8, color as u8])?;
+        }
+
+        // Loop forever (NETSCAPE2.0 application extension)
+        self.out.write_all(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00")
+    }
+
+    fn write_frame(&mut self, image: &[u8], delay: u64) -> io::Result<()> {
+        let delay = cmp::min(delay, 0xFFFF) as u16;
+        let width = (self.width * self.scale) as u16;
+        let height = (self.height * self.scale) as u16;
+
+        // Graphic control extension: no disposal, no transparency, and the delay
+        self.out.write_all(&[0x21, 0xF9, 0x04, 0x04, delay as u8, (delay >> 8) as u8, 0, 0])?;
+
+        // Image descriptor: the whole logical screen without a local color table
+        self.out.write_all(&[0x2C,
+                             0,
+                             0,
+                             0,
+                             0,
+                             width as u8,
+                             (width >> 8) as u8,
+                             height as u8,
+                             (height >> 8) as u8,
+                             0])?;
+
+        // Scale the image
+        let mut indices = Vec::with_capacity(image.len() * self.scale * self.scale);
+        for row in image.chunks(cmp::max(self.width, 1)) {
+            for _ in 0..self.scale {
+                for &index in row {
+                    for _ in 0..self.scale {
+                        indices.push(index);
+                    }
+                }
+            }
+        }
+
+        // LZW-compressed image data in sub-blocks of at most 255 bytes
+        let min_code_size = cmp::max(self.colors.len().trailing_zeros() as u8, 2);
+        self.out.write_all(&[min_code_size])?;
+
+        for block in lzw(min_code_size, &indices).chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+
+        self.out.write_all(&[0])
+    }
+}
+
+// Packs variable-width codes (LSB first)
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.bits |= (code as u32) << self.len;
+        self.len += size;
+
+        while self.len >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    fn into_inner(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+
+        self.bytes
+    }
+}
+
+// GIF-flavored LZW compression
+fn lzw(min_code_size: u8, indices: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut w: BitWriter = Default::default();
+    let mut table = HashMap::new();
+    let mut next = end + 1;
+    let mut size = min_code_size as u32 + 1;
+
+    w.write(clear, size);
+
+    let mut indices = indices.iter();
+    let mut prefix = match indices.next() {
+        Some(&index) => index as u16,
+        None => {
+            w.write(end, size);
+            return w.into_inner();
+        }
+    };
+
+    for &index in indices {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+
+        w.write(prefix, size);
+
+        if next < 4096 {
+            table.insert((prefix, index), next);
+
+            // Codes grow by a bit once the next code no longer fits
+            if next == (1 << size) && size < 12 {
+                size += 1;
+            }
+
+            next += 1;
+        } else {
+            // Table is full; start over
+            w.write(clear, size);
+
+            table.clear();
+            next = end + 1;
+            size = min_code_size as u32 + 1;
+        }
+
+        prefix = index as u16;
+    }
+
+    w.write(prefix, size);
+
+    // The decoder adds a code on reading the last one; the end code may no longer fit
+    if next == (1 << size) && size < 12 {
+        size += 1;
+    }
+
+    w.write(end, size);
+
+    w.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+    use pixel::Format;
+    use super::{Recorder, lzw};
+
+    // Output shared with the test (the recorder owns its writer)
+    #[derive(Clone, Default)]
+    struct Sink(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // GIF-flavored LZW decompression
+    fn unlzw(min_code_size: u8, data: &[u8]) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let end = clear + 1;
+
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut size = min_code_size as usize + 1;
+        let mut previous: Option<usize> = None;
+        let mut out = Vec::new();
+
+        let (mut bits, mut len, mut bytes) = (0u32, 0, data.iter());
+        loop {
+            while len < size {
+                bits |= (*bytes.next().expect("missing end code") as u32) << len;
+                len += 8;
+            }
+
+            let code = (bits & ((1 << size) - 1)) as usize;
+            bits >>= size;
+            len -= size;
+
+            if code == clear {
+                table = (0..end + 1).map(|index| vec![index as u8]).collect();
+                size = min_code_size as usize + 1;
+                previous = None;
+                continue;
+            } else if code == end {
+                return out;
+            }
+
+            let entry = match previous {
+                Some(previous) => {
+                    let mut entry = table[previous].clone();
+                    let first = if code < table.len() {
+                        table[code][0]
+                    } else {
+                        assert_eq!(code, table.len());
+                        entry[0]
+                    };
+
+                    entry.push(first);
+                    if table.len() < 4096 {
+                        table.push(entry);
+                    }
+
+                    if table.len() == (1 << size) && size < 12 {
+                        size += 1;
+                    }
+
+                    table[code].clone()
+                }
+
+                None => table[code].clone(),
+            };
+
+            out.extend(entry);
+            previous = Some(code);
+        }
+    }
+
+    // (width, height, color table, and every frame as (color indices, delay))
+    fn decode(gif: &[u8]) -> (usize, usize, Vec<u32>, Vec<(Vec<u8>, u16)>) {
+        assert_eq!(&gif[..6], b"GIF89a");
+
+        let word = |at: usize| gif[at] as usize | (gif[at + 1] as usize) << 8;
+        let (width, height) = (word(6), word(8));
+        let colors: Vec<u32> = gif[13..13 + 3 * (2 << (gif[10] & 7))]
+            .chunks(3)
+            .map(|rgb| (rgb[0] as u32) << 16 | (rgb[1] as u32) << 8 | rgb[2] as u32)
+            .collect();
+
+        // Sub-blocks from `at`; returns (data, position after the terminator)
+        let blocks = |mut at: usize| {
+            let mut data = Vec::new();
+            while gif[at] != 0 {
+                data.extend_from_slice(&gif[at + 1..at + 1 + gif[at] as usize]);
+                at += 1 + gif[at] as usize;
+            }
+
+            (data, at + 1)
+        };
+
+        let mut at = 13 + 3 * colors.len();
+        let mut frames = Vec::new();
+        let mut delay = 0;
+
+        loop {
+            match gif[at] {
+                0x21 => {
+                    let (data, next) = blocks(at + 2);
+                    if gif[at + 1] == 0xF9 {
+                        delay = data[1] as u16 | (data[2] as u16) << 8;
+                    }
+
+                    at = next;
+                }
+
+                0x2C => {
+                    assert_eq!((word(at + 5), word(at + 7)), (width, height));
+
+                    let (data, next) = blocks(at + 11);
+                    frames.push((unlzw(gif[at + 10], &data), delay));
+                    at = next;
+                }
+
+                0x3B => return (width, height, colors, frames),
+                other => panic!("unexpected block ${:02X}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        // Long enough to fill the code table (and start over) a few times
+        let mut state = 1u32;
+        let indices: Vec<u8> = (0..30000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 24) as u8
+            })
+            .collect();
+
+        for &min_code_size in &[2, 4, 8] {
+            let mask = ((1u16 << min_code_size) - 1) as u8;
+            let indices: Vec<u8> = indices.iter().map(|&index| index & mask).collect();
+
+            assert_eq!(unlzw(min_code_size, &lzw(min_code_size, &indices)), indices);
+        }
+
+        // Runs (long strings of the same index)
+        let indices = vec![1; 5000];
+        assert_eq!(unlzw(2, &lzw(2, &indices)), indices);
+
+        assert_eq!(unlzw(2, &lzw(2, &[])), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn record() {
+        let format = Format::Rgba8888;
+        let colors = vec![0x000000, 0xFFFFFF, 0xFF0000];
+        let frame = |pixels: &[u32]| -> Vec<u8> {
+            pixels.iter().flat_map(|&color| format.encode(color)).collect()
+        };
+
+        // 0xF00202 is not in the color table; it is recorded as red (the nearest)
+        let a = frame(&[0x000000, 0xFFFFFF, 0xF00202, 0x000000]);
+        let b = frame(&[0xFFFFFF, 0x000000, 0x000000, 0xFF0000]);
+
+        let sink: Sink = Default::default();
+        let mut recorder = Recorder::new(Box::new(sink.clone()), colors, format, 2);
+
+        // A for 2 frames, B, A, B (at 4/60 s, shown for less than 2/100 s; dropped), and
+        // A for 5
+        for image in &[&a, &a, &b, &a, &b, &a, &a, &a, &a, &a] {
+            recorder.record(image, 2, 2).unwrap();
+        }
+
+        recorder.finish().unwrap();
+
+        let (width, height, colors, frames) = decode(&sink.0.borrow());
+        assert_eq!((width, height), (4, 4));
+        assert_eq!(colors, [0x000000, 0xFFFFFF, 0xFF0000, 0x000000]);
+
+        // Scaled 2x; the delays are in 1/100 s
+        let a = vec![0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 0, 0, 2, 2, 0, 0];
+        let b = vec![1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 2, 2, 0, 0, 2, 2];
+        assert_eq!(frames, [(a.clone(), 3), (b, 2), (a.clone(), 2), (a, 10)]);
+    }
+}
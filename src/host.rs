@@ -0,0 +1,67 @@
+#[cfg(feature = "axal")]
+use axal::{self, Key};
+
+use keypad::Keypad;
+
+// Platform a `Core` runs on: the keypad, and sinks for video and audio
+pub trait Host: Keypad {
+    // Show a frame (`width` x `height` pixels in the pixel format of the core); only
+    // called when the frame changed
+    fn video_refresh(&mut self, framebuffer: &[u8], width: usize, height: usize);
+
+    // Sound (or silence) the buzzer for the frame just run; called every frame
+    fn audio_buzzer(&mut self, _on: bool) {}
+}
+
+// CHIP-8 hex keyboard -> modern keyboard
+#[cfg(feature = "axal")]
+const KEYBOARD_MAP: [Key; 0x10] = [Key::X, Key::Num1, Key::Num2, Key::Num3, Key::Q, Key::W,
+                                   Key::E, Key::A, Key::S, Key::D, Key::Z, Key::C, Key::Num4,
+                                   Key::R, Key::F, Key::V];
+
+// Host backed by an axal frontend (its keyboard and video; axal has no audio)
+#[cfg(feature = "axal")]
+pub struct AxalHost<'a>(pub &'a mut axal::Runtime);
+
+#[cfg(feature = "axal")]
+impl<'a> Keypad for AxalHost<'a> {
+    fn key_state(&mut self, key: u8) -> bool {
+        self.0.input_keyboard_state(0, KEYBOARD_MAP[(key & 0xF) as usize])
+    }
+}
+
+#[cfg(feature = "axal")]
+impl<'a> Host for AxalHost<'a> {
+    fn video_refresh(&mut self, framebuffer: &[u8], width: usize, height: usize) {
+        self.0.video_refresh(framebuffer, width as u32, height as u32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Core;
+    use interpreter::Mode;
+    use test_host::FakeHost;
+
+    #[test]
+    fn run_frame() {
+        // LD V0, $FF; LD ST, V0; CLS; JP $206 (spins)
+        let rom = [0x60, 0xFF, 0xF0, 0x18, 0x00, 0xE0, 0x12, 0x06];
+
+        let mut core: Core = Default::default();
+        core.insert_rom_bytes(&rom, Mode::Chip8);
+        core.reset();
+
+        let mut host = FakeHost::default();
+        for _ in 0..3 {
+            core.run_frame(&mut host);
+        }
+
+        // The first frame is drawn; the others are unchanged
+        assert_eq!(host.frames.len(), 1);
+        assert_eq!((host.frames[0].1, host.frames[0].2), (64, 32));
+
+        // ST counts down on the wall clock, so the buzzer is still on
+        assert_eq!(host.audio, [true, true, true]);
+    }
+}
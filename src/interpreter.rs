@@ -1,19 +1,41 @@
+#[cfg(feature = "std")]
 use std::fs::File;
-use std::io::Read;
-use std::path::Path;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+use std::boxed::Box;
 use std::vec::Vec;
+#[cfg(feature = "std")]
 use std::time::Instant;
 
-use axal;
-
 use super_chip;
+use xo_chip;
 use chip_8;
 use chip_8x;
-use opcode::Opcode;
+use compiler::{Compiler, Execution, Recompiled};
+use opcode::Instruction;
 use mmu;
+use state;
+#[cfg(feature = "std")]
+use flags;
+use debugger::{Debugger, Registers};
+use display::Display;
+#[cfg(feature = "std")]
+use trace::Tracer;
+use keypad::Keypad;
+#[cfg(feature = "std")]
+use palette;
+use palette::Palette;
+use pixel::{self, Region};
+use post_process::PostProcess;
+use quirks::Quirks;
+use rng::{self, Rng};
+#[cfg(feature = "std")]
+use screenshot;
 
 // Interpreter mode of operation
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Mode {
     Chip8,
     HiResChip8,
@@ -23,8 +45,45 @@ pub enum Mode {
     XoChip,
 }
 
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Chip8
+    }
+}
+
 impl Mode {
-    fn from_file(filename: &str) -> Self {
+    // Address where ROMs are loaded (and execution begins)
+    pub fn entry(&self) -> usize {
+        match *self {
+            Mode::Chip8x => 0x300,
+            _ => 0x200,
+        }
+    }
+
+    // Size of RAM
+    pub fn memory_size(&self) -> usize {
+        match *self {
+            Mode::XoChip => 0x10000,
+            _ => 0x1000,
+        }
+    }
+
+    // Parse a mode name (as accepted by `-m <mode>`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "chip-8" | "8" => Mode::Chip8,
+            "hires-chip-8" | "hires" => Mode::HiResChip8,
+            "chip-10" | "10" => Mode::Chip10,
+            "chip-8x" | "8x" => Mode::Chip8x,
+            "super-chip" | "sc" => Mode::SuperChip,
+            "xo-chip" | "xo" => Mode::XoChip,
+            _ => return None,
+        })
+    }
+
+    // Determine the mode from the extension of a ROM's filename
+    #[cfg(feature = "std")]
+    pub fn from_file(filename: &str) -> Self {
         let ext = Path::new(filename).extension().unwrap_or_default().to_string_lossy();
 
         match &*ext {
@@ -35,10 +94,35 @@ impl Mode {
     }
 }
 
+// Source of the 60 Hz clock for DT / ST
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Clock {
+    // Wall-clock time elapsed between instructions (std only)
+    #[cfg(feature = "std")]
+    RealTime,
+
+    // The host calls `Interpreter::tick` once per 1/60 s (eg. once per frame);
+    // required for deterministic (headless) runs, and the default without std
+    External,
+}
+
+impl Default for Clock {
+    #[cfg(feature = "std")]
+    fn default() -> Self {
+        Clock::RealTime
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn default() -> Self {
+        Clock::External
+    }
+}
+
 #[derive(Default)]
 pub struct Context {
     // Framebuffer / Video RAM
     pub framebuffer: Vec<u8>,
+    pub pixel_format: pixel::Format,
 
     // General registers (16x 8-bit)
     pub v: [u8; 16],
@@ -56,16 +140,22 @@ pub struct Context {
     pub stack_len: usize,
 
     // Display buffer (screen) and active resolution
-    //  screen.len() == screen_width * screen_height
-    pub screen: Vec<bool>,
-    pub screen_width: usize,
-    pub screen_height: usize,
+    pub display: Display,
 
     // Delay timer
     pub dt: u8,
 
     // Sound timer
     pub st: u8,
+
+    // Active compatibility quirks
+    pub quirks: Quirks,
+
+    // Random number generator (for RND)
+    pub rng: Rng,
+
+    // Display colors
+    pub palette: Palette,
 }
 
 impl Context {
@@ -86,9 +176,7 @@ impl Context {
         self.framebuffer.clear();
 
         // Clear screen
-        for dot in &mut self.screen {
-            *dot = false;
-        }
+        self.display.clear();
     }
 
     pub fn stack_push(&mut self, m: &mut mmu::Mmu, value: u16) {
@@ -114,6 +202,40 @@ impl Context {
 
         ((hi as u16) << 8) | (lo as u16)
     }
+
+    fn save_state(&self, w: &mut state::Writer) {
+        w.write_bytes(&self.v);
+        w.write_usize(self.i);
+        w.write_usize(self.pc);
+        w.write_u8(self.sp);
+        w.write_usize(self.stack_len);
+
+        self.display.save_state(w);
+
+        w.write_u8(self.dt);
+        w.write_u8(self.st);
+
+        self.rng.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut state::Reader) {
+        let v = r.read_bytes();
+        for (dst, src) in self.v.iter_mut().zip(v) {
+            *dst = *src;
+        }
+
+        self.i = r.read_usize();
+        self.pc = r.read_usize();
+        self.sp = r.read_u8();
+        self.stack_len = r.read_usize();
+
+        self.display.load_state(r);
+
+        self.dt = r.read_u8();
+        self.st = r.read_u8();
+
+        self.rng.load_state(r);
+    }
 }
 
 pub trait Runtime {
@@ -128,31 +250,48 @@ pub trait Runtime {
         m.write_all(0x200, buffer);
     }
 
-    // Update framebuffer (in context)
-    fn update_framebuffer(&mut self, c: &mut Context) {
-        // Blit screen onto framebuffer
-        c.framebuffer.resize(c.screen.len(), 0);
-        for y in 0..c.screen_height {
-            let offset_y = y * c.screen_width;
+    // Persistent flag registers (SUPER-CHIP / XO-CHIP), if any
+    //  Only `Core` (with std) saves them to disk.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    fn flags(&mut self) -> Option<&mut [u8]> {
+        None
+    }
 
-            for x in 0..c.screen_width {
-                let offset = offset_y + x;
+    // Save runtime-specific state (for snapshots)
+    fn save_state(&self, w: &mut state::Writer) {}
 
-                // Get dot from screen
-                let dot = c.screen[offset];
+    // Restore runtime-specific state written by `save_state`
+    fn load_state(&mut self, r: &mut state::Reader) {}
 
-                // Blit to framebuffer
-                c.framebuffer[offset] = if dot { 0xFF } else { 0x00 };
+    // Check state restored by `load_state` against the context restored with it
+    fn check_state(&self, c: &Context) -> bool {
+        true
+    }
+
+    // Update the rows of the framebuffer (in context) marked dirty in the display
+    fn update_framebuffer(&mut self, c: &mut Context) {
+        let display = &c.display;
+        let row_len = display.width() * c.pixel_format.bytes_per_pixel();
+
+        for y in 0..display.height() {
+            if !display.is_dirty(y) {
+                continue;
             }
+
+            // Blit row onto framebuffer (in the color of the planes each dot is set in)
+            pixel::render(c.pixel_format,
+                          &mut c.framebuffer[y * row_len..(y + 1) * row_len],
+                          &c.palette.colors[..1 << display.planes()],
+                          (0..display.width()).map(|x| display.color(x, y)));
         }
     }
 
-    // Execute passed operation; return false if unhandled
+    // Execute passed instruction; return false if unhandled
     fn execute(&mut self,
-               r: &mut axal::Runtime,
+               k: &mut Keypad,
                c: &mut Context,
                m: &mut mmu::Mmu,
-               opcode: Opcode)
+               instruction: Instruction)
                -> bool;
 }
 
@@ -164,21 +303,96 @@ pub struct Interpreter {
     // Memory management unit (incl. RAM)
     mmu: mmu::Mmu,
 
-    // Active runtime (CHIP-8, CHIP-8X, etc.)
+    // Active runtime (CHIP-8, CHIP-8X, etc.) and the mode it was selected by
     runtime: Option<Box<Runtime>>,
+    mode: Mode,
 
     // 60 Hz timer that controls DT / ST
+    clock: Clock,
     timer_elapsed: u64,
+    #[cfg(feature = "std")]
     timer_instant: Option<Instant>,
+
+    // Directory used to persist flag registers; flags are not persisted if unset
+    #[cfg(feature = "std")]
+    save_dir: Option<PathBuf>,
+
+    // Hash of the inserted ROM (keys persistent storage)
+    rom_hash: u64,
+
+    // Breakpoints and pause state
+    debugger: Debugger,
+
+    // Instruction trace (when enabled)
+    #[cfg(feature = "std")]
+    tracer: Option<Tracer>,
+
+    // How `run` executes instructions, and the blocks compiled so far
+    execution: Execution,
+    compiler: Compiler,
+
+    // Quirks to use instead of those of the active mode (when set)
+    quirks: Option<Quirks>,
+
+    // Seed of the RNG; a random seed (or without std, a fixed one) is used on reset if
+    // unset
+    seed: Option<u32>,
+
+    // Algorithm of the RNG
+    rng: rng::Algorithm,
+
+    // Palette to use instead of that configured for the ROM (when set)
+    palette: Option<Palette>,
+
+    // Palette configured for the ROM (in the save directory), if any
+    rom_palette: Option<Palette>,
+
+    // Post-processing of the framebuffer (when enabled) and its output
+    post_process: Option<PostProcess>,
+    output: Vec<u8>,
+}
+
+// 64-bit FNV-1a hash of a ROM
+fn rom_hash(buffer: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+
+    for &b in buffer {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+// Runtime of a mode
+// TODO: Support other modes
+fn new_runtime(mode: Mode) -> Box<Runtime> {
+    match mode {
+        Mode::Chip8x => Box::new(chip_8x::Chip8x::default()),
+        Mode::SuperChip => Box::new(super_chip::SuperChip::default()),
+        Mode::XoChip => Box::new(xo_chip::XoChip::default()),
+
+        _ => Box::new(chip_8::Chip8::default()),
+    }
 }
 
 impl Interpreter {
+    // Construct an interpreter that renders its framebuffer in `format`
+    pub fn with_pixel_format(format: pixel::Format) -> Self {
+        let mut interpreter: Interpreter = Default::default();
+        interpreter.context.pixel_format = format;
+
+        interpreter
+    }
+
+    pub fn pixel_format(&self) -> pixel::Format {
+        self.context.pixel_format
+    }
+
     pub fn configure(&mut self) {
-        // The standard screen size is 64x32
-        self.context.screen_width = 64;
-        self.context.screen_height = 32;
-        self.context.screen.resize(self.context.screen_width * self.context.screen_height,
-                                   Default::default());
+        // The standard screen size is 64x32; XO-CHIP has 2 planes
+        let planes = if self.mode == Mode::XoChip { 2 } else { 1 };
+        self.context.display.resize(64, 32, planes);
 
         // TODO: Allow stack_len to be controlled somewhere
         self.context.stack_len = 256;
@@ -280,7 +494,11 @@ impl Interpreter {
         self.mmu.write(0x4E, 0x80);
         self.mmu.write(0x4F, 0x80);
 
-        // TODO: Compatibility flags should be in here
+        // Compatibility quirks
+        self.context.quirks = self.quirks.unwrap_or_else(|| Quirks::for_mode(self.mode));
+
+        // Display colors
+        self.context.palette = self.palette.or(self.rom_palette).unwrap_or_default();
 
         // Configure runtime
         if let Some(ref mut runtime) = self.runtime {
@@ -288,38 +506,94 @@ impl Interpreter {
         }
     }
 
+    #[cfg(feature = "std")]
     pub fn insert_rom(&mut self, filename: &str, mode: Option<Mode>) {
         // Determine mode
         let mode = mode.unwrap_or_else(|| Mode::from_file(filename));
 
-        // Construct runtime
-        // TODO: Support other modes
-        self.runtime = Some(match mode {
-            Mode::Chip8x => Box::new(Default::default(): chip_8x::Chip8x),
-
-            _ => {
-                // TODO: Use XO-CHIP here
-                Box::new(Default::default(): chip_8::Chip8)
-            }
-        });
-
         // Read in ROM
         let mut stream = File::open(filename).unwrap();
         let mut buffer = Vec::new();
         stream.read_to_end(&mut buffer).unwrap();
 
+        self.insert_rom_bytes(&buffer, mode);
+    }
+
+    pub fn insert_rom_bytes(&mut self, buffer: &[u8], mode: Mode) {
+        self.mode = mode;
+
+        // Allocate RAM (a ROM that does not fit is truncated)
+        self.mmu = mmu::Mmu::new(mode);
+        self.compiler.clear();
+
+        // Construct runtime
+        self.runtime = Some(new_runtime(mode));
+
+        self.rom_hash = rom_hash(buffer);
+
         // Insert ROM
         if let Some(ref mut runtime) = self.runtime {
-            runtime.insert_rom(&mut self.mmu, &buffer);
+            runtime.insert_rom(&mut self.mmu, buffer);
         }
 
+        self.rom_palette = None;
+        self.load_saved();
+
         // Configure interpreter (and associated runtime)
         // The hook is here to allow for ROMs to eventually control
         // any parameters here.
         self.configure();
     }
 
+    // Load the flags and palette saved for the ROM (in the save directory)
+    #[cfg(feature = "std")]
+    fn load_saved(&mut self) {
+        // Load persistent flags
+        if let (Some(dir), Some(runtime)) = (self.save_dir.as_ref(), self.runtime.as_mut()) {
+            if let Some(flags) = runtime.flags() {
+                if let Err(err) = flags::load(dir, self.rom_hash, flags) {
+                    warning!("failed to load flags: {}", err);
+                }
+            }
+        }
+
+        // Load the palette configured for the ROM
+        if let Some(ref dir) = self.save_dir {
+            match palette::load(dir, self.rom_hash) {
+                Ok(palette) => self.rom_palette = palette,
+                Err(err) => warning!("failed to load palette: {}", err),
+            }
+        }
+    }
+
+    // Without std, nothing is saved
+    #[cfg(not(feature = "std"))]
+    fn load_saved(&mut self) {}
+
+    // Flush persistent flags (to the save directory)
+    #[cfg(feature = "std")]
+    fn save_flags(&mut self) {
+        if let (Some(dir), Some(runtime)) = (self.save_dir.as_ref(), self.runtime.as_mut()) {
+            if let Some(flags) = runtime.flags() {
+                if let Err(err) = flags::save(dir, self.rom_hash, flags) {
+                    warning!("failed to save flags: {}", err);
+                }
+            }
+        }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn save_flags(&mut self) {}
+
+    // Set the directory used to persist flag registers (`None` disables persistence)
+    #[cfg(feature = "std")]
+    pub fn set_save_dir(&mut self, dir: Option<PathBuf>) {
+        self.save_dir = dir;
+    }
+
     pub fn remove_rom(&mut self) {
+        self.save_flags();
+
         // Wipe out RAM
         self.mmu.clear();
 
@@ -331,13 +605,152 @@ impl Interpreter {
         // Reset context
         self.context.reset();
 
+        // Reseed RNG
+        self.context.rng = Rng::new(self.rng, self.seed.unwrap_or_else(rng::random_seed));
+
         // Reset associated runtime
         if let Some(ref mut runtime) = self.runtime {
             runtime.reset(&mut self.context);
         }
     }
 
-    pub fn run_next(&mut self, r: &mut axal::Runtime) {
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    // Hash of the inserted ROM
+    pub fn rom_hash(&self) -> u64 {
+        self.rom_hash
+    }
+
+    // Seed the RNG (now and on every reset); `None` seeds it randomly on reset (with std)
+    pub fn set_seed(&mut self, seed: Option<u32>) {
+        self.seed = seed;
+
+        if let Some(seed) = seed {
+            self.context.rng = Rng::new(self.rng, seed);
+        }
+    }
+
+    pub fn seed(&self) -> Option<u32> {
+        self.seed
+    }
+
+    // Select the RNG algorithm; the RNG is reseeded (with the seed, if set)
+    pub fn set_rng(&mut self, algorithm: rng::Algorithm) {
+        self.rng = algorithm;
+        self.context.rng = Rng::new(algorithm, self.seed.unwrap_or_else(rng::random_seed));
+    }
+
+    pub fn rng(&self) -> rng::Algorithm {
+        self.rng
+    }
+
+    // Override the quirks of the active mode (`None` reverts to the defaults of the mode)
+    pub fn set_quirks(&mut self, quirks: Option<Quirks>) {
+        self.quirks = quirks;
+        self.context.quirks = quirks.unwrap_or_else(|| Quirks::for_mode(self.mode));
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.context.quirks
+    }
+
+    // Override the palette (`None` reverts to that configured for the ROM, or the default)
+    pub fn set_palette(&mut self, palette: Option<Palette>) {
+        self.palette = palette;
+        self.context.palette = palette.or(self.rom_palette).unwrap_or_default();
+        self.context.display.mark_all_dirty();
+    }
+
+    pub fn palette(&self) -> Palette {
+        self.context.palette
+    }
+
+    // Enable (or disable with `None`) instruction tracing
+    #[cfg(feature = "std")]
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        self.tracer = tracer;
+    }
+
+    pub fn debugger(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.debugger.is_paused()
+    }
+
+    // Step over the instruction at PC (`CALL` runs until the subroutine returns)
+    pub fn step_over(&mut self) {
+        let pc = self.context.pc;
+        let is_call = (self.mmu.read(pc) >> 4) == 0x2;
+
+        self.debugger.step_over(pc, self.context.sp, is_call);
+    }
+
+    // Run until the current subroutine returns
+    pub fn step_out(&mut self) {
+        self.debugger.step_out(self.context.sp);
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            v: self.context.v,
+            i: self.context.i,
+            pc: self.context.pc,
+            sp: self.context.sp,
+            dt: self.context.dt,
+            st: self.context.st,
+        }
+    }
+
+    // Size of RAM; addresses wrap around at the end
+    pub fn memory_size(&self) -> usize {
+        self.mmu.size()
+    }
+
+    // Inspect RAM
+    pub fn read_memory(&self, address: usize) -> u8 {
+        self.mmu.peek(address)
+    }
+
+    pub fn write_memory(&mut self, address: usize, value: u8) {
+        self.mmu.write(address, value);
+    }
+
+    pub fn set_registers(&mut self, registers: Registers) {
+        self.context.v = registers.v;
+        self.context.i = registers.i;
+        self.context.pc = registers.pc;
+        self.context.sp = registers.sp;
+        self.context.dt = registers.dt;
+        self.context.st = registers.st;
+    }
+
+    pub fn clock(&self) -> Clock {
+        self.clock
+    }
+
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+        self.timer_elapsed = 0;
+        self.stop_timer();
+    }
+
+    // Forget the timer reference (so the time until the next instruction is not counted)
+    #[cfg(feature = "std")]
+    fn stop_timer(&mut self) {
+        self.timer_instant = None;
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn stop_timer(&mut self) {}
+
+    // Clock DT / ST by the wall-clock time elapsed since the last instruction (if the
+    // clock is real-time)
+    #[cfg(feature = "std")]
+    fn clock_timers(&mut self) {
         // If timer point reference is non-zero; check elapsed and
         // clock ST / DT
         if let Some(timer_instant) = self.timer_instant {
@@ -348,36 +761,527 @@ impl Interpreter {
             // 1/60 s => 16_666_666 ns
             if self.timer_elapsed >= 16_666_666 {
                 self.timer_elapsed -= 16_666_666;
+                self.tick();
+            }
+        }
+    }
 
-                if self.context.dt > 0 {
-                    self.context.dt -= 1;
-                }
+    #[cfg(not(feature = "std"))]
+    fn clock_timers(&mut self) {}
 
-                if self.context.st > 0 {
-                    self.context.st -= 1;
-                }
+    // Update timer point reference
+    #[cfg(feature = "std")]
+    fn start_timer(&mut self) {
+        if self.clock == Clock::RealTime {
+            self.timer_instant = Some(Instant::now());
+        }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn start_timer(&mut self) {}
+
+    // Trace the instruction about to execute (when tracing)
+    #[cfg(feature = "std")]
+    fn trace(&mut self) {
+        if let Some(mut tracer) = self.tracer.take() {
+            let pc = self.context.pc;
+            let bytes = [self.mmu.peek(pc),
+                         self.mmu.peek(pc + 1),
+                         self.mmu.peek(pc + 2),
+                         self.mmu.peek(pc + 3)];
+
+            match tracer.record(self.mode, &self.context, &bytes) {
+                Ok(()) => self.tracer = Some(tracer),
+                Err(err) => warning!("tracing disabled: {}", err),
             }
         }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn trace(&mut self) {}
+
+    #[cfg(feature = "std")]
+    fn is_tracing(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn is_tracing(&self) -> bool {
+        false
+    }
+
+    // Clock DT / ST by 1/60 s
+    pub fn tick(&mut self) {
+        if self.context.dt > 0 {
+            self.context.dt -= 1;
+        }
+
+        if self.context.st > 0 {
+            self.context.st -= 1;
+        }
+
+        self.context.rng.tick();
+    }
+
+    pub fn run_next(&mut self, k: &mut Keypad) {
+        // Debugger: Stop before the next instruction if paused or a breakpoint is hit
+        if self.debugger.check(self.context.pc, self.context.sp) {
+            // Timers are frozen while paused
+            self.stop_timer();
+
+            return;
+        }
+
+        self.clock_timers();
+        self.trace();
 
-        // Read next 16-bit opcode (and increment PC)
-        let opcode = Opcode::read_next(&mut self.context.pc, &mut self.mmu);
+        // Fetch next instruction (and increment PC)
+        let pc = self.context.pc;
+        let instruction = self.mmu.fetch(pc);
+        self.context.pc += instruction.len();
 
-        // Execute opcode (with runtime)
+        // Execute instruction (with runtime)
         if let Some(ref mut runtime) = self.runtime {
-            if !runtime.execute(r, &mut self.context, &mut self.mmu, opcode) {
-                panic!("unhandled opcode: {}", opcode);
+            if !runtime.execute(k, &mut self.context, &mut self.mmu, instruction) {
+                // Unhandled instruction; pause (at the offending instruction) instead of
+                // running off into data
+                warning!("unhandled instruction {} at ${:03X}", instruction, pc);
+
+                self.context.pc = pc;
+                self.debugger.pause();
             }
         }
 
-        // Update timer point reference
-        self.timer_instant = Some(Instant::now());
+        self.start_timer();
     }
 
-    pub fn screen_as_framebuffer(&mut self) -> (&[u8], usize, usize) {
+    // Select how `run` executes instructions
+    pub fn set_execution(&mut self, execution: Execution) {
+        self.execution = execution;
+    }
+
+    pub fn execution(&self) -> Execution {
+        self.execution
+    }
+
+    // Use a recompiled ROM (see `recompile`) for the blocks it covers when compiling
+    //  Anything it does not cover (or code that no longer matches it) is compiled as usual.
+    pub fn set_recompiled(&mut self, recompiled: Option<Recompiled>) {
+        self.compiler.set_recompiled(recompiled);
+    }
+
+    // Run (at most) `count` instructions; stops early if execution pauses
+    //  Compiled blocks are only used while nothing needs to watch every instruction:
+    //  on the external clock, without a tracer, and with the debugger idle.
+    pub fn run(&mut self, k: &mut Keypad, count: usize) {
+        let mut executed = 0;
+
+        while executed < count {
+            let compile = self.execution != Execution::Interpret && self.runtime.is_some() &&
+                          self.clock == Clock::External &&
+                          !self.is_tracing() && self.debugger.is_idle();
+
+            let n = if compile {
+                self.run_block(k, count - executed)
+            } else {
+                0
+            };
+
+            // The instruction at PC is not compiled (eg. it draws or reads a key)
+            if n == 0 {
+                self.run_next(k);
+                executed += 1;
+            } else {
+                executed += n;
+            }
+
+            if self.is_paused() {
+                break;
+            }
+        }
+    }
+
+    // Run (at most `budget` instructions of) the compiled block at PC; returns the
+    // number of instructions run
+    fn run_block(&mut self, k: &mut Keypad, budget: usize) -> usize {
+        if self.execution != Execution::Differential {
+            return self.compiler.run(&mut self.context, &mut self.mmu, budget);
+        }
+
+        // Differential: run the same instructions through the interpreter (from the same
+        // state) and compare
+        let pc = self.context.pc;
+        let before = self.snapshot();
+
+        let n = self.compiler.run(&mut self.context, &mut self.mmu, budget);
+        if n == 0 {
+            return 0;
+        }
+
+        let compiled = self.snapshot();
+        self.restore(&before);
+
+        for _ in 0..n {
+            self.run_next(k);
+        }
+
+        if self.snapshot() != compiled {
+            panic!("compiled block at ${:03X} differs from the interpreter after {} \
+                    instructions",
+                   pc,
+                   n);
+        }
+
+        n
+    }
+
+    // Capture the complete machine state (context, RAM, and runtime) and the palette
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut w: state::Writer = Default::default();
+
+        self.save_machine_state(&mut w);
+        self.context.palette.save_state(&mut w);
+
+        w.into_inner()
+    }
+
+    fn save_machine_state(&self, w: &mut state::Writer) {
+        self.context.save_state(w);
+        self.mmu.save_state(w);
+        w.write_u64(self.timer_elapsed);
+
+        if let Some(ref runtime) = self.runtime {
+            runtime.save_state(w);
+        }
+    }
+
+    // 32-bit FNV-1a hash of the machine state (as captured by `snapshot`)
+    //  The palette is left out; it does not affect execution.
+    pub fn checksum(&self) -> u32 {
+        let mut w: state::Writer = Default::default();
+        self.save_machine_state(&mut w);
+
+        let mut hash = 0x811c9dc5u32;
+
+        for b in w.into_inner() {
+            hash ^= b as u32;
+            hash = hash.wrapping_mul(0x01000193);
+        }
+
+        hash
+    }
+
+    // Restore machine state captured by `snapshot`; the same ROM (and mode)
+    // must be loaded. Returns false (leaving the machine as it was) if the snapshot is
+    // truncated or does not fit the active mode.
+    pub fn restore(&mut self, buffer: &[u8]) -> bool {
+        if !self.check_snapshot(buffer) {
+            return false;
+        }
+
+        let mut r = state::Reader::new(buffer);
+
+        self.context.load_state(&mut r);
+        self.mmu.load_state(&mut r);
+        self.timer_elapsed = r.read_u64();
+
+        if let Some(ref mut runtime) = self.runtime {
+            runtime.load_state(&mut r);
+        }
+
+        self.context.palette.load_state(&mut r);
+
+        // Restart the timer reference so time spent "in the past" is not counted
+        self.stop_timer();
+
+        true
+    }
+
+    // Check a snapshot by reading it into scratch state: it must be complete (and no
+    // longer), hold RAM of the size of the active mode, and fit the active runtime
+    fn check_snapshot(&self, buffer: &[u8]) -> bool {
+        let mut r = state::Reader::new(buffer);
+
+        let mut context: Context = Default::default();
+        context.load_state(&mut r);
+
+        if r.read_bytes().len() != self.mode.memory_size() {
+            return false;
+        }
+
+        r.read_u64();
+
+        if self.runtime.is_some() {
+            let mut runtime = new_runtime(self.mode);
+            runtime.load_state(&mut r);
+
+            if !runtime.check_state(&context) {
+                return false;
+            }
+        }
+
+        let mut palette = self.context.palette;
+        palette.load_state(&mut r);
+
+        !r.is_truncated() && r.is_at_end()
+    }
+
+    // Display buffer (one `bool` per dot; set in any plane) and its active resolution
+    pub fn screen(&self) -> (Vec<bool>, usize, usize) {
+        let display = &self.context.display;
+
+        (display.to_dots(), display.width(), display.height())
+    }
+
+    // Display buffer (packed rows per plane)
+    pub fn display(&self) -> &Display {
+        &self.context.display
+    }
+
+    // Update the framebuffer (and post-process it, if enabled) from the display
+    //  Only rows changed since the last update are redrawn; returns the region of the
+    //  framebuffer that changed (`None` if nothing did).
+    pub fn refresh_framebuffer(&mut self) -> Option<Region> {
+        let (width, height) = (self.context.display.width(), self.context.display.height());
+
+        // A framebuffer of another size (eg. after a change of resolution) is redrawn
+        let len = width * height * self.context.pixel_format.bytes_per_pixel();
+        if self.context.framebuffer.len() != len {
+            self.context.framebuffer.resize(len, 0);
+            self.context.display.mark_all_dirty();
+        }
+
+        let (top, bottom) = match self.context.display.dirty_rows() {
+            Some(rows) => rows,
+            None => return None,
+        };
+
         if let Some(ref mut runtime) = self.runtime {
             runtime.update_framebuffer(&mut self.context);
         }
 
-        (&self.context.framebuffer, self.context.screen_width, self.context.screen_height)
+        self.context.display.clean();
+
+        match self.post_process {
+            Some(ref post_process) => {
+                post_process.apply(self.context.pixel_format,
+                                   &self.context.framebuffer,
+                                   width,
+                                   height,
+                                   self.context.palette.colors[0],
+                                   &mut self.output);
+
+                Some(post_process.region(width, height, top, bottom))
+            }
+
+            None => {
+                Some(Region {
+                    x: 0,
+                    y: top,
+                    width: width,
+                    height: bottom - top,
+                })
+            }
+        }
+    }
+
+    // Framebuffer (post-processed, if enabled) as of the last refresh, and its size
+    pub fn framebuffer(&self) -> (&[u8], usize, usize) {
+        let (width, height) = (self.context.display.width(), self.context.display.height());
+
+        match self.post_process {
+            Some(ref post_process) => {
+                let (width, height) = post_process.output_size(width, height);
+                (&self.output, width, height)
+            }
+
+            None => (&self.context.framebuffer, width, height),
+        }
+    }
+
+    // Refresh and return the framebuffer (post-processed, if enabled) and its size
+    pub fn screen_as_framebuffer(&mut self) -> (&[u8], usize, usize) {
+        self.refresh_framebuffer();
+        self.framebuffer()
+    }
+
+    // Enable (or disable with `None`) post-processing of the framebuffer
+    pub fn set_post_process(&mut self, post_process: Option<PostProcess>) {
+        self.post_process = post_process;
+        self.context.display.mark_all_dirty();
+    }
+
+    // Size of the framebuffer for a `width` x `height` screen
+    pub fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        match self.post_process {
+            Some(ref post_process) => post_process.output_size(width, height),
+            None => (width, height),
+        }
+    }
+
+    // Write the current screen as an image; each dot becomes a `scale` x `scale` block
+    #[cfg(feature = "std")]
+    pub fn screenshot(&mut self,
+                      out: &mut Write,
+                      format: screenshot::Format,
+                      scale: usize)
+                      -> io::Result<()> {
+        match format {
+            screenshot::Format::Png => {
+                let format = self.context.pixel_format;
+                let (framebuffer, width, height) = self.screen_as_framebuffer();
+                screenshot::write_png(out, framebuffer, format, width, height, scale)
+            }
+
+            screenshot::Format::Pbm => {
+                let (screen, width, height) = self.screen();
+                screenshot::write_pbm(out, &screen, width, height, scale)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use interpreter::{Interpreter, Mode};
+    use test_host::{self, FakeHost};
+
+    // $200  LD V0, 1
+    // $202  CALL $208
+    // $204  LD V1, 2
+    // $206  JP $206
+    // $208  ADD V0, 1
+    // $20A  ADD V0, 1
+    // $20C  RET
+    const ROM: [u8; 14] = [0x60, 0x01, 0x22, 0x08, 0x61, 0x02, 0x12, 0x06, 0x70, 0x01, 0x70,
+                           0x01, 0x00, 0xEE];
+
+    // Run (until paused) and return PC and V0
+    fn run(interpreter: &mut Interpreter) -> (usize, u8) {
+        let mut host: FakeHost = Default::default();
+        interpreter.run(&mut host, 100);
+
+        let registers = interpreter.registers();
+        (registers.pc, registers.v[0])
+    }
+
+    #[test]
+    fn breakpoints() {
+        let mut interpreter = test_host::interpreter(Mode::Chip8, &ROM, None);
+        interpreter.debugger().add_breakpoint(0x20A);
+        interpreter.debugger().add_breakpoint(0x204);
+
+        assert_eq!(run(&mut interpreter), (0x20A, 2));
+        assert!(interpreter.is_paused());
+
+        // Paused; nothing runs until resumed
+        assert_eq!(run(&mut interpreter), (0x20A, 2));
+
+        interpreter.debugger().resume();
+        assert_eq!(run(&mut interpreter), (0x204, 3));
+
+        // Without breakpoints, execution runs freely (into the loop at $206)
+        interpreter.debugger().clear_breakpoints();
+        interpreter.debugger().resume();
+        assert_eq!(run(&mut interpreter), (0x206, 3));
+        assert!(!interpreter.is_paused());
+        assert_eq!(interpreter.registers().v[1], 2);
+    }
+
+    #[test]
+    fn step_over() {
+        let mut interpreter = test_host::interpreter(Mode::Chip8, &ROM, None);
+        interpreter.debugger().pause();
+
+        interpreter.debugger().step_into();
+        assert_eq!(run(&mut interpreter), (0x202, 1));
+
+        // The whole subroutine runs; execution pauses after the `CALL`
+        interpreter.step_over();
+        assert_eq!(run(&mut interpreter), (0x204, 3));
+        assert_eq!(interpreter.registers().sp, 0);
+
+        interpreter.step_over();
+        assert_eq!(run(&mut interpreter), (0x206, 3));
+        assert_eq!(interpreter.registers().v[1], 2);
+    }
+
+    #[test]
+    fn step_into() {
+        let mut interpreter = test_host::interpreter(Mode::Chip8, &ROM, None);
+        interpreter.debugger().add_breakpoint(0x202);
+        assert_eq!(run(&mut interpreter), (0x202, 1));
+
+        interpreter.debugger().step_into();
+        assert_eq!(run(&mut interpreter), (0x208, 1));
+        assert_eq!(interpreter.registers().sp, 1);
+    }
+
+    #[test]
+    fn step_out() {
+        let mut interpreter = test_host::interpreter(Mode::Chip8, &ROM, None);
+        interpreter.debugger().add_breakpoint(0x20A);
+        assert_eq!(run(&mut interpreter), (0x20A, 2));
+
+        interpreter.step_out();
+        assert_eq!(run(&mut interpreter), (0x204, 3));
+        assert_eq!(interpreter.registers().sp, 0);
+    }
+
+    #[test]
+    fn run_to() {
+        let mut interpreter = test_host::interpreter(Mode::Chip8, &ROM, None);
+        interpreter.debugger().pause();
+
+        interpreter.debugger().run_to(0x20C);
+        assert_eq!(run(&mut interpreter), (0x20C, 3));
+        assert!(interpreter.is_paused());
+
+        // The target is cleared once reached
+        interpreter.debugger().resume();
+        assert_eq!(run(&mut interpreter), (0x206, 3));
+        assert!(!interpreter.is_paused());
+    }
+
+    #[test]
+    fn restore() {
+        let mut interpreter = test_host::interpreter(Mode::Chip8, &ROM, None);
+        let snapshot = interpreter.snapshot();
+
+        run(&mut interpreter);
+        let after = interpreter.snapshot();
+
+        // A truncated (or padded) snapshot is rejected and leaves the machine alone
+        assert!(!interpreter.restore(&snapshot[..snapshot.len() - 1]));
+
+        let mut padded = snapshot.clone();
+        padded.push(0);
+        assert!(!interpreter.restore(&padded));
+        assert!(interpreter.snapshot() == after);
+
+        assert!(interpreter.restore(&snapshot));
+        assert!(interpreter.snapshot() == snapshot);
+    }
+
+    #[test]
+    fn restore_foreign() {
+        let mut interpreter = test_host::interpreter(Mode::Chip8x, &ROM, None);
+        let before = interpreter.snapshot();
+
+        // Snapshots of other modes
+        let chip_8 = test_host::interpreter(Mode::Chip8, &ROM, None).snapshot();
+        let xo_chip = test_host::interpreter(Mode::XoChip, &ROM, None).snapshot();
+        assert!(!interpreter.restore(&chip_8));
+        assert!(!interpreter.restore(&xo_chip));
+
+        // A color lens (followed by the palette, 12 colors) one byte short of the display
+        let lens = before.len() - 12 * 4 - 64 * 32;
+        let mut short = before[..lens - 4].to_vec();
+        short.extend_from_slice(&[0xFF, 0x07, 0, 0]);
+        short.extend_from_slice(&before[lens + 1..]);
+        assert!(!interpreter.restore(&short));
+
+        assert!(interpreter.snapshot() == before);
+        interpreter.refresh_framebuffer();
     }
 }
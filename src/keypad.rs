@@ -0,0 +1,22 @@
+// Source of the state of the 16-key hex keypad
+pub trait Keypad {
+    // True if hex key `key` (0-F) is pressed
+    fn key_state(&mut self, key: u8) -> bool;
+}
+
+// Keypad with a fixed state (bit N = key N)
+#[cfg(feature = "std")]
+pub struct KeyState(pub u16);
+
+#[cfg(feature = "std")]
+impl Keypad for KeyState {
+    fn key_state(&mut self, key: u8) -> bool {
+        (self.0 >> (key & 0xF)) & 1 != 0
+    }
+}
+
+// Read the state of every key (bit N = key N)
+#[cfg(feature = "std")]
+pub fn sample(k: &mut Keypad) -> u16 {
+    (0..16).fold(0, |keys, key| keys | ((k.key_state(key) as u16) << key))
+}
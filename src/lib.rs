@@ -1,63 +1,523 @@
-#![feature(type_ascription)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(all(feature = "axal", not(feature = "std")))]
+compile_error!("the `axal` feature requires the `std` feature");
+
+#[cfg(feature = "axal")]
 #[macro_use]
 extern crate axal;
 
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+
+#[cfg(feature = "std")]
 extern crate rand;
 
+// Without std, `std::` paths resolve to `core` and `alloc` (which is all the interpreter
+// uses)
+#[cfg(not(feature = "std"))]
+mod std {
+    pub use core::*;
+    pub use alloc::{boxed, collections, vec};
+}
+
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+// Print a warning (dropped without std)
+macro_rules! warning {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "std")]
+        eprintln!("warning: {}", format_args!($($arg)*));
+
+        #[cfg(not(feature = "std"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+pub use debugger::{Debugger, Registers};
+#[cfg(feature = "std")]
+pub use disassembler::{disassemble, Syntax};
+#[cfg(feature = "std")]
+pub use assembler::{assemble, Assembly};
+pub use compiler::{Execution, Recompiled, RecompiledBlock};
+pub use host::Host;
+#[cfg(feature = "axal")]
+pub use host::AxalHost;
+pub use interpreter::{Clock, Context, Interpreter, Mode};
+pub use keypad::Keypad;
+pub use mmu::Mmu;
+#[cfg(feature = "std")]
+pub use movie::{Frame as MovieFrame, Movie};
+pub use opcode::Instruction;
+pub use palette::Palette;
+pub use pixel::{Format as PixelFormat, Region};
+pub use post_process::{Effect as PostEffect, PostProcess, Rotation};
+pub use quirks::Quirks;
+#[cfg(feature = "std")]
+pub use recompiler::recompile;
+pub use rng::Algorithm as RngAlgorithm;
+#[cfg(feature = "std")]
+pub use screenshot::Format as ScreenshotFormat;
+#[cfg(feature = "std")]
+pub use trace::{Filter as TraceFilter, Format as TraceFormat, Tracer};
+
 mod mmu;
 mod opcode;
+mod compiler;
+mod state;
+mod debugger;
+mod display;
+mod keypad;
+mod host;
+mod quirks;
+mod palette;
+mod pixel;
+mod post_process;
+mod rng;
+
+// Files, images, and tools (std only)
+#[cfg(feature = "std")]
+mod recompiler;
+#[cfg(feature = "std")]
+mod rewind;
+#[cfg(feature = "std")]
+mod flags;
+#[cfg(feature = "std")]
+mod disassembler;
+#[cfg(feature = "std")]
+mod assembler;
+#[cfg(feature = "std")]
+mod trace;
+#[cfg(feature = "std")]
+mod screenshot;
+#[cfg(feature = "std")]
+mod gif;
+#[cfg(feature = "std")]
+mod movie;
+
+#[cfg(test)]
+mod test_host;
 
 mod chip_8;
 mod chip_8x;
 mod super_chip;
+mod xo_chip;
 
 mod interpreter;
 
+// Frontend core: the interpreter plus rewind, recording, and input movies (std only)
+#[cfg(feature = "std")]
 #[derive(Default)]
 pub struct Core {
     interpreter: interpreter::Interpreter,
+
+    // Per-frame snapshots used to step backwards in time (when enabled)
+    rewind: Option<rewind::Rewind>,
+
+    // Animated GIF of every frame (while recording)
+    recorder: Option<gif::Recorder>,
+
+    // Input movie being recorded or played back
+    movie: Option<movie::Session>,
+
+    // Region of the framebuffer changed by the last frame
+    changed: Option<Region>,
+
+    // RNG seed and algorithm, quirks, and clock to return to once a movie stops
+    seed: Option<u32>,
+    rng: RngAlgorithm,
+    quirks: Option<Quirks>,
+    clock: Clock,
+}
+
+#[cfg(feature = "std")]
+impl Core {
+    // Construct a core that renders its framebuffer in `format` (R3_G3_B2 by default)
+    pub fn with_pixel_format(format: PixelFormat) -> Self {
+        Core {
+            interpreter: interpreter::Interpreter::with_pixel_format(format),
+            ..Default::default()
+        }
+    }
+
+    // Enable rewind; holds at most `depth` frames using at most `budget` bytes
+    pub fn enable_rewind(&mut self, depth: usize, budget: usize) {
+        self.rewind = Some(rewind::Rewind::new(depth, budget));
+    }
+
+    pub fn disable_rewind(&mut self) {
+        self.rewind = None;
+    }
+
+    // Breakpoints, pause / resume, single-step, and run-to-cursor
+    pub fn debugger(&mut self) -> &mut Debugger {
+        self.interpreter.debugger()
+    }
+
+    // Step over the next instruction (a `CALL` runs until it returns)
+    pub fn step_over(&mut self) {
+        self.interpreter.step_over();
+    }
+
+    // Run until the current subroutine returns
+    pub fn step_out(&mut self) {
+        self.interpreter.step_out();
+    }
+
+    // Inspect V0-VF, I, PC, SP, DT, and ST
+    pub fn registers(&self) -> Registers {
+        self.interpreter.registers()
+    }
+
+    // Edit V0-VF, I, PC, SP, DT, and ST
+    pub fn set_registers(&mut self, registers: Registers) {
+        self.interpreter.set_registers(registers);
+    }
+
+    // Override the compatibility quirks of the active mode (`None` reverts to its defaults)
+    pub fn set_quirks(&mut self, quirks: Option<Quirks>) {
+        self.quirks = quirks;
+        self.interpreter.set_quirks(quirks);
+    }
+
+    // Region of the framebuffer changed by the last frame (`None` if unchanged, in which
+    // case the video refresh was skipped)
+    pub fn changed_region(&self) -> Option<Region> {
+        self.changed
+    }
+
+    // Override the palette (`None` reverts to that configured for the ROM, or the default)
+    pub fn set_palette(&mut self, palette: Option<Palette>) {
+        self.interpreter.set_palette(palette);
+    }
+
+    // Enable (or disable with `None`) post-processing (scaling, rotation, and effects)
+    // of the framebuffer given to the frontend
+    pub fn set_post_process(&mut self, post_process: Option<PostProcess>) {
+        self.interpreter.set_post_process(post_process);
+    }
+
+    // Seed the RNG (now and on every reset); `None` seeds it randomly on reset
+    pub fn set_seed(&mut self, seed: Option<u32>) {
+        self.seed = seed;
+        self.interpreter.set_seed(seed);
+    }
+
+    // Select the RNG algorithm (used by `RND`)
+    pub fn set_rng(&mut self, algorithm: RngAlgorithm) {
+        self.rng = algorithm;
+        self.interpreter.set_rng(algorithm);
+    }
+
+    // Enable (or disable with `None`) instruction tracing
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        self.interpreter.set_tracer(tracer);
+    }
+
+    // Set the directory where SUPER-CHIP / XO-CHIP flag registers are persisted
+    pub fn set_save_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.interpreter.set_save_dir(Some(dir.into()));
+    }
+
+    // Write the current screen as an image; each dot becomes a `scale` x `scale` block
+    pub fn screenshot(&mut self,
+                      out: &mut Write,
+                      format: ScreenshotFormat,
+                      scale: usize)
+                      -> io::Result<()> {
+        self.interpreter.screenshot(out, format, scale)
+    }
+
+    // Start recording every frame to an animated GIF (stopping any recording in progress)
+    //  Each dot becomes a `scale` x `scale` block.
+    pub fn start_recording(&mut self, out: Box<Write>, scale: usize) -> io::Result<()> {
+        self.stop_recording()?;
+        let colors = self.interpreter.palette().colors(self.interpreter.mode()).to_vec();
+        self.recorder = Some(gif::Recorder::new(out,
+                                                colors,
+                                                self.interpreter.pixel_format(),
+                                                scale));
+
+        Ok(())
+    }
+
+    // Stop recording (and finish the GIF)
+    pub fn stop_recording(&mut self) -> io::Result<()> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    // Start recording an input movie; the interpreter is reset (a movie starts from
+    // power-on) with the RNG seeded from `seed` (or the configured seed, or randomly)
+    pub fn start_movie_recording(&mut self, seed: Option<u32>) {
+        let seed = seed.or(self.seed).unwrap_or_else(rand::random);
+        let movie = Movie::new(self.interpreter.mode(),
+                               self.interpreter.rom_hash(),
+                               seed,
+                               self.interpreter.rng(),
+                               self.interpreter.quirks());
+
+        self.begin_movie(&movie);
+        self.movie = Some(movie::Session::Recording(movie));
+    }
+
+    // Play back an input movie from power-on; the ROM it was recorded with must be loaded
+    //  Live input is ignored until the movie ends; the movie stays loaded (and timers
+    //  are clocked per frame) until `stop_movie`. Rewind is unavailable during playback.
+    pub fn play_movie(&mut self, movie: Movie) -> io::Result<()> {
+        if movie.rom_hash != self.interpreter.rom_hash() || movie.mode != self.interpreter.mode() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "movie was recorded with a different ROM or mode"));
+        }
+
+        self.begin_movie(&movie);
+        self.movie = Some(movie::Session::Playback {
+            movie: movie,
+            frame: 0,
+            desync: None,
+        });
+
+        Ok(())
+    }
+
+    // Stop recording or playing back; returns the movie
+    pub fn stop_movie(&mut self) -> Option<Movie> {
+        let movie = match self.movie.take() {
+            Some(movie::Session::Recording(movie)) |
+            Some(movie::Session::Playback { movie, .. }) => movie,
+            None => return None,
+        };
+
+        // Return to the configured RNG, quirks, and clock
+        self.interpreter.set_seed(self.seed);
+        self.interpreter.set_rng(self.rng);
+        self.interpreter.set_quirks(self.quirks);
+        self.interpreter.set_clock(self.clock);
+
+        Some(movie)
+    }
+
+    // First frame of the movie being played back where the machine state diverged
+    // from the recording (if any)
+    pub fn movie_desync(&self) -> Option<usize> {
+        match self.movie {
+            Some(movie::Session::Playback { desync, .. }) => desync,
+            _ => None,
+        }
+    }
+
+    fn begin_movie(&mut self, movie: &Movie) {
+        // A movie in progress is stopped first so the configured settings are kept
+        self.stop_movie();
+        self.clock = self.interpreter.clock();
+
+        self.interpreter.set_quirks(Some(movie.quirks));
+        self.interpreter.set_rng(movie.rng);
+        self.interpreter.set_seed(Some(movie.seed));
+
+        // Timers are clocked once per frame so a movie does not depend on timing
+        self.interpreter.set_clock(Clock::External);
+        self.interpreter.reset();
+
+        if let Some(ref mut rewind) = self.rewind {
+            rewind.clear();
+        }
+    }
+
+    // Interpreter: Run 8 instructions = 1 frame ~> 480 Hz
+    //  The frame is cut short if the debugger pauses execution
+    fn run_instructions(&mut self, k: &mut Keypad) {
+        self.interpreter.run(k, 8);
+    }
+
+    // Step back a _single_ frame; returns false if there is nothing (left) to rewind
+    //  Meant to be called once per frame (instead of `run_frame`) while the host
+    //  holds its rewind key. A movie being recorded loses the frame rewound over; a
+    //  movie being played back cannot be rewound.
+    pub fn rewind(&mut self, h: &mut Host) -> bool {
+        if let Some(movie::Session::Playback { .. }) = self.movie {
+            return false;
+        }
+
+        let snapshot = match self.rewind.as_mut().and_then(|rewind| rewind.pop()) {
+            Some(snapshot) => snapshot,
+            None => return false,
+        };
+
+        self.interpreter.restore(&snapshot);
+
+        if let Some(movie::Session::Recording(ref mut movie)) = self.movie {
+            movie.frames.pop();
+        }
+
+        // Video: Refresh
+        let (framebuffer, width, height) = self.interpreter.screen_as_framebuffer();
+        h.video_refresh(framebuffer, width, height);
+
+        true
+    }
+
+    pub fn reset(&mut self) {
+        self.interpreter.reset();
+
+        if let Some(ref mut rewind) = self.rewind {
+            rewind.clear();
+        }
+    }
+
+    // Load a ROM from a file; the mode is determined from the file extension
+    pub fn insert_rom(&mut self, filename: &str) {
+        self.interpreter.insert_rom(filename, None);
+
+        if let Some(ref mut rewind) = self.rewind {
+            rewind.clear();
+        }
+    }
+
+    pub fn insert_rom_bytes(&mut self, rom: &[u8], mode: Mode) {
+        self.interpreter.insert_rom_bytes(rom, mode);
+
+        if let Some(ref mut rewind) = self.rewind {
+            rewind.clear();
+        }
+    }
+
+    pub fn remove_rom(&mut self) {
+        if let Err(err) = self.stop_recording() {
+            warning!("failed to finish recording: {}", err);
+        }
+
+        self.stop_movie();
+
+        self.interpreter.remove_rom();
+
+        if let Some(ref mut rewind) = self.rewind {
+            rewind.clear();
+        }
+    }
+
+    // Run core for a _single_ frame
+    pub fn run_frame<H: Host>(&mut self, h: &mut H) {
+        // Rewind: Record the state at the start of this frame
+        if let Some(ref mut rewind) = self.rewind {
+            if !self.interpreter.is_paused() {
+                rewind.push(self.interpreter.snapshot());
+            }
+        }
+
+        // Movie: Input for this frame is recorded once up front (or comes from the movie)
+        //  Nothing is recorded (or played) while paused.
+        let keys = match self.movie {
+            _ if self.interpreter.is_paused() => None,
+
+            Some(movie::Session::Recording(_)) => Some(keypad::sample(h)),
+
+            Some(movie::Session::Playback { ref movie, frame, .. }) => {
+                movie.frames.get(frame).map(|frame| frame.keys)
+            }
+
+            None => None,
+        };
+
+        match keys {
+            Some(keys) => self.run_instructions(&mut keypad::KeyState(keys)),
+            None => self.run_instructions(h),
+        }
+
+        if self.movie.is_some() && !self.interpreter.is_paused() {
+            self.interpreter.tick();
+        }
+
+        if let Some(keys) = keys {
+            let checksum = self.interpreter.checksum();
+
+            match self.movie {
+                Some(movie::Session::Recording(ref mut movie)) => {
+                    movie.frames.push(MovieFrame {
+                        keys: keys,
+                        checksum: checksum,
+                    });
+                }
+
+                Some(movie::Session::Playback { ref movie, ref mut frame, ref mut desync }) => {
+                    if desync.is_none() && movie.frames[*frame].checksum != checksum {
+                        warning!("movie desynced at frame {}", *frame);
+                        *desync = Some(*frame);
+                    }
+
+                    *frame += 1;
+                }
+
+                None => {}
+            }
+        }
+
+        // Audio: The buzzer sounds while ST is set
+        h.audio_buzzer(self.interpreter.registers().st > 0);
+
+        // Video: Refresh (skipped if nothing changed)
+        self.changed = self.interpreter.refresh_framebuffer();
+
+        let (framebuffer, width, height) = self.interpreter.framebuffer();
+        if self.changed.is_some() {
+            h.video_refresh(framebuffer, width, height);
+        }
+
+        // Recording: Capture the frame
+        if let Some(mut recorder) = self.recorder.take() {
+            match recorder.record(framebuffer, width, height) {
+                Ok(()) => self.recorder = Some(recorder),
+                Err(err) => warning!("recording stopped: {}", err),
+            }
+        }
+    }
 }
 
+// Thin adapter for axal frontends
+#[cfg(feature = "axal")]
 impl axal::Core for Core {
     fn info(&self) -> axal::Info {
+        let (width, height) = self.interpreter.output_size(64, 32);
+        let (max_width, max_height) = self.interpreter.output_size(128, 64);
+
         axal::Info::new("xCHIP", env!("CARGO_PKG_VERSION"))
-            .pixel_format(axal::PixelFormat::R3_G3_B2)
-            .size(64, 32)
-            .max_size(128, 64)
+            .pixel_format(self.interpreter.pixel_format().to_axal())
+            .size(width as u32, height as u32)
+            .max_size(max_width as u32, max_height as u32)
     }
 
     fn reset(&mut self) {
-        self.interpreter.reset();
+        Core::reset(self);
     }
 
     fn rom_insert(&mut self, filename: &str) {
-        self.interpreter.insert_rom(filename, None);
+        self.insert_rom(filename);
     }
 
     fn rom_remove(&mut self) {
-        self.interpreter.remove_rom();
+        self.remove_rom();
     }
 
     // Run core for a _single_ frame
     fn run_next(&mut self, r: &mut axal::Runtime) {
-        // Interpreter: Run 8 instructions = 1 frame ~> 480 Hz
-        for _ in 0..8 {
-            self.interpreter.run_next(r);
-        }
-
-        // Video: Refresh
-        let (framebuffer, width, height) = self.interpreter.screen_as_framebuffer();
-        r.video_refresh(framebuffer, width as u32, height as u32);
+        self.run_frame(&mut AxalHost(r));
     }
 
     // fn serialize() { }
     // fn deserialize() { }
 }
 
-// impl axal::Debug for Core { }
+// NOTE: The debugger is reached through `Core::debugger` (and the stepping methods);
+//       axal has no debugging interface to forward it through
 
 // impl axal::UI (name?) for Core { }
 
 // Generate C API
+#[cfg(feature = "axal")]
 ax_generate_lib!(Core);
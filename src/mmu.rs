@@ -1,46 +1,226 @@
+use std::cmp;
 use std::vec::Vec;
 
+use interpreter::Mode;
+use opcode::Instruction;
+use state;
+
+// Size of a page (in bytes); writes invalidate decoded instructions a page at a time
+pub const PAGE_SIZE: usize = 0x100;
+
 pub struct Mmu {
     ram: Vec<u8>,
+
+    // Instructions are decoded for this mode
+    mode: Mode,
+
+    // Instruction decoded at each address (if fetched since the last write to its page)
+    decoded: Vec<Option<Instruction>>,
+
+    // Pages holding decoded instructions (code pages)
+    code: Vec<bool>,
+
+    // Bumped every time the instructions of a page (or of every page) are invalidated
+    versions: Vec<u32>,
+    generation: u32,
 }
 
 impl Mmu {
+    // Construct with the RAM of `mode` (see `Mode::memory_size`); addresses wrap around
+    // at the end of RAM
+    pub fn new(mode: Mode) -> Self {
+        let mut m = Mmu {
+            ram: vec![0; cmp::max(mode.memory_size(), 1)],
+            mode: mode,
+            decoded: Vec::new(),
+            code: Vec::new(),
+            versions: Vec::new(),
+            generation: 0,
+        };
+
+        m.invalidate_all();
+        m
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn size(&self) -> usize {
+        self.ram.len()
+    }
+
     pub fn clear(&mut self) {
         for b in &mut self.ram {
             *b = 0;
         }
+
+        self.invalidate_all();
     }
 
-    fn extend(&mut self, address: usize) {
-        if address >= self.ram.len() {
-            self.ram.resize(address + 1, 0);
-        }
+    #[inline]
+    fn wrap(&self, address: usize) -> usize {
+        address % self.ram.len()
     }
 
     pub fn read(&mut self, address: usize) -> u8 {
-        self.extend(address);
+        self.peek(address)
+    }
 
-        self.ram[address as usize]
+    // Read without the need for a mutable reference
+    pub fn peek(&self, address: usize) -> u8 {
+        self.ram[self.wrap(address)]
     }
 
     pub fn write(&mut self, address: usize, value: u8) {
-        self.extend(address);
+        let address = self.wrap(address);
+
+        self.ram[address] = value;
+
+        // Self-modifying code: forget the instructions decoded from this page
+        if self.code[address / PAGE_SIZE] {
+            self.invalidate(address / PAGE_SIZE);
+        }
+    }
+
+    // Fetch the instruction at `address` (decoded once, then cached until its page is
+    // written to)
+    #[inline]
+    pub fn fetch(&mut self, address: usize) -> Instruction {
+        if let Some(instruction) = self.decoded.get(address).and_then(|&instruction| instruction) {
+            return instruction;
+        }
+
+        let bytes = [self.peek(address),
+                     self.peek(address + 1),
+                     self.peek(address + 2),
+                     self.peek(address + 3)];
+
+        let instruction = Instruction::decode(self.mode, &bytes).unwrap();
+        let end = address + instruction.len();
+
+        // Instructions that wrap around the end of RAM are not cached
+        if end <= self.ram.len() {
+            self.decoded[address] = Some(instruction);
+            self.mark_code(address, end);
+        }
+
+        instruction
+    }
+
+    // Mark the pages of `address..end` as code pages (so writes to them invalidate what
+    // was decoded from them)
+    pub fn mark_code(&mut self, address: usize, end: usize) {
+        let end = cmp::min(end, self.ram.len());
+
+        for page in (address / PAGE_SIZE)..((end + PAGE_SIZE - 1) / PAGE_SIZE) {
+            self.code[page] = true;
+        }
+    }
+
+    // Whether RAM at `address` holds `bytes`
+    pub fn matches(&self, address: usize, bytes: &[u8]) -> bool {
+        bytes.iter().enumerate().all(|(i, &b)| self.peek(address + i) == b)
+    }
+
+    // Forget the instructions decoded from page `page` (including any that start at the
+    // end of the page before)
+    fn invalidate(&mut self, page: usize) {
+        let start = (page * PAGE_SIZE).saturating_sub(3);
+        let end = cmp::min((page + 1) * PAGE_SIZE, self.decoded.len());
+
+        for opcode in &mut self.decoded[start..end] {
+            *opcode = None;
+        }
+
+        self.code[page] = false;
+        self.versions[page] = self.versions[page].wrapping_add(1);
+    }
+
+    fn invalidate_all(&mut self) {
+        let pages = (self.ram.len() + PAGE_SIZE - 1) / PAGE_SIZE;
+
+        self.decoded = vec![None; self.ram.len()];
+        self.code = vec![false; pages];
+        self.versions = vec![0; pages];
+        self.generation = self.generation.wrapping_add(1);
+    }
 
-        self.ram[address as usize] = value;
+    // Version of the code in the page of `address`; changes whenever the instructions
+    // decoded from the page are invalidated (eg. to check that code compiled from the
+    // page is current)
+    pub fn code_version(&self, address: usize) -> u64 {
+        let page = self.wrap(address) / PAGE_SIZE;
+
+        ((self.generation as u64) << 32) | (self.versions[page] as u64)
     }
 
-    #[allow(needless_range_loop, unknown_lints)]
+    // Write `buffer` starting at `address`; anything past the end of RAM is dropped
     pub fn write_all(&mut self, address: usize, buffer: &[u8]) {
-        self.extend(address + buffer.len());
+        if address >= self.ram.len() {
+            return;
+        }
+
+        let len = cmp::min(buffer.len(), self.ram.len() - address);
 
-        for i in 0..(buffer.len()) {
-            self.ram[address + i] = buffer[i];
+        self.ram[address..address + len].copy_from_slice(&buffer[..len]);
+        self.invalidate_all();
+    }
+
+    pub fn save_state(&self, w: &mut state::Writer) {
+        w.write_bytes(&self.ram);
+    }
+
+    pub fn load_state(&mut self, r: &mut state::Reader) {
+        let ram = r.read_bytes();
+
+        // RAM never changes size (a truncated or foreign snapshot leaves it as it was)
+        if ram.len() == self.ram.len() {
+            self.ram = ram.to_vec();
         }
+
+        self.invalidate_all();
     }
 }
 
 impl Default for Mmu {
     fn default() -> Self {
-        Mmu { ram: Default::default() }
+        Mmu::new(Default::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use interpreter::Mode;
+    use opcode::Instruction;
+    use super::Mmu;
+
+    #[test]
+    fn fetch() {
+        let mut m: Mmu = Default::default();
+        m.write_all(0x200, &[0x12, 0x34, 0x00, 0xE0]);
+        m.write(0x2FF, 0xA1);
+
+        assert_eq!(m.fetch(0x200), Instruction::Jp(0x234));
+        assert_eq!(m.fetch(0x2FF), Instruction::LdI(0x100));
+
+        // Writes to a code page invalidate its instructions (and one spanning into it)
+        m.write(0x201, 0x56);
+        m.write(0x300, 0xBC);
+        assert_eq!(m.fetch(0x200), Instruction::Jp(0x256));
+        assert_eq!(m.fetch(0x2FF), Instruction::LdI(0x1BC));
+
+        // The last address wraps around to the start of RAM
+        m.write(0xFFF, 0x60);
+        m.write(0x000, 0x07);
+        assert_eq!(m.fetch(0xFFF), Instruction::LdImm(0x0, 0x07));
+
+        // XO-CHIP `F000 NNNN` is invalidated by writes to its address
+        let mut m = Mmu::new(Mode::XoChip);
+        m.write_all(0x2FE, &[0xF0, 0x00, 0x12, 0x34]);
+
+        assert_eq!(m.fetch(0x2FE), Instruction::LdILong(0x1234));
+        m.write(0x301, 0x56);
+        assert_eq!(m.fetch(0x2FE), Instruction::LdILong(0x1256));
     }
 }
@@ -0,0 +1,252 @@
+use std::io::{self, Write};
+
+use interpreter::Mode;
+use quirks::Quirks;
+use rng::Algorithm;
+use state;
+
+const MAGIC: &'static [u8; 4] = b"XCMV";
+const VERSION: u8 = 2;
+
+// A single frame of input
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Frame {
+    // Keypad state (bit N = key N)
+    pub keys: u16,
+
+    // Checksum of the machine state at the end of the frame
+    pub checksum: u32,
+}
+
+// Per-frame keypad input recorded from power-on, and the settings needed to replay it
+#[derive(Clone, PartialEq, Debug)]
+pub struct Movie {
+    pub mode: Mode,
+    pub rom_hash: u64,
+    pub seed: u32,
+    pub rng: Algorithm,
+    pub quirks: Quirks,
+    pub frames: Vec<Frame>,
+}
+
+// Movie being recorded or played back (by `Core`)
+pub enum Session {
+    Recording(Movie),
+
+    Playback {
+        movie: Movie,
+
+        // Next frame to play
+        frame: usize,
+
+        // First frame whose checksum did not match the recording
+        desync: Option<usize>,
+    },
+}
+
+const MODES: [Mode; 6] = [Mode::Chip8,
+                          Mode::HiResChip8,
+                          Mode::Chip8x,
+                          Mode::Chip10,
+                          Mode::SuperChip,
+                          Mode::XoChip];
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Movie {
+    pub fn new(mode: Mode, rom_hash: u64, seed: u32, rng: Algorithm, quirks: Quirks) -> Self {
+        Movie {
+            mode: mode,
+            rom_hash: rom_hash,
+            seed: seed,
+            rng: rng,
+            quirks: quirks,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn write(&self, out: &mut Write) -> io::Result<()> {
+        let mut w: state::Writer = Default::default();
+
+        for &b in MAGIC {
+            w.write_u8(b);
+        }
+
+        w.write_u8(VERSION);
+        w.write_u8(MODES.iter().position(|&mode| mode == self.mode).unwrap_or(0) as u8);
+        w.write_u64(self.rom_hash);
+        w.write_u32(self.seed);
+        w.write_bool(self.rng == Algorithm::Vip);
+
+        w.write_bool(self.quirks.shift_vx);
+        w.write_bool(self.quirks.load_store_keep_i);
+        w.write_bool(self.quirks.jump_vx);
+        w.write_bool(self.quirks.add_i_vf);
+
+        w.write_usize(self.frames.len());
+        for frame in &self.frames {
+            w.write_u16(frame.keys);
+            w.write_u32(frame.checksum);
+        }
+
+        out.write_all(&w.into_inner())
+    }
+
+    pub fn read(buffer: &[u8]) -> io::Result<Self> {
+        let mut r = state::Reader::new(buffer);
+
+        let magic = [r.read_u8(), r.read_u8(), r.read_u8(), r.read_u8()];
+        if magic != *MAGIC {
+            return Err(invalid("not a movie"));
+        }
+
+        if r.read_u8() != VERSION {
+            return Err(invalid("unsupported movie version"));
+        }
+
+        let mode = *MODES.get(r.read_u8() as usize).ok_or_else(|| invalid("unknown mode"))?;
+        let mut movie = Movie::new(mode,
+                                   r.read_u64(),
+                                   r.read_u32(),
+                                   if r.read_bool() {
+                                       Algorithm::Vip
+                                   } else {
+                                       Algorithm::Xorshift
+                                   },
+                                   Quirks {
+                                       shift_vx: r.read_bool(),
+                                       load_store_keep_i: r.read_bool(),
+                                       jump_vx: r.read_bool(),
+                                       add_i_vf: r.read_bool(),
+                                   });
+
+        let len = r.read_usize();
+        for _ in 0..len {
+            if r.is_truncated() {
+                break;
+            }
+
+            movie.frames.push(Frame {
+                keys: r.read_u16(),
+                checksum: r.read_u32(),
+            });
+        }
+
+        if r.is_truncated() {
+            return Err(invalid("truncated movie"));
+        }
+
+        Ok(movie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Core;
+    use interpreter::{Clock, Mode};
+    use quirks::Quirks;
+    use rng::Algorithm;
+    use test_host::FakeHost;
+    use super::{Frame, Movie};
+
+    #[test]
+    fn write_read() {
+        let mut movie = Movie::new(Mode::SuperChip,
+                                   0x0123456789ABCDEF,
+                                   0xDEADBEEF,
+                                   Algorithm::Vip,
+                                   Quirks::for_mode(Mode::SuperChip));
+
+        for i in 0..100 {
+            movie.frames.push(Frame {
+                keys: 1 << (i % 16),
+                checksum: i * 0x01000193,
+            });
+        }
+
+        let mut buffer = Vec::new();
+        movie.write(&mut buffer).unwrap();
+
+        assert_eq!(Movie::read(&buffer).unwrap(), movie);
+
+        // Truncated or foreign data is rejected
+        assert!(Movie::read(&buffer[..buffer.len() - 1]).is_err());
+        assert!(Movie::read(b"GIF89a").is_err());
+    }
+
+    // Counts frames in V0 (and randomizes V1); LD V0, 0 .. ADD V0, 1; RND V1, $FF; JP $202
+    fn core() -> Core {
+        let rom = [0x60, 0x00, 0x70, 0x01, 0xC1, 0xFF, 0x12, 0x02];
+
+        let mut core: Core = Default::default();
+        core.insert_rom_bytes(&rom, Mode::Chip8);
+        core.reset();
+
+        core
+    }
+
+    #[test]
+    fn settings_restored() {
+        let mut core = core();
+        let quirks = Quirks { shift_vx: true, ..Quirks::for_mode(Mode::Chip8) };
+        core.set_quirks(Some(quirks));
+        core.set_rng(Algorithm::Vip);
+        core.interpreter.set_clock(Clock::External);
+
+        let mut host = FakeHost::default();
+        core.start_movie_recording(Some(1));
+        for _ in 0..10 {
+            core.run_frame(&mut host);
+        }
+
+        let mut movie = core.stop_movie().unwrap();
+
+        // Playback uses the quirks of the movie; stopping returns to those configured
+        core.set_quirks(None);
+        core.interpreter.set_clock(Clock::RealTime);
+        movie.quirks.jump_vx = true;
+        core.play_movie(movie).unwrap();
+        assert!(core.interpreter.quirks().jump_vx);
+        assert_eq!(core.interpreter.clock(), Clock::External);
+
+        core.stop_movie();
+        assert_eq!(core.interpreter.quirks(), Quirks::for_mode(Mode::Chip8));
+        assert_eq!(core.interpreter.clock(), Clock::RealTime);
+        assert_eq!(core.interpreter.rng(), Algorithm::Vip);
+    }
+
+    #[test]
+    fn rewind_while_recording() {
+        let mut core = core();
+        core.enable_rewind(100, 1 << 20);
+
+        let mut host = FakeHost::default();
+        core.start_movie_recording(Some(1));
+        for _ in 0..10 {
+            core.run_frame(&mut host);
+        }
+
+        // Rewinding drops the frames rewound over from the movie
+        for _ in 0..4 {
+            assert!(core.rewind(&mut host));
+        }
+
+        for _ in 0..6 {
+            core.run_frame(&mut host);
+        }
+
+        let movie = core.stop_movie().unwrap();
+        assert_eq!(movie.frames.len(), 12);
+
+        // ... so it plays back in sync; and cannot be rewound during playback
+        core.play_movie(movie).unwrap();
+        for _ in 0..12 {
+            core.run_frame(&mut host);
+        }
+
+        assert_eq!(core.movie_desync(), None);
+        assert!(!core.rewind(&mut host));
+    }
+}
@@ -1,8 +1,10 @@
-use mmu::Mmu;
 use std::fmt;
+use std::vec::Vec;
+
+use interpreter::Mode;
 
 // A standard CHIP-8 opcode is 2-bytes long (big-endian)
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Opcode {
     hi: u8,
     lo: u8,
@@ -10,15 +12,8 @@ pub struct Opcode {
 
 impl Opcode {
     #[inline]
-    pub fn read_next(pc: &mut usize, m: &mut Mmu) -> Self {
-        let r = Opcode {
-            hi: m.read(*pc),
-            lo: m.read(*pc + 1),
-        };
-
-        *pc += 2;
-
-        r
+    pub fn new(hi: u8, lo: u8) -> Self {
+        Opcode { hi: hi, lo: lo }
     }
 
     #[inline]
@@ -37,6 +32,11 @@ impl Opcode {
     pub fn extract_u8(&self) -> u8 {
         self.lo
     }
+
+    #[inline]
+    pub fn to_u16(&self) -> u16 {
+        ((self.hi as u16) << 8) | (self.lo as u16)
+    }
 }
 
 impl fmt::Display for Opcode {
@@ -44,3 +44,406 @@ impl fmt::Display for Opcode {
         write!(f, "${:02X}{:02X}", self.hi, self.lo)
     }
 }
+
+// A decoded instruction
+//  Operands are register indices (`x`, `y`), immediates, and addresses; they are listed
+//  in the order of the mnemonic.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Instruction {
+    // CHIP-8
+    Cls,
+    Ret,
+    Jp(u16),
+    Call(u16),
+    SeImm(u8, u8),
+    SneImm(u8, u8),
+    Se(u8, u8),
+    LdImm(u8, u8),
+    AddImm(u8, u8),
+    Ld(u8, u8),
+    Or(u8, u8),
+    And(u8, u8),
+    Xor(u8, u8),
+    Add(u8, u8),
+    Sub(u8, u8),
+    Shr(u8, u8),
+    Subn(u8, u8),
+    Shl(u8, u8),
+    Sne(u8, u8),
+    LdI(u16),
+
+    // `JP V0, u12`; X (the upper nibble of u12) is kept for the jump quirk (`JP Vx, u12`)
+    JpV0(u8, u16),
+
+    Rnd(u8, u8),
+    Show(u8, u8, u8),
+    Skp(u8),
+    Sknp(u8),
+    LdVxDt(u8),
+    LdDtVx(u8),
+    LdStVx(u8),
+    AddI(u8),
+    LdFont(u8),
+    LdBcd(u8),
+    Store(u8),
+    Load(u8),
+
+    // SUPER-CHIP
+    ScrollDown(u8),
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    Sed,
+    Cld,
+    Show16(u8, u8),
+    LdFont10(u8),
+    SaveFlags(u8),
+    RestoreFlags(u8),
+
+    // XO-CHIP
+    ScrollUp(u8),
+    SaveRange(u8, u8),
+    RestoreRange(u8, u8),
+    LdILong(u16),
+    Plane(u8),
+    Audio,
+    Pitch(u8),
+
+    // CHIP-8X
+    BgStep,
+    AddNibbles(u8, u8),
+    Col(u8, u8),
+    ColRows(u8, u8, u8),
+
+    // Not an instruction (of the mode it was decoded for)
+    Unknown(u16),
+}
+
+impl Instruction {
+    // Decode the instruction at the beginning of `bytes` for the given mode; `None` if
+    // `bytes` is too short (XO-CHIP `F000 NNNN` needs 4)
+    pub fn decode(mode: Mode, bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 2 {
+            return None;
+        }
+
+        let opcode = Opcode::new(bytes[0], bytes[1]);
+        let instruction = match mode {
+            Mode::Chip8x => decode_chip_8x(opcode).or_else(|| decode_chip_8(opcode)),
+            Mode::SuperChip => decode_super_chip(opcode).or_else(|| decode_chip_8(opcode)),
+            Mode::XoChip => {
+                if opcode.to_u16() == 0xF000 && bytes.len() < 4 {
+                    return None;
+                }
+
+                decode_xo_chip(bytes)
+                    .or_else(|| decode_super_chip(opcode))
+                    .or_else(|| decode_chip_8(opcode))
+            }
+
+            Mode::Chip8 | Mode::HiResChip8 | Mode::Chip10 => decode_chip_8(opcode),
+        };
+
+        Some(instruction.unwrap_or_else(|| Instruction::Unknown(opcode.to_u16())))
+    }
+
+    // Length in bytes (2, or 4 for XO-CHIP `F000 NNNN`)
+    #[inline]
+    pub fn len(&self) -> usize {
+        match *self {
+            Instruction::LdILong(_) => 4,
+            _ => 2,
+        }
+    }
+
+    // Encode back to (big-endian) bytes
+    pub fn encode(&self) -> Vec<u8> {
+        use self::Instruction::*;
+
+        // Assemble an opcode from nibbles
+        let op = |a: u16, b: u8, c: u8, d: u8| {
+            (a << 12) | ((b as u16 & 0xF) << 8) | ((c as u16 & 0xF) << 4) | (d as u16 & 0xF)
+        };
+
+        let opcode = match *self {
+            Cls => 0x00E0,
+            Ret => 0x00EE,
+            Jp(address) => 0x1000 | (address & 0xFFF),
+            Call(address) => 0x2000 | (address & 0xFFF),
+            SeImm(x, nn) => op(0x3, x, 0, 0) | nn as u16,
+            SneImm(x, nn) => op(0x4, x, 0, 0) | nn as u16,
+            Se(x, y) => op(0x5, x, y, 0x0),
+            LdImm(x, nn) => op(0x6, x, 0, 0) | nn as u16,
+            AddImm(x, nn) => op(0x7, x, 0, 0) | nn as u16,
+            Ld(x, y) => op(0x8, x, y, 0x0),
+            Or(x, y) => op(0x8, x, y, 0x1),
+            And(x, y) => op(0x8, x, y, 0x2),
+            Xor(x, y) => op(0x8, x, y, 0x3),
+            Add(x, y) => op(0x8, x, y, 0x4),
+            Sub(x, y) => op(0x8, x, y, 0x5),
+            Shr(x, y) => op(0x8, x, y, 0x6),
+            Subn(x, y) => op(0x8, x, y, 0x7),
+            Shl(x, y) => op(0x8, x, y, 0xE),
+            Sne(x, y) => op(0x9, x, y, 0x0),
+            LdI(address) => 0xA000 | (address & 0xFFF),
+            JpV0(_, address) => 0xB000 | (address & 0xFFF),
+            Rnd(x, nn) => op(0xC, x, 0, 0) | nn as u16,
+            Show(x, y, n) => op(0xD, x, y, n),
+            Skp(x) => op(0xE, x, 0x9, 0xE),
+            Sknp(x) => op(0xE, x, 0xA, 0x1),
+            LdVxDt(x) => op(0xF, x, 0x0, 0x7),
+            LdDtVx(x) => op(0xF, x, 0x1, 0x5),
+            LdStVx(x) => op(0xF, x, 0x1, 0x8),
+            AddI(x) => op(0xF, x, 0x1, 0xE),
+            LdFont(x) => op(0xF, x, 0x2, 0x9),
+            LdBcd(x) => op(0xF, x, 0x3, 0x3),
+            Store(x) => op(0xF, x, 0x5, 0x5),
+            Load(x) => op(0xF, x, 0x6, 0x5),
+
+            ScrollDown(n) => op(0x0, 0x0, 0xC, n),
+            ScrollRight => 0x00FB,
+            ScrollLeft => 0x00FC,
+            Exit => 0x00FD,
+            Sed => 0x00FE,
+            Cld => 0x00FF,
+            Show16(x, y) => op(0xD, x, y, 0x0),
+            LdFont10(x) => op(0xF, x, 0x3, 0x0),
+            SaveFlags(x) => op(0xF, x, 0x7, 0x5),
+            RestoreFlags(x) => op(0xF, x, 0x8, 0x5),
+
+            ScrollUp(n) => op(0x0, 0x0, 0xD, n),
+            SaveRange(x, y) => op(0x5, x, y, 0x2),
+            RestoreRange(x, y) => op(0x5, x, y, 0x3),
+            LdILong(address) => {
+                return vec![0xF0, 0x00, (address >> 8) as u8, address as u8];
+            }
+            Plane(n) => op(0xF, n, 0x0, 0x1),
+            Audio => 0xF002,
+            Pitch(x) => op(0xF, x, 0x3, 0xA),
+
+            BgStep => 0x02A0,
+            AddNibbles(x, y) => op(0x5, x, y, 0x1),
+            Col(x, y) => op(0xB, x, y, 0x0),
+            ColRows(x, y, n) => op(0xB, x, y, n),
+
+            Unknown(opcode) => opcode,
+        };
+
+        vec![(opcode >> 8) as u8, opcode as u8]
+    }
+}
+
+// CHIP-8 (and the modes that only differ in display size)
+fn decode_chip_8(opcode: Opcode) -> Option<Instruction> {
+    use self::Instruction::*;
+
+    let address = opcode.extract_u12();
+    let byte = opcode.extract_u8();
+
+    Some(match opcode.unwrap() {
+        (0x0, 0x0, 0xE, 0x0) => Cls,
+        (0x0, 0x0, 0xE, 0xE) => Ret,
+        (0x1, ..) => Jp(address),
+        (0x2, ..) => Call(address),
+        (0x3, x, ..) => SeImm(x, byte),
+        (0x4, x, ..) => SneImm(x, byte),
+        (0x5, x, y, 0x0) => Se(x, y),
+        (0x6, x, ..) => LdImm(x, byte),
+        (0x7, x, ..) => AddImm(x, byte),
+        (0x8, x, y, 0x0) => Ld(x, y),
+        (0x8, x, y, 0x1) => Or(x, y),
+        (0x8, x, y, 0x2) => And(x, y),
+        (0x8, x, y, 0x3) => Xor(x, y),
+        (0x8, x, y, 0x4) => Add(x, y),
+        (0x8, x, y, 0x5) => Sub(x, y),
+        (0x8, x, y, 0x6) => Shr(x, y),
+        (0x8, x, y, 0x7) => Subn(x, y),
+        (0x8, x, y, 0xE) => Shl(x, y),
+        (0x9, x, y, 0x0) => Sne(x, y),
+        (0xA, ..) => LdI(address),
+        (0xB, x, ..) => JpV0(x, address),
+        (0xC, x, ..) => Rnd(x, byte),
+        (0xD, x, y, n) => Show(x, y, n),
+        (0xE, x, 0x9, 0xE) => Skp(x),
+        (0xE, x, 0xA, 0x1) => Sknp(x),
+        (0xF, x, 0x0, 0x7) => LdVxDt(x),
+        (0xF, x, 0x1, 0x5) => LdDtVx(x),
+        (0xF, x, 0x1, 0x8) => LdStVx(x),
+        (0xF, x, 0x1, 0xE) => AddI(x),
+        (0xF, x, 0x2, 0x9) => LdFont(x),
+        (0xF, x, 0x3, 0x3) => LdBcd(x),
+        (0xF, x, 0x5, 0x5) => Store(x),
+        (0xF, x, 0x6, 0x5) => Load(x),
+
+        _ => return None,
+    })
+}
+
+// SUPER-CHIP additions (on top of CHIP-8)
+fn decode_super_chip(opcode: Opcode) -> Option<Instruction> {
+    use self::Instruction::*;
+
+    Some(match opcode.unwrap() {
+        (0x0, 0x0, 0xC, n) => ScrollDown(n),
+        (0x0, 0x0, 0xF, 0xB) => ScrollRight,
+        (0x0, 0x0, 0xF, 0xC) => ScrollLeft,
+        (0x0, 0x0, 0xF, 0xD) => Exit,
+        (0x0, 0x0, 0xF, 0xE) => Sed,
+        (0x0, 0x0, 0xF, 0xF) => Cld,
+        (0xD, x, y, 0x0) => Show16(x, y),
+        (0xF, x, 0x3, 0x0) => LdFont10(x),
+        (0xF, x, 0x7, 0x5) => SaveFlags(x),
+        (0xF, x, 0x8, 0x5) => RestoreFlags(x),
+
+        _ => return None,
+    })
+}
+
+// XO-CHIP additions (on top of SUPER-CHIP); `bytes` holds at least 4 bytes for
+// `F000 NNNN`
+fn decode_xo_chip(bytes: &[u8]) -> Option<Instruction> {
+    use self::Instruction::*;
+
+    Some(match Opcode::new(bytes[0], bytes[1]).unwrap() {
+        (0x0, 0x0, 0xD, n) => ScrollUp(n),
+        (0x5, x, y, 0x2) => SaveRange(x, y),
+        (0x5, x, y, 0x3) => RestoreRange(x, y),
+        (0xF, 0x0, 0x0, 0x0) => LdILong(((bytes[2] as u16) << 8) | (bytes[3] as u16)),
+        (0xF, n, 0x0, 0x1) => Plane(n),
+        (0xF, 0x0, 0x0, 0x2) => Audio,
+        (0xF, x, 0x3, 0xA) => Pitch(x),
+
+        _ => return None,
+    })
+}
+
+// CHIP-8X additions (on top of CHIP-8; `BNNN` is replaced)
+fn decode_chip_8x(opcode: Opcode) -> Option<Instruction> {
+    use self::Instruction::*;
+
+    Some(match opcode.unwrap() {
+        (0x0, 0x2, 0xA, 0x0) => BgStep,
+        (0x5, x, y, 0x1) => AddNibbles(x, y),
+        (0xB, x, y, 0x0) => Col(x, y),
+        (0xB, x, y, n) => ColRows(x, y, n),
+
+        _ => return None,
+    })
+}
+
+// Mnemonics (as in the comments of the runtimes and the disassembly listing)
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Instruction::*;
+
+        match *self {
+            Cls => write!(f, "CLS"),
+            Ret => write!(f, "RET"),
+            Jp(address) => write!(f, "JP ${:03X}", address),
+            Call(address) => write!(f, "CALL ${:03X}", address),
+            SeImm(x, nn) => write!(f, "SE V{:X}, ${:02X}", x, nn),
+            SneImm(x, nn) => write!(f, "SNE V{:X}, ${:02X}", x, nn),
+            Se(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            LdImm(x, nn) => write!(f, "LD V{:X}, ${:02X}", x, nn),
+            AddImm(x, nn) => write!(f, "ADD V{:X}, ${:02X}", x, nn),
+            Ld(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Or(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            And(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Xor(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Add(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Sub(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Shr(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Subn(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Shl(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Sne(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            LdI(address) => write!(f, "LD I, ${:03X}", address),
+            JpV0(_, address) => write!(f, "JP V0, ${:03X}", address),
+            Rnd(x, nn) => write!(f, "RND V{:X}, ${:02X}", x, nn),
+            Show(x, y, n) => write!(f, "SHOW V{:X}, V{:X}, {}", x, y, n),
+            Skp(x) => write!(f, "SKP V{:X}", x),
+            Sknp(x) => write!(f, "SKNP V{:X}", x),
+            LdVxDt(x) => write!(f, "LD V{:X}, DT", x),
+            LdDtVx(x) => write!(f, "LD DT, V{:X}", x),
+            LdStVx(x) => write!(f, "LD ST, V{:X}", x),
+            AddI(x) => write!(f, "ADD I, V{:X}", x),
+            LdFont(x) => write!(f, "LD [I], FONT V{:X}", x),
+            LdBcd(x) => write!(f, "LD [I], BCD V{:X}", x),
+            Store(x) => write!(f, "LD [I], V{:X}", x),
+            Load(x) => write!(f, "LD V{:X}, [I]", x),
+
+            ScrollDown(n) => write!(f, "SCDOWN {}", n),
+            ScrollRight => write!(f, "SCRIGHT"),
+            ScrollLeft => write!(f, "SCLEFT"),
+            Exit => write!(f, "EXIT"),
+            Sed => write!(f, "SED"),
+            Cld => write!(f, "CLD"),
+            Show16(x, y) => write!(f, "SHOW16 V{:X}, V{:X}", x, y),
+            LdFont10(x) => write!(f, "LD I, FONT10 V{:X}", x),
+            SaveFlags(x) => write!(f, "SAVE V{:X}", x),
+            RestoreFlags(x) => write!(f, "RESTORE V{:X}", x),
+
+            ScrollUp(n) => write!(f, "SCUP {}", n),
+            SaveRange(x, y) => write!(f, "SAVE V{:X} .. V{:X}", x, y),
+            RestoreRange(x, y) => write!(f, "RESTORE V{:X} .. V{:X}", x, y),
+            LdILong(address) => write!(f, "LD I, LONG ${:04X}", address),
+            Plane(n) => write!(f, "PLANE {}", n),
+            Audio => write!(f, "AUDIO"),
+            Pitch(x) => write!(f, "PITCH V{:X}", x),
+
+            BgStep => write!(f, "BGSTEP"),
+            AddNibbles(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Col(x, y) => write!(f, "COL V{:X}, V{:X}", x, y),
+            ColRows(x, y, n) => write!(f, "COL V{:X}, V{:X}, {}", x, y, n),
+
+            Unknown(opcode) => write!(f, "DB ${:02X}, ${:02X}", opcode >> 8, opcode & 0xFF),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use interpreter::Mode;
+    use super::Instruction;
+
+    #[test]
+    fn encode() {
+        let modes = [Mode::Chip8, Mode::Chip8x, Mode::SuperChip, Mode::XoChip];
+
+        // Every opcode decodes (in every mode) to an instruction that encodes back to it
+        for &mode in &modes {
+            for opcode in 0..0x10000 {
+                let bytes = [(opcode >> 8) as u8, opcode as u8, 0x12, 0x34];
+                let instruction = Instruction::decode(mode, &bytes).unwrap();
+
+                assert_eq!(instruction.encode()[..],
+                           bytes[..instruction.len()],
+                           "{:?} in {:?}",
+                           instruction,
+                           mode);
+            }
+        }
+
+        assert_eq!(Instruction::decode(Mode::XoChip, &[0xF0, 0x00, 0x12]), None);
+        assert_eq!(Instruction::decode(Mode::Chip8, &[0xF0, 0x00]),
+                   Some(Instruction::Unknown(0xF000)));
+    }
+
+    #[test]
+    fn decode() {
+        let decode = |mode, hi, lo| Instruction::decode(mode, &[hi, lo, 0x12, 0x34]).unwrap();
+
+        assert_eq!(decode(Mode::Chip8, 0xD1, 0x20), Instruction::Show(1, 2, 0));
+        assert_eq!(decode(Mode::SuperChip, 0xD1, 0x20), Instruction::Show16(1, 2));
+        assert_eq!(decode(Mode::Chip8, 0xB3, 0x45), Instruction::JpV0(3, 0x345));
+        assert_eq!(decode(Mode::Chip8x, 0xB3, 0x45), Instruction::ColRows(3, 4, 5));
+        assert_eq!(decode(Mode::SuperChip, 0x00, 0xD2), Instruction::Unknown(0x00D2));
+        assert_eq!(decode(Mode::XoChip, 0x00, 0xD2), Instruction::ScrollUp(2));
+        assert_eq!(decode(Mode::XoChip, 0xF0, 0x00), Instruction::LdILong(0x1234));
+
+        // Mnemonics
+        assert_eq!(decode(Mode::Chip8, 0x8A, 0xB4).to_string(), "ADD VA, VB");
+        assert_eq!(decode(Mode::Chip8, 0xA1, 0x23).to_string(), "LD I, $123");
+        assert_eq!(decode(Mode::XoChip, 0xF0, 0x00).to_string(), "LD I, LONG $1234");
+        assert_eq!(decode(Mode::Chip8, 0x01, 0x23).to_string(), "DB $01, $23");
+    }
+}
@@ -0,0 +1,179 @@
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+#[cfg(feature = "std")]
+use std::path::Path;
+use std::vec::Vec;
+
+use interpreter::Mode;
+use state;
+
+// Colors of the display (as 24-bit RGB; 0xRRGGBB)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Palette {
+    // Dots off (background), and dots on in plane 1, plane 2, and both planes
+    //  Only XO-CHIP has two planes; other modes use the first two colors.
+    pub colors: [u32; 4],
+
+    // CHIP-8X color index (black, red, blue, violet, green, yellow, aqua, and white)
+    // -> color
+    pub chip_8x: [u32; 8],
+}
+
+const CHIP_8X: [u32; 8] = [0x000000, 0xFF0000, 0x0000FF, 0xFF00FF, 0x00FF00, 0xFFFF00,
+                           0x00FFFF, 0xFFFFFF];
+
+// Built-in palettes; (name, colors)
+const THEMES: [(&'static str, [u32; 4]); 4] =
+    [("classic", [0x000000, 0xFFFFFF, 0xAAAAAA, 0x555555]),
+     ("amber", [0x000000, 0xFFB000, 0x805800, 0xFFD880]),
+     ("green", [0x000000, 0x33FF33, 0x1A801A, 0x99FF99]),
+     ("lcd", [0x9BBC0F, 0x306230, 0x8BAC0F, 0x0F380F])];
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            colors: THEMES[0].1,
+            chip_8x: CHIP_8X,
+        }
+    }
+}
+
+impl Palette {
+    // Built-in palette by name: `classic`, `amber`, `green` (phosphor), or `lcd`
+    pub fn from_name(name: &str) -> Option<Self> {
+        THEMES.iter().find(|theme| theme.0 == name).map(|theme| {
+            Palette {
+                colors: theme.1,
+                chip_8x: CHIP_8X,
+            }
+        })
+    }
+
+    // Parse a palette: either the name of a built-in palette, or a list of colors
+    // (`RRGGBB` in hex, optionally prefixed by `#`) separated by commas or whitespace
+    //  The list is 2 (background and foreground) or 4 colors (XO-CHIP), optionally
+    //  followed by the 8 CHIP-8X colors. With 2 colors, plane 2 is drawn halfway
+    //  between the background and foreground.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if let Some(palette) = Palette::from_name(text) {
+            return Some(palette);
+        }
+
+        let mut colors = Vec::new();
+        for word in text.split(|c: char| c == ',' || c.is_whitespace()) {
+            if word.is_empty() {
+                continue;
+            }
+
+            let word = if word.starts_with('#') { &word[1..] } else { word };
+            if word.len() != 6 {
+                return None;
+            }
+
+            colors.push(u32::from_str_radix(word, 16).ok()?);
+        }
+
+        let mut palette: Palette = Default::default();
+        let planes = match colors.len() {
+            2 | 10 => 2,
+            4 | 12 => 4,
+            _ => return None,
+        };
+
+        if planes == 2 {
+            palette.colors = [colors[0], colors[1], blend(colors[0], colors[1]), colors[1]];
+        } else {
+            palette.colors.copy_from_slice(&colors[..4]);
+        }
+
+        if colors.len() > planes {
+            palette.chip_8x.copy_from_slice(&colors[planes..]);
+        }
+
+        Some(palette)
+    }
+
+    // Colors used by `mode`
+    pub fn colors(&self, mode: Mode) -> &[u32] {
+        match mode {
+            Mode::Chip8x => &self.chip_8x,
+            Mode::XoChip => &self.colors,
+            _ => &self.colors[..2],
+        }
+    }
+
+    pub fn save_state(&self, w: &mut state::Writer) {
+        for &color in self.colors.iter().chain(&self.chip_8x) {
+            w.write_u32(color);
+        }
+    }
+
+    pub fn load_state(&mut self, r: &mut state::Reader) {
+        for color in self.colors.iter_mut().chain(&mut self.chip_8x) {
+            *color = r.read_u32();
+        }
+    }
+}
+
+// Halfway between two colors
+fn blend(a: u32, b: u32) -> u32 {
+    (0..3).fold(0, |color, i| {
+        let shift = i * 8;
+        let channel = (((a >> shift) & 0xFF) + ((b >> shift) & 0xFF)) / 2;
+
+        color | (channel << shift)
+    })
+}
+
+// Load the palette configured for a ROM (`<dir>/<rom hash>.palette`, in the format
+// read by `Palette::parse`), if any
+#[cfg(feature = "std")]
+pub fn load(dir: &Path, rom_hash: u64) -> io::Result<Option<Palette>> {
+    let mut stream = match File::open(dir.join(format!("{:016x}.palette", rom_hash))) {
+        Ok(stream) => stream,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let mut text = String::new();
+    stream.read_to_string(&mut text)?;
+
+    Palette::parse(&text)
+        .map(Some)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid palette"))
+}
+
+#[cfg(test)]
+mod tests {
+    use interpreter::Mode;
+    use super::Palette;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Palette::parse("amber"), Palette::from_name("amber"));
+        assert_eq!(Palette::parse("fuchsia"), None);
+        assert_eq!(Palette::parse("#000000"), None);
+        assert_eq!(Palette::parse("000000, 12345G"), None);
+
+        let palette = Palette::parse("#102030 #F0E0D0").unwrap();
+        assert_eq!(palette.colors, [0x102030, 0xF0E0D0, 0x808080, 0xF0E0D0]);
+        assert_eq!(palette.chip_8x, Palette::default().chip_8x);
+
+        let text = "000000 FFFFFF 0000FF FF0000  000000 111111 222222 333333 444444 555555 \
+                    666666 777777";
+        let palette = Palette::parse(text).unwrap();
+        assert_eq!(palette.colors, [0x000000, 0xFFFFFF, 0x0000FF, 0xFF0000]);
+        assert_eq!(palette.chip_8x[7], 0x777777);
+    }
+
+    #[test]
+    fn colors() {
+        let palette: Palette = Default::default();
+        assert_eq!(palette.colors(Mode::Chip8), &[0x000000, 0xFFFFFF]);
+        assert_eq!(palette.colors(Mode::XoChip).len(), 4);
+        assert_eq!(palette.colors(Mode::Chip8x)[1], 0xFF0000);
+    }
+}
@@ -0,0 +1,139 @@
+use std::vec::Vec;
+
+#[cfg(feature = "axal")]
+use axal;
+
+// Framebuffer pixel format
+//  Multi-byte pixels are packed into a native-endian integer (as a frontend reads them
+//  back as `u16` / `u32`).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    // 8-bit; 3 bits red, 3 bits green, and 2 bits blue (MSB to LSB)
+    R3G3B2,
+
+    // 16-bit; 5 bits red, 6 bits green, and 5 bits blue (MSB to LSB)
+    Rgb565,
+
+    // 32-bit; 8 bits each of red, green, blue, and alpha (MSB to LSB)
+    Rgba8888,
+}
+
+// Rectangle of a framebuffer (in pixels)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::R3G3B2
+    }
+}
+
+impl Format {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match *self {
+            Format::R3G3B2 => 1,
+            Format::Rgb565 => 2,
+            Format::Rgba8888 => 4,
+        }
+    }
+
+    #[cfg(feature = "axal")]
+    pub fn to_axal(&self) -> axal::PixelFormat {
+        match *self {
+            Format::R3G3B2 => axal::PixelFormat::R3_G3_B2,
+            Format::Rgb565 => axal::PixelFormat::RGB565,
+            Format::Rgba8888 => axal::PixelFormat::RGBA8888,
+        }
+    }
+
+    // Encode a 24-bit RGB color (0xRRGGBB) as a pixel
+    pub fn encode(&self, color: u32) -> Vec<u8> {
+        let (r, g, b) = ((color >> 16) & 0xFF, (color >> 8) & 0xFF, color & 0xFF);
+
+        match *self {
+            Format::R3G3B2 => vec![((r & 0xE0) | ((g >> 5) << 2) | (b >> 6)) as u8],
+            Format::Rgb565 => ne_bytes(((r >> 3) << 11) | ((g >> 2) << 5) | (b >> 3), 2),
+            Format::Rgba8888 => ne_bytes((color << 8) | 0xFF, 4),
+        }
+    }
+
+    // Decode a pixel to a 24-bit RGB color (0xRRGGBB); each channel is scaled to 8 bits
+    pub fn decode(&self, pixel: &[u8]) -> u32 {
+        let value = from_ne_bytes(&pixel[..self.bytes_per_pixel()]);
+        let (r, g, b) = match *self {
+            Format::R3G3B2 => (scale(value >> 5, 3), scale(value >> 2, 3), scale(value, 2)),
+            Format::Rgb565 => (scale(value >> 11, 5), scale(value >> 5, 6), scale(value, 5)),
+            Format::Rgba8888 => return value >> 8,
+        };
+
+        (r << 16) | (g << 8) | b
+    }
+}
+
+// Scale the low `bits` bits of `value` to 8 bits
+fn scale(value: u32, bits: u32) -> u32 {
+    let max = (1 << bits) - 1;
+
+    (value & max) * 255 / max
+}
+
+fn ne_bytes(value: u32, len: usize) -> Vec<u8> {
+    let bytes = (0..len).map(|i| (value >> (i * 8)) as u8);
+
+    if cfg!(target_endian = "big") {
+        bytes.rev().collect()
+    } else {
+        bytes.collect()
+    }
+}
+
+fn from_ne_bytes(bytes: &[u8]) -> u32 {
+    let fold = |value, &b| (value << 8) | b as u32;
+
+    if cfg!(target_endian = "big") {
+        bytes.iter().fold(0, fold)
+    } else {
+        bytes.iter().rev().fold(0, fold)
+    }
+}
+
+// Render dots into (a part of) a framebuffer; `indices` yields the color (an index into
+// `colors`) of every dot
+pub fn render<I>(format: Format, framebuffer: &mut [u8], colors: &[u32], indices: I)
+    where I: Iterator<Item = usize>
+{
+    let pixels: Vec<Vec<u8>> = colors.iter().map(|&color| format.encode(color)).collect();
+
+    for (pixel, index) in framebuffer.chunks_mut(format.bytes_per_pixel()).zip(indices) {
+        pixel.copy_from_slice(&pixels[index]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Format;
+
+    #[test]
+    fn encode_decode() {
+        let formats = [Format::R3G3B2, Format::Rgb565, Format::Rgba8888];
+
+        for &format in &formats {
+            for &color in &[0x000000, 0xFFFFFF, 0xFF0000, 0x00FF00, 0x0000FF] {
+                let pixel = format.encode(color);
+
+                assert_eq!(pixel.len(), format.bytes_per_pixel());
+                assert_eq!(format.decode(&pixel), color, "{:?} {:06X}", format, color);
+            }
+        }
+
+        assert_eq!(Format::R3G3B2.encode(0xFFB000), vec![0b111_101_00]);
+        assert_eq!(Format::R3G3B2.decode(&[0b101_101_10]), 0xB6B6AA);
+        assert_eq!(Format::Rgb565.decode(&Format::Rgb565.encode(0xFFB000)), 0xFFB200);
+        assert_eq!(Format::Rgba8888.decode(&Format::Rgba8888.encode(0x123456)), 0x123456);
+    }
+}
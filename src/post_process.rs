@@ -0,0 +1,270 @@
+use std::cmp;
+use std::vec::Vec;
+
+use pixel::{self, Region};
+
+// Width of the hires screen; lores (64 dots wide) screens are drawn at twice the scale
+// so both appear at the same size
+const HIRES_WIDTH: usize = 128;
+
+// Clockwise rotation of the screen
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Rotation {
+    Rotate0,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+// Effect drawn over the scaled screen
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Effect {
+    None,
+
+    // The last row and column of every dot is drawn in the background color
+    Gaps,
+
+    // Every other (output) row is drawn at half brightness
+    Scanlines,
+}
+
+// Post-processing of the framebuffer for display
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PostProcess {
+    // Output pixels per hires dot (lores dots are twice as large)
+    pub scale: usize,
+
+    pub rotation: Rotation,
+    pub effect: Effect,
+
+    // Fixed output size; the screen is drawn at the largest scale that fits (instead
+    // of `scale`) and centered on the background color
+    pub letterbox: Option<(usize, usize)>,
+}
+
+impl Default for PostProcess {
+    fn default() -> Self {
+        PostProcess {
+            scale: 1,
+            rotation: Rotation::Rotate0,
+            effect: Effect::None,
+            letterbox: None,
+        }
+    }
+}
+
+impl PostProcess {
+    // Size (after rotation) of a `width` x `height` screen, and the size of its dots
+    fn layout(&self, width: usize, height: usize) -> (usize, usize, usize) {
+        let factor = cmp::max(HIRES_WIDTH / cmp::max(width, 1), 1);
+        let (width, height) = match self.rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => (width, height),
+            Rotation::Rotate90 | Rotation::Rotate270 => (height, width),
+        };
+
+        let scale = match self.letterbox {
+            Some((out_width, out_height)) => {
+                cmp::min(out_width / cmp::max(width * factor, 1),
+                         out_height / cmp::max(height * factor, 1))
+            }
+
+            None => self.scale,
+        };
+
+        (width, height, cmp::max(scale, 1) * factor)
+    }
+
+    // Size of the output for a `width` x `height` screen
+    pub fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        match self.letterbox {
+            Some(size) => size,
+            None => {
+                let (width, height, dot) = self.layout(width, height);
+                (width * dot, height * dot)
+            }
+        }
+    }
+
+    // Region of the output covering rows `top..bottom` of a `width` x `height` screen
+    pub fn region(&self, width: usize, height: usize, top: usize, bottom: usize) -> Region {
+        let (rotated_width, rotated_height, dot) = self.layout(width, height);
+        let (out_width, out_height) = self.output_size(width, height);
+
+        // Every row changed; the whole output (including any letterbox) is refreshed
+        if top == 0 && bottom >= height {
+            return Region {
+                x: 0,
+                y: 0,
+                width: out_width,
+                height: out_height,
+            };
+        }
+
+        // Rows -> (rotated) dots: (left, top, right, bottom)
+        let (l, t, r, b) = match self.rotation {
+            Rotation::Rotate0 => (0, top, rotated_width, bottom),
+            Rotation::Rotate90 => (height - bottom, 0, height - top, rotated_height),
+            Rotation::Rotate180 => (0, height - bottom, rotated_width, height - top),
+            Rotation::Rotate270 => (top, 0, bottom, rotated_height),
+        };
+
+        // Dots -> output pixels (clipped to the output)
+        let left = (out_width as isize - (rotated_width * dot) as isize) / 2;
+        let top = (out_height as isize - (rotated_height * dot) as isize) / 2;
+        let clip = |value: usize, offset: isize, max: usize| {
+            cmp::min(cmp::max((value * dot) as isize + offset, 0) as usize, max)
+        };
+
+        let (x, y) = (clip(l, left, out_width), clip(t, top, out_height));
+
+        Region {
+            x: x,
+            y: y,
+            width: clip(r, left, out_width) - x,
+            height: clip(b, top, out_height) - y,
+        }
+    }
+
+    // Process a `width` x `height` framebuffer (in `format`) into `out`; returns the size
+    // of the output
+    pub fn apply(&self,
+                 format: pixel::Format,
+                 framebuffer: &[u8],
+                 width: usize,
+                 height: usize,
+                 background: u32,
+                 out: &mut Vec<u8>)
+                 -> (usize, usize) {
+        let bpp = format.bytes_per_pixel();
+        let (rotated_width, rotated_height, dot) = self.layout(width, height);
+        let (out_width, out_height) = self.output_size(width, height);
+
+        // Offset of the screen in the output (negative if cropped)
+        let left = (out_width as isize - (rotated_width * dot) as isize) / 2;
+        let top = (out_height as isize - (rotated_height * dot) as isize) / 2;
+
+        let background = format.encode(background);
+
+        // Scanlines: the framebuffer at half brightness
+        let mut dark = Vec::new();
+        if self.effect == Effect::Scanlines {
+            for pixel in framebuffer.chunks(bpp) {
+                dark.extend(format.encode((format.decode(pixel) >> 1) & 0x7F7F7F));
+            }
+        }
+
+        out.clear();
+        for y in 0..out_height as isize {
+            for x in 0..out_width as isize {
+                let (x, y) = (x - left, y - top);
+                if x < 0 || y < 0 || x >= (rotated_width * dot) as isize ||
+                   y >= (rotated_height * dot) as isize {
+                    out.extend_from_slice(&background);
+                    continue;
+                }
+
+                let (x, y) = (x as usize, y as usize);
+                if self.effect == Effect::Gaps && dot > 1 &&
+                   (x % dot == dot - 1 || y % dot == dot - 1) {
+                    out.extend_from_slice(&background);
+                    continue;
+                }
+
+                // Dot (of the unrotated screen) under this pixel
+                let (rx, ry) = (x / dot, y / dot);
+                let (sx, sy) = match self.rotation {
+                    Rotation::Rotate0 => (rx, ry),
+                    Rotation::Rotate90 => (ry, height - 1 - rx),
+                    Rotation::Rotate180 => (width - 1 - rx, height - 1 - ry),
+                    Rotation::Rotate270 => (width - 1 - ry, rx),
+                };
+
+                let offset = (sy * width + sx) * bpp;
+                let source = if self.effect == Effect::Scanlines && y % 2 == 1 {
+                    &dark[..]
+                } else {
+                    framebuffer
+                };
+
+                match source.get(offset..offset + bpp) {
+                    Some(pixel) => out.extend_from_slice(pixel),
+                    None => out.extend_from_slice(&background),
+                }
+            }
+        }
+
+        (out_width, out_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pixel::Format;
+    use super::{Effect, PostProcess, Rotation};
+
+    // Process a 2x2 (lores-sized) R3_G3_B2 framebuffer
+    fn apply(post_process: PostProcess, framebuffer: &[u8]) -> (Vec<u8>, usize, usize) {
+        let mut out = Vec::new();
+        let (width, height) =
+            post_process.apply(Format::R3G3B2, framebuffer, 2, 2, 0x000000, &mut out);
+
+        assert_eq!(out.len(), width * height);
+        (out, width, height)
+    }
+
+    #[test]
+    fn scale_and_rotate() {
+        // A 2 dot wide screen is drawn at 64x the scale (as lores is 2x)
+        let mut post_process = PostProcess {
+            scale: 1,
+            ..Default::default()
+        };
+
+        let (out, width, height) = apply(post_process, &[1, 2, 3, 4]);
+        assert_eq!((width, height), (128, 128));
+        assert_eq!((out[0], out[64], out[64 * 128], out[64 * 128 + 64]), (1, 2, 3, 4));
+
+        // Clockwise: the bottom-left dot ends up top-left
+        for &(rotation, expect) in &[(Rotation::Rotate90, [3, 1, 4, 2]),
+                                     (Rotation::Rotate180, [4, 3, 2, 1]),
+                                     (Rotation::Rotate270, [2, 4, 1, 3])] {
+            post_process.rotation = rotation;
+
+            let (out, _, _) = apply(post_process, &[1, 2, 3, 4]);
+            assert_eq!([out[0], out[64], out[64 * 128], out[64 * 128 + 64]],
+                       expect,
+                       "{:?}",
+                       rotation);
+        }
+    }
+
+    #[test]
+    fn letterbox() {
+        let post_process = PostProcess {
+            letterbox: Some((300, 200)),
+            ..Default::default()
+        };
+
+        // Largest scale that fits is 1 (128x128), centered
+        let (out, width, height) = apply(post_process, &[0xFF; 4]);
+        assert_eq!((width, height), (300, 200));
+        assert_eq!((out[36 * 300 + 85], out[36 * 300 + 86]), (0x00, 0xFF));
+        assert_eq!((out[164 * 300 + 86], out[163 * 300 + 213]), (0x00, 0xFF));
+    }
+
+    #[test]
+    fn effects() {
+        let mut post_process = PostProcess {
+            effect: Effect::Gaps,
+            ..Default::default()
+        };
+
+        let (out, _, _) = apply(post_process, &[0xFF; 4]);
+        assert_eq!((out[62], out[63], out[63 * 128]), (0xFF, 0x00, 0x00));
+
+        post_process.effect = Effect::Scanlines;
+
+        let (out, _, _) = apply(post_process, &[0xFF; 4]);
+        assert_eq!((out[0], out[128]), (0xFF, 0b011_011_01));
+    }
+}
@@ -0,0 +1,47 @@
+use interpreter::Mode;
+
+// Behavior that differs between CHIP-8 interpreters (and that ROMs depend on)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Quirks {
+    // 8XY6 / 8XYE shift Vx in place (ignoring Vy) instead of setting Vx = Vy shifted
+    pub shift_vx: bool,
+
+    // FX55 / FX65 leave I unchanged instead of incrementing it past the last register
+    pub load_store_keep_i: bool,
+
+    // BNNN jumps to XNN + VX instead of NNN + V0
+    pub jump_vx: bool,
+
+    // FX1E sets VF when I overflows past $FFF (and clears it otherwise)
+    //  Not done by the COSMAC VIP but some games (eg. Spacefight 2091!) depend on it.
+    pub add_i_vf: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            shift_vx: false,
+            load_store_keep_i: false,
+            jump_vx: false,
+            add_i_vf: true,
+        }
+    }
+}
+
+impl Quirks {
+    // Quirks of the original interpreter for `mode`
+    pub fn for_mode(mode: Mode) -> Self {
+        match mode {
+            Mode::SuperChip => {
+                Quirks {
+                    shift_vx: true,
+                    load_store_keep_i: true,
+                    jump_vx: true,
+                    add_i_vf: false,
+                }
+            }
+
+            _ => Default::default(),
+        }
+    }
+}
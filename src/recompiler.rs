@@ -0,0 +1,365 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use compiler::{self, Kind};
+use interpreter::Mode;
+use opcode::Instruction;
+
+// Most instructions recompiled into a block (as for compiled blocks)
+const MAX_BLOCK_LEN: usize = 64;
+
+// Recompile a ROM into the source of a Rust module
+//  Control flow is followed from the entry point to find the code of the ROM (and the
+//  addresses blocks start at). Every block of straight-line code is recompiled into a
+//  function; the module exports them as `PROGRAM` (a `Recompiled`) for
+//  `Interpreter::set_recompiled`. Anything that is not recompiled is left to the
+//  interpreter: code only reached through a computed jump (`JP V0`), code outside of the
+//  ROM, instructions that are never compiled (eg. `DRW`), and code that is overwritten
+//  (self-modifying code).
+pub fn recompile(rom: &[u8], mode: Mode) -> String {
+    let entry = mode.entry();
+    let end = entry + rom.len();
+
+    let decode = |address: usize| -> Option<Instruction> {
+        if address < entry || address >= end {
+            return None;
+        }
+
+        let mut bytes = [0; 4];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = rom.get(address - entry + i).cloned().unwrap_or(0);
+        }
+
+        Instruction::decode(mode, &bytes).and_then(|instruction| {
+            if address + instruction.len() <= end {
+                Some(instruction)
+            } else {
+                None
+            }
+        })
+    };
+
+    // Blocks start at the entry point, at the targets of branches, and after every
+    // instruction that is not compiled
+    let mut code = BTreeSet::new();
+    let mut leaders = BTreeSet::new();
+    let mut pending = vec![entry];
+    leaders.insert(entry);
+
+    while let Some(address) = pending.pop() {
+        if code.contains(&address) {
+            continue;
+        }
+
+        let instruction = match decode(address) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+
+        code.insert(address);
+
+        let next = address + instruction.len();
+        let targets = match instruction {
+            Instruction::Ret | Instruction::Exit | Instruction::JpV0(..) |
+            Instruction::Unknown(_) => vec![],
+
+            Instruction::Jp(target) => vec![target as usize],
+            Instruction::Call(target) => vec![target as usize, next],
+
+            Instruction::SeImm(..) | Instruction::SneImm(..) | Instruction::Se(..) |
+            Instruction::Sne(..) | Instruction::Skp(_) | Instruction::Sknp(_) => {
+                vec![next, next + 2]
+            }
+
+            _ => {
+                pending.push(next);
+                if compiler::kind(instruction).is_none() {
+                    leaders.insert(next);
+                }
+
+                continue;
+            }
+        };
+
+        for target in targets {
+            leaders.insert(target);
+            pending.push(target);
+        }
+    }
+
+    // Recompile the block at every leader (within the code found); blocks cut short
+    // continue with another block
+    let mut blocks = BTreeMap::new();
+    let mut pending: Vec<usize> = leaders.into_iter().filter(|a| code.contains(a)).collect();
+
+    while let Some(address) = pending.pop() {
+        if blocks.contains_key(&address) {
+            continue;
+        }
+
+        let mut ops = Vec::new();
+        let mut next = address;
+
+        while let Some(instruction) = decode(next) {
+            let kind = match compiler::kind(instruction) {
+                Some(kind) => kind,
+                None => break,
+            };
+
+            ops.push((next, instruction, kind));
+            next += instruction.len();
+
+            if kind == Kind::Branch {
+                break;
+            }
+
+            if ops.len() == MAX_BLOCK_LEN {
+                pending.push(next);
+                break;
+            }
+        }
+
+        if !ops.is_empty() {
+            blocks.insert(address, ops);
+        }
+    }
+
+    let mut source = String::new();
+    writeln!(source,
+             "// Recompiled from a {}-byte {:?} ROM by `xchip::recompile`; do not edit\n",
+             rom.len(),
+             mode)
+        .unwrap();
+
+    writeln!(source, "use xchip::{{Context, Mmu, Mode, Recompiled, RecompiledBlock}};\n").unwrap();
+    writeln!(source, "pub const PROGRAM: Recompiled = Recompiled {{").unwrap();
+    writeln!(source, "    mode: Mode::{:?},", mode).unwrap();
+    writeln!(source, "    blocks: BLOCKS,").unwrap();
+    writeln!(source, "}};\n").unwrap();
+
+    writeln!(source, "const BLOCKS: &[RecompiledBlock] = &[").unwrap();
+    for &address in blocks.keys() {
+        writeln!(source,
+                 "    RecompiledBlock {{ address: 0x{0:04X}, code: CODE_{0:04X}, run: \
+                  block_{0:04x} }},",
+                 address)
+            .unwrap();
+    }
+
+    writeln!(source, "];").unwrap();
+
+    for (&address, ops) in &blocks {
+        let &(last, instruction, _) = ops.last().unwrap();
+        let bytes = &rom[address - entry..last + instruction.len() - entry];
+
+        write_block(&mut source, address, bytes, ops);
+    }
+
+    source
+}
+
+// Write the code (`CODE_XXXX`) and function (`block_xxxx`) of the block at `address`
+fn write_block(out: &mut String,
+               address: usize,
+               bytes: &[u8],
+               ops: &[(usize, Instruction, Kind)]) {
+    writeln!(out, "\nconst CODE_{:04X}: &[u8] = &[", address).unwrap();
+    for line in bytes.chunks(12) {
+        let line: Vec<String> = line.iter().map(|b| format!("0x{:02X}", b)).collect();
+        writeln!(out, "    {},", line.join(", ")).unwrap();
+    }
+
+    writeln!(out, "];\n").unwrap();
+
+    let mut body = String::new();
+    let mut uses_mmu = false;
+
+    for (n, &(op_address, instruction, kind)) in ops.iter().enumerate() {
+        let next = op_address + instruction.len();
+
+        writeln!(body,
+                 "{}    // ${:04X}  {}",
+                 if n == 0 { "" } else { "\n" },
+                 op_address,
+                 instruction)
+            .unwrap();
+        if n > 0 {
+            writeln!(body, "    if budget == {} {{\n        return {};\n    }}\n", n, n)
+                .unwrap();
+        }
+
+        // PC is incremented before the instruction executes (as by the interpreter)
+        writeln!(body, "    c.pc = 0x{:04X};", next).unwrap();
+
+        uses_mmu = uses_mmu || uses_mmu_of(instruction);
+
+        for line in statements(instruction, next).lines() {
+            writeln!(body, "    {}", line).unwrap();
+        }
+
+        // Self-modifying code: leave the block once the rest of it was overwritten
+        if kind == Kind::Write && n + 1 < ops.len() {
+            writeln!(body,
+                     "\n    if !m.matches(0x{:04X}, &CODE_{:04X}[{}..]) {{\n        \
+                      return {};\n    }}",
+                     next,
+                     address,
+                     next - address,
+                     n + 1)
+                .unwrap();
+        }
+    }
+
+    write!(out,
+           "fn block_{:04x}(c: &mut Context, {}: &mut Mmu, {}: usize) -> usize {{\n{}",
+           address,
+           if uses_mmu { "m" } else { "_m" },
+           if ops.len() > 1 { "budget" } else { "_budget" },
+           body)
+        .unwrap();
+
+    writeln!(out, "\n    {}\n}}", ops.len()).unwrap();
+}
+
+// Rust statements for a compiled instruction (with the semantics of the CHIP-8 runtime,
+// as compiled blocks); PC is already `next`
+fn statements(instruction: Instruction, next: usize) -> String {
+    let skip = |condition: String| {
+        format!("if {} {{\n    c.pc = 0x{:04X};\n}}", condition, next + 2)
+    };
+
+    let shift = |x: u8, y: u8| if x == y {
+        format!("let r = c.v[{}];", x)
+    } else {
+        format!("let r = if c.quirks.shift_vx {{ c.v[{}] }} else {{ c.v[{}] }};",
+                x,
+                y)
+    };
+
+    match instruction {
+        // Branches
+        Instruction::Ret => "c.pc = c.stack_pop(m) as usize;".into(),
+        Instruction::Jp(address) => format!("c.pc = 0x{:04X};", address),
+        Instruction::Call(address) => {
+            format!("c.stack_push(m, 0x{:04X});\nc.pc = 0x{:04X};", next, address)
+        }
+        Instruction::SeImm(x, byte) => skip(format!("c.v[{}] == 0x{:02X}", x, byte)),
+        Instruction::SneImm(x, byte) => skip(format!("c.v[{}] != 0x{:02X}", x, byte)),
+        Instruction::Se(x, y) => skip(format!("c.v[{}] == c.v[{}]", x, y)),
+        Instruction::Sne(x, y) => skip(format!("c.v[{}] != c.v[{}]", x, y)),
+        Instruction::JpV0(x, address) => {
+            format!("let v = c.v[if c.quirks.jump_vx {{ {} }} else {{ 0 }}];\nc.pc = \
+                     (0x{:04X}u16.wrapping_add(v as u16)) as usize;",
+                    x,
+                    address)
+        }
+
+        // Registers
+        Instruction::LdImm(x, byte) => format!("c.v[{}] = 0x{:02X};", x, byte),
+        Instruction::AddImm(x, byte) => {
+            format!("c.v[{0}] = c.v[{0}].wrapping_add(0x{1:02X});", x, byte)
+        }
+        Instruction::Ld(x, y) => format!("c.v[{}] = c.v[{}];", x, y),
+        Instruction::Or(x, y) => format!("c.v[{}] |= c.v[{}];", x, y),
+        Instruction::And(x, y) => format!("c.v[{}] &= c.v[{}];", x, y),
+        Instruction::Xor(x, y) => format!("c.v[{}] ^= c.v[{}];", x, y),
+        Instruction::Add(x, y) => {
+            format!("let r = c.v[{}] as u16 + c.v[{}] as u16;\nc.v[{}] = r as u8;\nc.v[0xF] = \
+                     (r > 0xFF) as u8;",
+                    x,
+                    y,
+                    x)
+        }
+        Instruction::Sub(x, y) => {
+            format!("let (vx, vy) = (c.v[{}], c.v[{}]);\nc.v[{}] = \
+                     vx.wrapping_sub(vy);\nc.v[0xF] = (vy <= vx) as u8;",
+                    x,
+                    y,
+                    x)
+        }
+        Instruction::Shr(x, y) => {
+            format!("{}\nc.v[{}] = r >> 1;\nc.v[0xF] = r & 1;", shift(x, y), x)
+        }
+        Instruction::Subn(x, y) => {
+            format!("let (vx, vy) = (c.v[{}], c.v[{}]);\nc.v[{}] = \
+                     vy.wrapping_sub(vx);\nc.v[0xF] = (vx <= vy) as u8;",
+                    x,
+                    y,
+                    x)
+        }
+        Instruction::Shl(x, y) => {
+            format!("{}\nc.v[{}] = r << 1;\nc.v[0xF] = r >> 7;", shift(x, y), x)
+        }
+        // A zero mask still draws a number (to keep the sequence)
+        Instruction::Rnd(x, 0) => format!("c.rng.next_u8();\nc.v[{}] = 0;", x),
+        Instruction::Rnd(x, byte) => format!("c.v[{}] = c.rng.next_u8() & 0x{:02X};", x, byte),
+        Instruction::LdDtVx(x) => format!("c.dt = c.v[{}];", x),
+        Instruction::LdStVx(x) => format!("c.st = c.v[{}];", x),
+
+        // Index and memory
+        Instruction::LdI(address) => format!("c.i = 0x{:04X};", address),
+        Instruction::AddI(x) => {
+            format!("let r = c.i as u32 + c.v[{}] as u32;\nlet mask = (m.size() - 1) as u32;\nc.i \
+                     = (r & mask) as usize;\nif c.quirks.add_i_vf {{\n    c.v[0xF] = (r > mask) \
+                     as u8;\n}}",
+                    x)
+        }
+        Instruction::LdFont(x) => format!("c.i = ((c.v[{}] & 0xF) as usize) * 5;", x),
+        Instruction::LdBcd(x) => {
+            format!("let (r, i) = (c.v[{}], c.i);\nm.write(i, r / 100);\nm.write(i + 1, (r % \
+                     100) / 10);\nm.write(i + 2, r % 10);",
+                    x)
+        }
+        Instruction::Store(x) => {
+            format!("for j in 0..{0} {{\n    m.write(c.i + j, c.v[j]);\n}}\n{1}",
+                    x as usize + 1,
+                    advance_i(x))
+        }
+        Instruction::Load(x) => {
+            format!("for j in 0..{0} {{\n    c.v[j] = m.read(c.i + j);\n}}\n{1}",
+                    x as usize + 1,
+                    advance_i(x))
+        }
+
+        _ => unreachable!(),
+    }
+}
+
+// Whether the statements of an instruction use the MMU
+fn uses_mmu_of(instruction: Instruction) -> bool {
+    match instruction {
+        Instruction::Ret | Instruction::Call(_) | Instruction::AddI(_) |
+        Instruction::LdBcd(_) | Instruction::Store(_) | Instruction::Load(_) => true,
+        _ => false,
+    }
+}
+
+// `I` after `LD [I], Vx` / `LD Vx, [I]`
+fn advance_i(x: u8) -> String {
+    format!("if !c.quirks.load_store_keep_i {{\n    c.i += {};\n}}", x as usize + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use interpreter::Mode;
+    use super::recompile;
+
+    #[test]
+    fn blocks() {
+        // (at $200) LD V0, 1; CALL $20A; JP $200; DB $00, $00; (at $20A) ADD V0, V0;
+        // DRW V0, V0, 1; LD V1, 2; RET; (unreachable) JP V0, $300
+        let rom = [0x60, 0x01, 0x22, 0x0A, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x04,
+                   0xD0, 0x01, 0x61, 0x02, 0x00, 0xEE, 0xB3, 0x00];
+
+        let source = recompile(&rom, Mode::Chip8);
+
+        // Entry, call target, return address, and after the `DRW`
+        for &address in &["0200", "0204", "020a", "020e"] {
+            assert!(source.contains(&format!("fn block_{}(", address)), "{}", address);
+        }
+
+        assert!(!source.contains("fn block_0212("));
+        assert!(source.contains("c.stack_push(m, 0x0204);\n    c.pc = 0x020A;"));
+        assert!(source.contains("    if budget == 1 {\n        return 1;\n    }"));
+    }
+}
@@ -0,0 +1,368 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+// A single recorded frame
+enum Frame {
+    // Complete (compressed) snapshot
+    Key(Vec<u8>),
+
+    // Snapshot XOR'd against the preceding keyframe (and then compressed)
+    Delta(Vec<u8>),
+}
+
+impl Frame {
+    fn len(&self) -> usize {
+        match *self {
+            Frame::Key(ref data) | Frame::Delta(ref data) => data.len(),
+        }
+    }
+
+    fn is_key(&self) -> bool {
+        match *self {
+            Frame::Key(_) => true,
+            Frame::Delta(_) => false,
+        }
+    }
+}
+
+// Number of frames between keyframes
+//  Longer intervals use less memory but deltas grow as the state drifts from its keyframe.
+const INTERVAL: usize = 60;
+
+// Ring buffer of per-frame interpreter snapshots
+//  Every `INTERVAL` frames a full keyframe is stored; frames in-between are stored
+//  as a delta against that keyframe. Snapshots differ very little from frame to
+//  frame so the deltas are mostly zeroes and compress extremely well.
+//
+//  An evicted keyframe is kept (compressed) as the base of its deltas until they are
+//  evicted as well; it counts against the budget but not the depth.
+pub struct Rewind {
+    // Maximum number of frames held
+    depth: usize,
+
+    // Maximum number of (compressed) bytes held
+    budget: usize,
+
+    frames: VecDeque<Frame>,
+
+    // Evicted keyframe the oldest deltas are relative to (if they were not evicted yet)
+    base: Option<Vec<u8>>,
+
+    // Sum of the length of all frames (and the base)
+    size: usize,
+
+    // Uncompressed copy of the most recent keyframe (used to encode deltas)
+    key: Option<Vec<u8>>,
+
+    // Frames pushed since the most recent keyframe
+    since_key: usize,
+}
+
+impl Rewind {
+    pub fn new(depth: usize, budget: usize) -> Self {
+        Rewind {
+            depth: depth,
+            budget: budget,
+            frames: VecDeque::new(),
+            base: None,
+            size: 0,
+            key: None,
+            since_key: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.base = None;
+        self.size = 0;
+        self.key = None;
+        self.since_key = 0;
+    }
+
+    // Record a snapshot
+    pub fn push(&mut self, snapshot: Vec<u8>) {
+        let frame = match self.key {
+            Some(ref key) if self.since_key < INTERVAL => {
+                Frame::Delta(compress(&xor(&snapshot, key)))
+            }
+
+            _ => Frame::Key(compress(&snapshot)),
+        };
+
+        if frame.is_key() {
+            self.key = Some(snapshot);
+            self.since_key = 1;
+        } else {
+            self.since_key += 1;
+        }
+
+        self.size += frame.len();
+        self.frames.push_back(frame);
+
+        // Evict the oldest frames until we are within the configured limits
+        while self.frames.len() > 1 &&
+              (self.frames.len() > self.depth || self.size > self.budget) {
+            self.evict();
+        }
+    }
+
+    // Remove and return the most recent snapshot
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        let frame = match self.frames.pop_back() {
+            Some(frame) => frame,
+            None => return None,
+        };
+
+        self.size -= frame.len();
+
+        let snapshot = match frame {
+            Frame::Key(data) => {
+                // Earlier deltas (and the next frame pushed) are relative to
+                // the preceding keyframe (or the base)
+                let previous = self.frames.iter().rposition(|frame| frame.is_key());
+                self.key = match previous {
+                    Some(index) => {
+                        match self.frames[index] {
+                            Frame::Key(ref data) => Some(decompress(data)),
+                            Frame::Delta(_) => None,
+                        }
+                    }
+
+                    None => self.base.as_ref().map(|data| decompress(data)),
+                };
+                self.since_key = match previous {
+                    Some(index) => self.frames.len() - index,
+                    None => self.frames.len() + 1,
+                };
+
+                Some(decompress(&data))
+            }
+
+            Frame::Delta(data) => {
+                self.since_key -= 1;
+
+                self.key.as_ref().map(|key| xor(&decompress(&data), key))
+            }
+        };
+
+        // Once empty, the next frame pushed is a keyframe
+        if self.frames.is_empty() {
+            self.clear();
+        }
+
+        snapshot
+    }
+
+    // Drop the oldest frame
+    //  A keyframe becomes the base of the deltas that depend on it (and stays counted in
+    //  the size); the base is dropped with the last of them.
+    fn evict(&mut self) {
+        match self.frames.pop_front() {
+            Some(Frame::Key(data)) => self.base = Some(data),
+            Some(Frame::Delta(data)) => self.size -= data.len(),
+            None => return,
+        }
+
+        let based = match self.frames.front() {
+            Some(frame) => !frame.is_key(),
+            None => false,
+        };
+
+        if !based {
+            if let Some(base) = self.base.take() {
+                self.size -= base.len();
+            }
+        }
+    }
+}
+
+// XOR `a` with `b`; the shorter buffer is treated as zero-extended
+//  The result has the length of `a`.
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter()
+        .enumerate()
+        .map(|(i, &x)| x ^ b.get(i).cloned().unwrap_or(0))
+        .collect()
+}
+
+// PackBits-style run-length encoding
+//  Control byte `n` < 128: `n + 1` literal bytes follow
+//  Control byte `n` >= 128: the next byte is repeated `n - 126` times
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 4);
+    let mut i = 0;
+
+    while i < data.len() {
+        // Measure run at `i`
+        let mut run = 1;
+        while i + run < data.len() && data[i + run] == data[i] && run < 129 {
+            run += 1;
+        }
+
+        if run >= 2 {
+            out.push((run + 126) as u8);
+            out.push(data[i]);
+            i += run;
+            continue;
+        }
+
+        // Gather literals until the next run (of at least 2) begins
+        let start = i;
+        while i < data.len() && i - start < 128 {
+            if i + 1 < data.len() && data[i] == data[i + 1] {
+                break;
+            }
+
+            i += 1;
+        }
+
+        out.push((i - start - 1) as u8);
+        out.extend_from_slice(&data[start..i]);
+    }
+
+    out
+}
+
+fn decompress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+
+    while i < data.len() {
+        let n = data[i] as usize;
+        i += 1;
+
+        if n < 128 {
+            let end = cmp::min(i + n + 1, data.len());
+            out.extend_from_slice(&data[i..end]);
+            i = end;
+        } else if i < data.len() {
+            for _ in 0..(n - 126) {
+                out.push(data[i]);
+            }
+
+            i += 1;
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(frame: usize) -> Vec<u8> {
+        let mut snapshot = vec![0; 256];
+        snapshot[0] = frame as u8;
+        snapshot[frame % 256] ^= 0xAA;
+        snapshot
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rewind = Rewind::new(1000, usize::max_value());
+        for frame in 0..150 {
+            rewind.push(snapshot(frame));
+        }
+
+        for frame in (0..150).rev() {
+            assert_eq!(rewind.pop(), Some(snapshot(frame)));
+        }
+
+        assert_eq!(rewind.pop(), None);
+
+        // Recording resumes cleanly once emptied
+        rewind.push(snapshot(7));
+        rewind.push(snapshot(8));
+        assert_eq!(rewind.pop(), Some(snapshot(8)));
+        assert_eq!(rewind.pop(), Some(snapshot(7)));
+    }
+
+    #[test]
+    fn push_after_pop() {
+        let mut rewind = Rewind::new(1000, usize::max_value());
+        for frame in 0..70 {
+            rewind.push(snapshot(frame));
+        }
+
+        // Pop back across a keyframe, then record a different future
+        for _ in 0..20 {
+            rewind.pop();
+        }
+
+        for frame in 100..110 {
+            rewind.push(snapshot(frame));
+        }
+
+        for frame in (100..110).rev().chain((0..50).rev()) {
+            assert_eq!(rewind.pop(), Some(snapshot(frame)));
+        }
+
+        assert_eq!(rewind.pop(), None);
+    }
+
+    #[test]
+    fn evict_depth() {
+        // Depths both below and above the keyframe interval
+        for &depth in &[1, 10, 59, 60, 61, 100] {
+            let mut rewind = Rewind::new(depth, usize::max_value());
+            for frame in 0..200 {
+                rewind.push(snapshot(frame));
+                assert_eq!(rewind.frames.len(), cmp::min(frame + 1, depth));
+            }
+
+            for frame in (200 - depth..200).rev() {
+                assert_eq!(rewind.pop(), Some(snapshot(frame)));
+            }
+
+            assert_eq!(rewind.pop(), None);
+        }
+    }
+
+    #[test]
+    fn push_after_evict() {
+        // The oldest deltas (frames 70..90) are relative to an evicted keyframe (frame 60)
+        let mut rewind = Rewind::new(30, usize::max_value());
+        for frame in 0..100 {
+            rewind.push(snapshot(frame));
+        }
+
+        assert!(rewind.base.is_some());
+
+        for _ in 0..10 {
+            rewind.pop();
+        }
+
+        for frame in 100..110 {
+            rewind.push(snapshot(frame));
+        }
+
+        for frame in (100..110).rev().chain((70..90).rev()) {
+            assert_eq!(rewind.pop(), Some(snapshot(frame)));
+        }
+
+        assert_eq!(rewind.pop(), None);
+        assert!(rewind.base.is_none());
+    }
+
+    #[test]
+    fn evict_budget() {
+        let mut rewind = Rewind::new(1000, 100);
+        for frame in 0..200 {
+            rewind.push(snapshot(frame));
+            assert!(rewind.size <= 100 || rewind.frames.len() == 1);
+
+            let base = rewind.base.as_ref().map_or(0, Vec::len);
+            assert_eq!(rewind.size,
+                       base + rewind.frames.iter().map(Frame::len).sum::<usize>());
+        }
+
+        let len = rewind.frames.len();
+        assert!(len > 1);
+
+        for frame in (200 - len..200).rev() {
+            assert_eq!(rewind.pop(), Some(snapshot(frame)));
+        }
+    }
+}
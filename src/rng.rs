@@ -0,0 +1,189 @@
+use state;
+
+// Page $01 of the COSMAC VIP CHIP-8 interpreter ($0100-$01FF)
+//  Holds the instruction handlers; the `RND` handler is at $01D9.
+const VIP_PAGE: [u8; 0x100] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x45, 0xA3, 0x98, 0x56, 0xD4, 0xF8, 0x81, 0xBC, 0xF8, 0x95, 0xAC,
+    0x22, 0xDC, 0x12, 0x56, 0xD4, 0x06, 0xB8, 0xD4, 0x06, 0xA8, 0xD4, 0x64, 0x0A, 0x01, 0xE6, 0x8A,
+    0xF4, 0xAA, 0x3B, 0x28, 0x9A, 0xFC, 0x01, 0xBA, 0xD4, 0xF8, 0x81, 0xBA, 0x06, 0xFA, 0x0F, 0xAA,
+    0x0A, 0xAA, 0xD4, 0xE6, 0x06, 0xBF, 0x93, 0xBE, 0xF8, 0x1B, 0xAE, 0x2A, 0x1A, 0xF8, 0x00, 0x5A,
+    0x0E, 0xF5, 0x3B, 0x4B, 0x56, 0x0A, 0xFC, 0x01, 0x5A, 0x30, 0x40, 0x4E, 0xF6, 0x3B, 0x3C, 0x9F,
+    0x56, 0x2A, 0x2A, 0xD4, 0x00, 0x22, 0x86, 0x52, 0xF8, 0xF0, 0xA7, 0x07, 0x5A, 0x87, 0xF3, 0x17,
+    0x1A, 0x3A, 0x5B, 0x12, 0xD4, 0x22, 0x86, 0x52, 0xF8, 0xF0, 0xA7, 0x0A, 0x57, 0x87, 0xF3, 0x17,
+    0x1A, 0x3A, 0x6B, 0x12, 0xD4, 0x15, 0x85, 0x22, 0x73, 0x95, 0x52, 0x25, 0x45, 0xA5, 0x86, 0xFA,
+    0x0F, 0xB5, 0xD4, 0x45, 0xE6, 0xF3, 0x3A, 0x82, 0x15, 0x15, 0xD4, 0x45, 0xE6, 0xF3, 0x3A, 0x88,
+    0xD4, 0x45, 0x07, 0x30, 0x8C, 0x45, 0x07, 0x30, 0x84, 0xE6, 0x62, 0x26, 0x45, 0xA3, 0x36, 0x88,
+    0xD4, 0x3E, 0x88, 0xD4, 0xF8, 0xF0, 0xA7, 0xE7, 0x45, 0xF4, 0xA5, 0x86, 0xFA, 0x0F, 0x3B, 0xB2,
+    0xFC, 0x01, 0xB5, 0xD4, 0x45, 0x56, 0xD4, 0x45, 0xE6, 0xF4, 0x56, 0xD4, 0x45, 0xFA, 0x0F, 0x3A,
+    0xC4, 0x07, 0x56, 0xD4, 0xAF, 0x22, 0xF8, 0xD3, 0x73, 0x8F, 0xF9, 0xF0, 0x52, 0xE6, 0x07, 0xD2,
+    0x56, 0xF8, 0xFF, 0xA6, 0xF8, 0x00, 0x7E, 0x56, 0xD4, 0x19, 0x89, 0xAE, 0x93, 0xBE, 0x99, 0xEE,
+    0xF4, 0x56, 0x76, 0xE6, 0xF4, 0xB9, 0x56, 0x45, 0xF2, 0x56, 0xD4, 0x45, 0xAA, 0x86, 0xFA, 0x0F,
+    0xBA, 0xD4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+// Random number generator algorithm
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Algorithm {
+    // Xorshift (32-bit); fast with a long period
+    Xorshift,
+
+    // The COSMAC VIP interpreter's routine: a 16-bit seed (R9) is incremented on every
+    // RND and every 1/60 s interrupt; its high byte is summed with the byte of the
+    // interpreter's own code (page $01) selected by its low byte, and the sum is added
+    // to itself rotated right through the carry to give the new high byte (and result).
+    Vip,
+}
+
+impl Default for Algorithm {
+    fn default() -> Self {
+        Algorithm::Xorshift
+    }
+}
+
+// Seed for an unseeded RNG: random with std; fixed without (seed the interpreter for
+// variety)
+#[cfg(feature = "std")]
+pub fn random_seed() -> u32 {
+    ::rand::random()
+}
+
+#[cfg(not(feature = "std"))]
+pub fn random_seed() -> u32 {
+    0x2545F491
+}
+
+// Random number generator used by `RND` (CXNN)
+//  Owned by the interpreter (instead of using the thread RNG) so that runs can be
+//  reproduced from a seed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rng {
+    algorithm: Algorithm,
+    state: u32,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(Default::default(), 0)
+    }
+}
+
+impl Rng {
+    pub fn new(algorithm: Algorithm, seed: u32) -> Self {
+        let state = match algorithm {
+            // Xorshift can not leave the all-zero state
+            Algorithm::Xorshift if seed == 0 => 0x6D2B79F5,
+            Algorithm::Xorshift => seed,
+
+            Algorithm::Vip => seed & 0xFFFF,
+        };
+
+        Rng {
+            algorithm: algorithm,
+            state: state,
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    // Next random byte
+    pub fn next_u8(&mut self) -> u8 {
+        match self.algorithm {
+            Algorithm::Xorshift => {
+                self.state ^= self.state << 13;
+                self.state ^= self.state >> 17;
+                self.state ^= self.state << 5;
+
+                (self.state >> 24) as u8
+            }
+
+            Algorithm::Vip => {
+                // INC R9; D := R9.1 + M($01, R9.0) (DF := carry)
+                let r9 = (self.state + 1) & 0xFFFF;
+                let sum = (r9 >> 8) + VIP_PAGE[(r9 & 0xFF) as usize] as u32;
+                let d = sum as u8;
+
+                // SHRC (DF into bit 7); ADD the sum (R9.1 := D)
+                let hi = ((d >> 1) | (((sum >> 8) as u8) << 7)).wrapping_add(d);
+
+                self.state = ((hi as u32) << 8) | (r9 & 0xFF);
+
+                hi
+            }
+        }
+    }
+
+    // Clock by 1/60 s
+    pub fn tick(&mut self) {
+        if self.algorithm == Algorithm::Vip {
+            self.state = (self.state + 1) & 0xFFFF;
+        }
+    }
+
+    pub fn save_state(&self, w: &mut state::Writer) {
+        w.write_bool(self.algorithm == Algorithm::Vip);
+        w.write_u32(self.state);
+    }
+
+    pub fn load_state(&mut self, r: &mut state::Reader) {
+        self.algorithm = if r.read_bool() {
+            Algorithm::Vip
+        } else {
+            Algorithm::Xorshift
+        };
+
+        self.state = r.read_u32();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Algorithm, Rng, VIP_PAGE};
+
+    #[test]
+    fn seeded() {
+        for &algorithm in &[Algorithm::Xorshift, Algorithm::Vip] {
+            let mut a = Rng::new(algorithm, 1234);
+            let mut b = Rng::new(algorithm, 1234);
+            let mut c = Rng::new(algorithm, 4321);
+
+            let a: Vec<u8> = (0..64).map(|_| a.next_u8()).collect();
+            let b: Vec<u8> = (0..64).map(|_| b.next_u8()).collect();
+            let c: Vec<u8> = (0..64).map(|_| c.next_u8()).collect();
+
+            assert_eq!(a, b);
+            assert!(a != c);
+        }
+    }
+
+    #[test]
+    fn vip() {
+        // The routine itself (INC R9 .. SEP R4)
+        assert_eq!(&VIP_PAGE[0xD9..0xEB],
+                   &[0x19, 0x89, 0xAE, 0x93, 0xBE, 0x99, 0xEE, 0xF4, 0x56, 0x76, 0xE6, 0xF4,
+                     0xB9, 0x56, 0x45, 0xF2, 0x56, 0xD4]);
+
+        // R9 = $12FF -> $1300; D = $13 + [$0100] ($00) = $13; SHRC = $09; $09 + $13 = $1C
+        let mut rng = Rng::new(Algorithm::Vip, 0x12FF);
+        assert_eq!(rng.next_u8(), 0x1C);
+
+        // R9 = $1C00 -> $1C01; D = $1C + [$0101] ($00) = $1C; SHRC = $0E; $0E + $1C = $2A
+        assert_eq!(rng.next_u8(), 0x2A);
+
+        // The interrupt advances the seed; R9 = $2A01 -> $2A02 -> $2A03
+        //  D = $2A + [$0103] ($00) = $2A; SHRC = $15; $15 + $2A = $3F
+        rng.tick();
+        assert_eq!(rng.next_u8(), 0x3F);
+
+        // With a carry: R9 = $F004 -> $F005; D = $F0 + [$0105] ($45) = $35 (DF = 1)
+        //  SHRC = $9A; $9A + $35 = $CF
+        let mut rng = Rng::new(Algorithm::Vip, 0xF004);
+        assert_eq!(rng.next_u8(), 0xCF);
+
+        // Sequence from power-on (R9 = 0)
+        let mut rng = Rng::new(Algorithm::Vip, 0);
+        let sequence: Vec<u8> = (0..8).map(|_| rng.next_u8()).collect();
+        assert_eq!(sequence, [0x00, 0x00, 0x00, 0x00, 0x67, 0x8F, 0xBA, 0x98]);
+    }
+}
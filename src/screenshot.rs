@@ -0,0 +1,181 @@
+use std::cmp;
+use std::io::{self, Write};
+
+use pixel;
+
+// Screenshot image format
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    // 24-bit RGB PNG of the framebuffer
+    //  Colors (palette, CHIP-8X color lens, XO-CHIP planes) are kept as shown.
+    Png,
+
+    // 1-bit (binary) PBM of the screen; 1 is an "on" dot
+    //  Meant for monochrome modes; colors are lost.
+    Pbm,
+}
+
+// Write a framebuffer (in `format`) as PNG; each dot becomes a `scale` x `scale` block
+pub fn write_png(out: &mut Write,
+                 framebuffer: &[u8],
+                 format: pixel::Format,
+                 width: usize,
+                 height: usize,
+                 scale: usize)
+                 -> io::Result<()> {
+    let scale = cmp::max(scale, 1);
+
+    out.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    // Header: width, height, bit depth (8), color type (2 = RGB), compression,
+    // filter, and interlace methods (0 = none)
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&u32_be((width * scale) as u32));
+    header.extend_from_slice(&u32_be((height * scale) as u32));
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(out, b"IHDR", &header)?;
+
+    // Image data: each (scaled) row is preceded by its filter type (0 = none)
+    let row_len = cmp::max(width, 1) * format.bytes_per_pixel();
+    let mut image = Vec::with_capacity((width * scale * 3 + 1) * height * scale);
+    for row in framebuffer.chunks(row_len).take(height) {
+        let row: Vec<u8> = row.chunks(format.bytes_per_pixel())
+            .flat_map(|pixel| {
+                let color = format.decode(pixel);
+                let rgb = [(color >> 16) as u8, (color >> 8) as u8, color as u8];
+
+                (0..scale).flat_map(move |_| rgb.to_vec())
+            })
+            .collect();
+
+        for _ in 0..scale {
+            image.push(0);
+            image.extend_from_slice(&row);
+        }
+    }
+
+    write_chunk(out, b"IDAT", &zlib_stored(&image))?;
+    write_chunk(out, b"IEND", &[])
+}
+
+// Write the screen as 1-bit (binary) PBM; each dot becomes a `scale` x `scale` block
+pub fn write_pbm(out: &mut Write,
+                 screen: &[bool],
+                 width: usize,
+                 height: usize,
+                 scale: usize)
+                 -> io::Result<()> {
+    let scale = cmp::max(scale, 1);
+
+    write!(out, "P4\n{} {}\n", width * scale, height * scale)?;
+
+    // Rows are packed 8 dots per byte (MSB first) and padded to a whole byte
+    let mut line = vec![0u8; (width * scale + 7) / 8];
+    for row in screen.chunks(cmp::max(width, 1)).take(height) {
+        for b in &mut line {
+            *b = 0;
+        }
+
+        for (x, &dot) in row.iter().enumerate() {
+            if dot {
+                for sx in (x * scale)..((x + 1) * scale) {
+                    line[sx / 8] |= 0x80 >> (sx % 8);
+                }
+            }
+        }
+
+        for _ in 0..scale {
+            out.write_all(&line)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn u32_be(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+fn write_chunk(out: &mut Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&u32_be(data.len() as u32))?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+
+    // CRC covers the chunk type and data
+    let crc = crc32(crc32(0, kind), data);
+    out.write_all(&u32_be(crc))
+}
+
+// zlib stream of uncompressed ("stored") deflate blocks
+//  Screenshots are tiny; compressing them is not worth an encoder.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+
+    let mut blocks = data.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        // An empty, final block
+        stream.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+
+        stream.push(blocks.peek().is_none() as u8);
+        stream.extend_from_slice(&[len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&u32_be(adler32(data)));
+    stream
+}
+
+// CRC-32 (ISO-HDLC) continued from `crc`
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+
+    for &b in data {
+        crc ^= b as u32;
+
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adler32, crc32, write_pbm};
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(0, b"IEND"), 0xAE426082);
+        assert_eq!(crc32(crc32(0, b"IE"), b"ND"), 0xAE426082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    }
+
+    #[test]
+    fn pbm() {
+        let screen = [true, false, false, true];
+        let mut out = Vec::new();
+        write_pbm(&mut out, &screen, 2, 2, 3).unwrap();
+
+        assert_eq!(out, b"P4\n6 6\n\xE0\xE0\xE0\x1C\x1C\x1C");
+    }
+}
@@ -0,0 +1,119 @@
+use std::vec::Vec;
+
+// Serializes interpreter state into a flat byte buffer
+//  All multi-byte values are written little-endian.
+#[derive(Default)]
+pub struct Writer {
+    buffer: Vec<u8>,
+}
+
+impl Writer {
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.buffer.push(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.buffer.push(value as u8);
+        self.buffer.push((value >> 8) as u8);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_u16(value as u16);
+        self.write_u16((value >> 16) as u16);
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_u32(value as u32);
+        self.write_u32((value >> 32) as u32);
+    }
+
+    // Sizes and addresses are always stored as 32-bit
+    pub fn write_usize(&mut self, value: usize) {
+        self.write_u32(value as u32);
+    }
+
+    // Length-prefixed byte slice
+    pub fn write_bytes(&mut self, value: &[u8]) {
+        self.write_usize(value.len());
+        self.buffer.extend_from_slice(value);
+    }
+}
+
+// Deserializes interpreter state written by `Writer`
+//  Reads past the end of the buffer yield zeroes and flag the reader as truncated.
+pub struct Reader<'a> {
+    buffer: &'a [u8],
+    truncated: bool,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Reader {
+            buffer: buffer,
+            truncated: false,
+        }
+    }
+
+    // True if any read went past the end of the buffer
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    // True if the whole buffer has been read
+    pub fn is_at_end(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub fn read_u8(&mut self) -> u8 {
+        if let Some((&value, rest)) = self.buffer.split_first() {
+            self.buffer = rest;
+            value
+        } else {
+            self.truncated = true;
+            0
+        }
+    }
+
+    pub fn read_bool(&mut self) -> bool {
+        self.read_u8() != 0
+    }
+
+    pub fn read_u16(&mut self) -> u16 {
+        (self.read_u8() as u16) | ((self.read_u8() as u16) << 8)
+    }
+
+    pub fn read_u32(&mut self) -> u32 {
+        (self.read_u16() as u32) | ((self.read_u16() as u32) << 16)
+    }
+
+    pub fn read_u64(&mut self) -> u64 {
+        (self.read_u32() as u64) | ((self.read_u32() as u64) << 32)
+    }
+
+    pub fn read_usize(&mut self) -> usize {
+        self.read_u32() as usize
+    }
+
+    pub fn read_bytes(&mut self) -> &'a [u8] {
+        let len = self.read_usize();
+        if len > self.buffer.len() {
+            self.truncated = true;
+            self.buffer = &[];
+
+            return &[];
+        }
+
+        let (value, rest) = self.buffer.split_at(len);
+        self.buffer = rest;
+
+        value
+    }
+}
@@ -1,10 +1,48 @@
 use std::cmp;
 
 use chip_8;
-use opcode::Opcode;
+use opcode::Instruction;
 use mmu::Mmu;
 use interpreter::{Runtime, Context};
-use axal;
+use state;
+use keypad::Keypad;
+
+// Location of the 10-byte (8x10) font sprites in RAM
+const FONT10_ADDRESS: usize = 0x50;
+
+// 8x10 font sprites for digits 0-F
+const FONT10: [u8; 0xA0] = [// 0
+                            0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF,
+                            // 1
+                            0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF,
+                            // 2
+                            0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF,
+                            // 3
+                            0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF,
+                            // 4
+                            0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03,
+                            // 5
+                            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF,
+                            // 6
+                            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF,
+                            // 7
+                            0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18,
+                            // 8
+                            0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF,
+                            // 9
+                            0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF,
+                            // A
+                            0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3,
+                            // B
+                            0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC,
+                            // C
+                            0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C,
+                            // D
+                            0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC,
+                            // E
+                            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF,
+                            // F
+                            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0];
 
 #[derive(PartialEq)]
 enum DisplayMode {
@@ -33,110 +71,184 @@ pub struct SuperChip {
     mode: DisplayMode,
 }
 
+impl SuperChip {
+    // Draw a `width` (8 or 16) dot wide sprite of `height` rows from [I] at (x, y)
+    //  In standard display mode each sprite dot covers a 2x2 dot region.
+    fn draw(&self, c: &mut Context, m: &mut Mmu, x: usize, y: usize, width: usize, height: usize) {
+        let scale = if self.mode == DisplayMode::Extended { 1 } else { 2 };
+        let bytes_per_row = width / 8;
+
+        // VF is cleared at the start of DRW so collision can be set easily
+        c.v[0xF] = 0;
+
+        for i in 0..height {
+            // Read the row of the sprite (MSB is the left-most dot)
+            let mut row = 0u128;
+            for b in 0..bytes_per_row {
+                row = (row << 8) | (m.read(c.i + i * bytes_per_row + b) as u128);
+            }
+
+            // Widen each dot of the row to `scale` dots
+            let row = if scale == 1 {
+                row
+            } else {
+                (0..width).fold(0, |wide, j| {
+                    let bit = (row >> (width - 1 - j)) & 1;
+                    (wide << scale) | if bit == 1 { (1 << scale) - 1 } else { 0 }
+                })
+            };
+
+            for dy in 0..scale {
+                // VF is set to indicate the transition 1 -> 0
+                if c.display.xor(0, x * scale, (y + i) * scale + dy, row, width * scale) {
+                    c.v[0xF] = 1;
+                }
+            }
+        }
+    }
+}
+
 impl Runtime for SuperChip {
     fn configure(&mut self, c: &mut Context) {
         // Increase screen size to 128x64
-        c.screen_width = 128;
-        c.screen_height = 64;
-        c.screen.resize(c.screen_width * c.screen_height, Default::default());
+        let planes = c.display.planes();
+        c.display.resize(128, 64, planes);
     }
 
     fn reset(&mut self, c: &mut Context) {
         // Reset CHIP-8
         self.chip_8.reset(c);
 
-        // Clear scratch storage
-        for v in &mut self.v_scratch {
-            *v = 0;
-        }
+        // NOTE: Scratch storage is persistent (as the RPL user flags were) and
+        //       is intentionally left alone
 
         // Reset display mode to Standard
         self.mode = DisplayMode::Standard;
     }
 
+    fn insert_rom(&mut self, m: &mut Mmu, buffer: &[u8]) {
+        // Setup 10-byte font sprites
+        m.write_all(FONT10_ADDRESS, &FONT10);
+
+        m.write_all(0x200, buffer);
+    }
+
+    fn flags(&mut self) -> Option<&mut [u8]> {
+        Some(&mut self.v_scratch)
+    }
+
+    fn save_state(&self, w: &mut state::Writer) {
+        w.write_bytes(&self.v_scratch);
+        w.write_bool(self.mode == DisplayMode::Extended);
+    }
+
+    fn load_state(&mut self, r: &mut state::Reader) {
+        let v_scratch = r.read_bytes();
+        for (dst, src) in self.v_scratch.iter_mut().zip(v_scratch) {
+            *dst = *src;
+        }
+
+        self.mode = if r.read_bool() {
+            DisplayMode::Extended
+        } else {
+            DisplayMode::Standard
+        };
+    }
+
     fn execute(&mut self,
-               r: &mut axal::Runtime,
+               k: &mut Keypad,
                c: &mut Context,
                m: &mut Mmu,
-               opcode: Opcode)
+               instruction: Instruction)
                -> bool {
-        match opcode.unwrap() {
+        match instruction {
             // SCDOWN
-            (0x0, 0x0, 0xC, n) => {
+            Instruction::ScrollDown(n) => {
                 // Scroll screen N lines down
                 // NOTE: This always operates on a 128x64 display regardless of the active mode
-                unimplemented!();
+                c.display.scroll_down(n as usize);
             }
 
             // SCRIGHT
-            (0x0, 0x0, 0xF, 0xB) => {
+            Instruction::ScrollRight => {
                 // Scroll screen 4 dots right
                 // NOTE: This always operates on a 128x64 display regardless of the active mode
-                unimplemented!();
+                c.display.scroll_right(4);
             }
 
             // SCLEFT
-            (0x0, 0x0, 0xF, 0xC) => {
+            Instruction::ScrollLeft => {
                 // Scroll screen 4 dots left
                 // NOTE: This always operates on a 128x64 display regardless of the active mode
-                unimplemented!();
+                c.display.scroll_right(-4);
             }
 
             // SED
             // TODO: If you have a better idea for a mnemonic; a PR would be appreciated
-            (0x0, 0x0, 0xF, 0xE) => {
+            Instruction::Sed => {
                 // Set extended display mode
                 self.mode = DisplayMode::Extended;
             }
 
             // CLD
             // TODO: If you have a better idea for a mnemonic; a PR would be appreciated
-            (0x0, 0x0, 0xF, 0xF) => {
+            Instruction::Cld => {
                 // Clear extended display mode (revert to standard)
                 self.mode = DisplayMode::Standard;
             }
 
             // SHOW16 Vx, Vy
-            (0xD, _, _, 0x0) if self.mode == DisplayMode::Extended => {
+            Instruction::Show16(x, y) => {
                 // Show 16x16 sprite from [I] at coordinates (Vx, Vy); VF := collision
-                unimplemented!();
+                //  In standard display mode this is `SHOW Vx, Vy, 0` (which shows nothing).
+                let x = c.v[x as usize] as usize;
+                let y = c.v[y as usize] as usize;
+
+                if self.mode == DisplayMode::Extended {
+                    self.draw(c, m, x, y, 16, 16);
+                } else {
+                    self.draw(c, m, x, y, 8, 0);
+                }
             }
 
             // SHOW Vx, Vy, N
-            (0xD, _, _, _) => {
+            Instruction::Show(x, y, n) => {
                 // Show 8x8 sprite from [I] at coordinates (Vx, Vy); VF := collision
                 // NOTE: This must be re-implemented from CHIP-8 because in standard display mode
                 //       2x2 dot blocks are shown instead of single dots
-                unimplemented!();
+                let x = c.v[x as usize] as usize;
+                let y = c.v[y as usize] as usize;
+
+                self.draw(c, m, x, y, 8, n as usize);
             }
 
             // LD I, FONT10 Vx
-            (0xF, _, 0x3, 0x0) => {
+            Instruction::LdFont10(x) => {
                 // Point I to 10-byte font sprite for digit Vx
-                unimplemented!();
+                c.i = FONT10_ADDRESS + ((c.v[x as usize] & 0xF) as usize) * 10;
             }
 
             // SAVE Vx .. Vy
-            (0xF, x, 0x7, 0x5) => {
+            Instruction::SaveFlags(x) => {
                 // Store V0..Vx into private interpreter memory; at most 8
                 //  registers can be stored
-                for i in 0..cmp::min(x as usize, 7) {
+                for i in 0..cmp::min(x as usize + 1, 8) {
                     self.v_scratch[i] = c.v[i];
                 }
             }
 
             // RESTORE Vx .. Vy
-            (0xF, x, 0x8, 0x5) => {
+            Instruction::RestoreFlags(x) => {
                 // Restore V0..Vx from private interpreter memory; at most 8
                 //  registers can be restored
-                for i in 0..cmp::min(x as usize, 7) {
+                for i in 0..cmp::min(x as usize + 1, 8) {
                     c.v[i] = self.v_scratch[i];
                 }
             }
 
             _ => {
                 // Unhandled by SUPER-CHIP
-                return self.chip_8.execute(r, c, m, opcode);
+                return self.chip_8.execute(k, c, m, instruction);
             }
         }
 
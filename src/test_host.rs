@@ -0,0 +1,71 @@
+// Fake host for unit tests
+//  Stands in for a frontend: the key state is scripted, and every video refresh
+//  and the buzzer state of every frame are recorded for inspection.
+
+use host::Host;
+use interpreter::{Clock, Interpreter, Mode};
+use keypad::Keypad;
+use quirks::Quirks;
+
+#[derive(Default)]
+pub struct FakeHost {
+    // Key state (scripted by the test)
+    pub keys: [bool; 16],
+
+    // Keys (0-F) polled by the interpreter, in order
+    pub polled: Vec<u8>,
+
+    // Every video refresh as (framebuffer, width, height)
+    pub frames: Vec<(Vec<u8>, usize, usize)>,
+
+    // Buzzer state (ST > 0) at the end of every frame
+    pub audio: Vec<bool>,
+}
+
+impl FakeHost {
+    pub fn press(&mut self, key: u8) {
+        self.keys[(key & 0xF) as usize] = true;
+    }
+
+    // Run a _single_ frame (as `Core::run_next` does): `ipf` instructions, a 1/60 s
+    // timer tick, and a video refresh
+    pub fn run_frame(&mut self, interpreter: &mut Interpreter, ipf: usize) {
+        for _ in 0..ipf {
+            interpreter.run_next(self);
+        }
+
+        interpreter.tick();
+        self.audio_buzzer(interpreter.registers().st > 0);
+
+        let (framebuffer, width, height) = interpreter.screen_as_framebuffer();
+        self.video_refresh(framebuffer, width, height);
+    }
+}
+
+impl Keypad for FakeHost {
+    fn key_state(&mut self, key: u8) -> bool {
+        self.polled.push(key & 0xF);
+        self.keys[(key & 0xF) as usize]
+    }
+}
+
+impl Host for FakeHost {
+    fn video_refresh(&mut self, framebuffer: &[u8], width: usize, height: usize) {
+        self.frames.push((framebuffer.to_vec(), width, height));
+    }
+
+    fn audio_buzzer(&mut self, on: bool) {
+        self.audio.push(on);
+    }
+}
+
+// Construct an interpreter (on an external clock) with `rom` loaded and reset
+pub fn interpreter(mode: Mode, rom: &[u8], quirks: Option<Quirks>) -> Interpreter {
+    let mut interpreter: Interpreter = Default::default();
+    interpreter.set_clock(Clock::External);
+    interpreter.insert_rom_bytes(rom, mode);
+    interpreter.set_quirks(quirks);
+    interpreter.reset();
+
+    interpreter
+}
@@ -0,0 +1,243 @@
+use std::io::{self, Write};
+
+use interpreter::{Context, Mode};
+use opcode::{Instruction, Opcode};
+
+// Output format of the trace
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    // One line per instruction, in the format of Cadmium's trace log, followed by the
+    // timers and mnemonic after a `;`:
+    //  V0:00 .. VF:00 I:0000 SP:0 PC:0200 O:00e0 ; DT:00 ST:00 CLS
+    //  The machine state is _before_ the instruction executes. Cut the lines at `;` to
+    //  diff against a Cadmium trace.
+    Text,
+
+    // "XCTR" followed by one 25-byte record per instruction (little-endian):
+    //  PC (u16), opcode (u16), V0-VF, I (u16), SP, DT, ST
+    Binary,
+}
+
+// Limits which instructions are traced
+#[derive(Clone, Copy, Debug)]
+pub struct Filter {
+    // Inclusive PC range
+    pc: Option<(usize, usize)>,
+
+    // Opcode classes (by upper nibble) that are traced; bit N = class N
+    classes: u16,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter {
+            pc: None,
+            classes: 0xFFFF,
+        }
+    }
+}
+
+impl Filter {
+    // Only trace instructions with `start <= PC <= end`
+    pub fn pc_range(mut self, start: usize, end: usize) -> Self {
+        self.pc = Some((start, end));
+        self
+    }
+
+    // Only trace the given opcode classes (by upper nibble; eg. `0xD` for `DXYN`)
+    pub fn classes(mut self, classes: &[u8]) -> Self {
+        self.classes = classes.iter().fold(0, |mask, &class| mask | (1 << (class & 0xF)));
+        self
+    }
+
+    fn accepts(&self, pc: usize, opcode: Opcode) -> bool {
+        let (class, ..) = opcode.unwrap();
+
+        self.pc.map_or(true, |(start, end)| pc >= start && pc <= end) &&
+        (self.classes & (1 << class)) != 0
+    }
+}
+
+pub struct Tracer {
+    out: Box<Write>,
+    format: Format,
+    filter: Filter,
+
+    // True once the header (binary format) has been written
+    started: bool,
+}
+
+impl Tracer {
+    pub fn new(out: Box<Write>, format: Format) -> Self {
+        Tracer {
+            out: out,
+            format: format,
+            filter: Default::default(),
+            started: false,
+        }
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    // Record the instruction at `c.pc` (`bytes` are the 4 bytes at PC; only XO-CHIP's
+    // `F000 NNNN` uses more than 2)
+    pub fn record(&mut self, mode: Mode, c: &Context, bytes: &[u8; 4]) -> io::Result<()> {
+        let opcode = Opcode::new(bytes[0], bytes[1]);
+        if !self.filter.accepts(c.pc, opcode) {
+            return Ok(());
+        }
+
+        match self.format {
+            Format::Text => {
+                for (x, v) in c.v.iter().enumerate() {
+                    write!(self.out, "V{:X}:{:02x} ", x, v)?;
+                }
+
+                let mnemonic = Instruction::decode(mode, bytes)
+                    .map(|instruction| instruction.to_string())
+                    .unwrap_or_default();
+
+                writeln!(self.out,
+                         "I:{:04x} SP:{:1x} PC:{:04x} O:{:02x}{:02x} ; DT:{:02x} ST:{:02x} {}",
+                         c.i,
+                         c.sp,
+                         c.pc,
+                         bytes[0],
+                         bytes[1],
+                         c.dt,
+                         c.st,
+                         mnemonic)
+            }
+
+            Format::Binary => {
+                if !self.started {
+                    self.out.write_all(b"XCTR")?;
+                    self.started = true;
+                }
+
+                let mut record = [0u8; 25];
+                record[0] = c.pc as u8;
+                record[1] = (c.pc >> 8) as u8;
+                record[2] = bytes[1];
+                record[3] = bytes[0];
+                record[4..20].copy_from_slice(&c.v);
+                record[20] = c.i as u8;
+                record[21] = (c.i >> 8) as u8;
+                record[22] = c.sp;
+                record[23] = c.dt;
+                record[24] = c.st;
+
+                self.out.write_all(&record)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+    use interpreter::{Interpreter, Mode};
+    use test_host::{self, FakeHost};
+    use super::{Filter, Format, Tracer};
+
+    // Output shared with the test (the tracer owns its writer)
+    #[derive(Clone, Default)]
+    struct Sink(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // $200  LD V0, $12
+    // $202  LD I, $300
+    // $204  CALL $208
+    // $206  JP $206
+    // $208  RET
+    const ROM: [u8; 10] = [0x60, 0x12, 0xA3, 0x00, 0x22, 0x08, 0x12, 0x06, 0x00, 0xEE];
+
+    // Trace `steps` instructions of `ROM`
+    fn trace(format: Format, filter: Filter, steps: usize) -> Vec<u8> {
+        let sink: Sink = Default::default();
+
+        let mut interpreter: Interpreter = test_host::interpreter(Mode::Chip8, &ROM, None);
+        interpreter.set_tracer(Some(Tracer::new(Box::new(sink.clone()), format).filter(filter)));
+
+        for _ in 0..steps {
+            interpreter.run_next(&mut FakeHost::default());
+        }
+
+        let output = sink.0.borrow().clone();
+        output
+    }
+
+    // The PC of every line of a text trace
+    fn pcs(output: &[u8]) -> Vec<String> {
+        String::from_utf8(output.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| line.split(" PC:").nth(1).unwrap()[..4].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn text() {
+        let output = String::from_utf8(trace(Format::Text, Default::default(), 5)).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0],
+                   "V0:00 V1:00 V2:00 V3:00 V4:00 V5:00 V6:00 V7:00 V8:00 V9:00 VA:00 VB:00 \
+                    VC:00 VD:00 VE:00 VF:00 I:0000 SP:0 PC:0200 O:6012 ; DT:00 ST:00 LD V0, $12");
+
+        // The state is from _before_ each instruction
+        assert!(lines[2].starts_with("V0:12 "));
+        assert!(lines[2].contains(" I:0300 SP:0 PC:0204 O:2208 ; DT:00 ST:00 CALL $208"));
+        assert!(lines[3].contains(" I:0300 SP:1 PC:0208 O:00ee ; DT:00 ST:00 RET"));
+        assert!(lines[4].contains(" I:0300 SP:0 PC:0206 O:1206 ; DT:00 ST:00 JP $206"));
+    }
+
+    #[test]
+    fn binary() {
+        let output = trace(Format::Binary, Default::default(), 3);
+
+        assert_eq!(&output[..4], b"XCTR");
+        assert_eq!(output.len(), 4 + 3 * 25);
+
+        // LD V0, $12 done; LD I, $300 done; CALL $208 (with V0 = $12, I = $300)
+        let record = &output[4 + 2 * 25..];
+        assert_eq!(&record[..4], &[0x04, 0x02, 0x08, 0x22]);
+        assert_eq!(record[4], 0x12);
+        assert_eq!(&record[5..20], &[0; 15]);
+        assert_eq!(&record[20..], &[0x00, 0x03, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn filters() {
+        let output = trace(Format::Text, Filter::default().pc_range(0x202, 0x206), 6);
+        assert_eq!(pcs(&output), ["0202", "0204", "0206", "0206"]);
+
+        // `CALL` and `RET` (classes 2 and 0)
+        let output = trace(Format::Text, Filter::default().classes(&[0x2, 0x0]), 6);
+        assert_eq!(pcs(&output), ["0204", "0208"]);
+
+        // Both filters apply
+        let filter = Filter::default().pc_range(0x200, 0x206).classes(&[0x1, 0x2]);
+        let output = trace(Format::Text, filter, 6);
+        assert_eq!(pcs(&output), ["0204", "0206", "0206"]);
+
+        // With every instruction filtered out, a binary trace has no header either
+        let output = trace(Format::Binary, Filter::default().classes(&[0xD]), 6);
+        assert!(output.is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+use std::cmp;
+
+use chip_8;
+use opcode::Instruction;
+use mmu::Mmu;
+use interpreter::{Runtime, Context};
+use state;
+use keypad::Keypad;
+
+// TODO: XO-CHIP graphics, audio, and extended memory instructions; for now only the
+//       CHIP-8 instructions and the extended (16) flag registers are supported
+#[derive(Default)]
+pub struct XoChip {
+    // XO-CHIP starts from the CHIP-8
+    chip_8: chip_8::Chip8,
+
+    // Scratch storage for all 16 general (V) registers (the SUPER-CHIP had 8)
+    v_scratch: [u8; 16],
+}
+
+impl Runtime for XoChip {
+    fn reset(&mut self, c: &mut Context) {
+        // Reset CHIP-8
+        self.chip_8.reset(c);
+
+        // NOTE: Scratch storage is persistent and is intentionally left alone
+    }
+
+    fn flags(&mut self) -> Option<&mut [u8]> {
+        Some(&mut self.v_scratch)
+    }
+
+    fn save_state(&self, w: &mut state::Writer) {
+        w.write_bytes(&self.v_scratch);
+    }
+
+    fn load_state(&mut self, r: &mut state::Reader) {
+        let v_scratch = r.read_bytes();
+        for (dst, src) in self.v_scratch.iter_mut().zip(v_scratch) {
+            *dst = *src;
+        }
+    }
+
+    fn execute(&mut self,
+               k: &mut Keypad,
+               c: &mut Context,
+               m: &mut Mmu,
+               instruction: Instruction)
+               -> bool {
+        match instruction {
+            // SAVE Vx
+            Instruction::SaveFlags(x) => {
+                // Store V0..Vx into private interpreter memory
+                for i in 0..cmp::min(x as usize + 1, 16) {
+                    self.v_scratch[i] = c.v[i];
+                }
+            }
+
+            // RESTORE Vx
+            Instruction::RestoreFlags(x) => {
+                // Restore V0..Vx from private interpreter memory
+                for i in 0..cmp::min(x as usize + 1, 16) {
+                    c.v[i] = self.v_scratch[i];
+                }
+            }
+
+            _ => {
+                // Unhandled by XO-CHIP
+                return self.chip_8.execute(k, c, m, instruction);
+            }
+        }
+
+        true
+    }
+}
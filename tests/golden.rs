@@ -0,0 +1,395 @@
+// Golden-screen regression tests
+//  Each test ROM (tests/roms/*.8o) is assembled, run headlessly for a fixed number of
+//  frames with a scripted keypad (and, for tests/roms/quirks.8o, once per quirk set),
+//  and the final screen (and on CHIP-8X, the color of every dot), registers, framebuffer
+//  hash, and (if the ROM has a `results` label) the memory written by the ROM are compared
+//  against the golden dump in tests/golden/<name>.txt.
+//
+//  Every ROM must also disassemble to Octo source that assembles back to the same bytes.
+//
+//  Every ROM is also recompiled to Rust (tests/recompiled/<rom>.rs); the recompiled
+//  modules must be current, and running them must match the interpreter.
+//
+//  Run with XCHIP_BLESS=1 to (re)write the golden dumps and recompiled modules after an
+//  intentional change.
+
+extern crate xchip;
+
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use xchip::{Clock, Execution, Interpreter, Keypad, Mode, Quirks, Recompiled};
+
+#[path = "recompiled/chip8_alu.rs"]
+mod chip8_alu;
+#[path = "recompiled/chip8_flow.rs"]
+mod chip8_flow;
+#[path = "recompiled/chip8_memory.rs"]
+mod chip8_memory;
+#[path = "recompiled/chip8_display.rs"]
+mod chip8_display;
+#[path = "recompiled/chip8_keys.rs"]
+mod chip8_keys;
+#[path = "recompiled/schip.rs"]
+mod schip;
+#[path = "recompiled/chip8x.rs"]
+mod chip8x;
+#[path = "recompiled/quirks.rs"]
+mod quirks;
+
+// Instructions executed per frame (matches the frontend)
+const IPF: usize = 8;
+
+// Number of bytes dumped from `results`
+const RESULTS_LEN: usize = 32;
+
+struct Golden {
+    name: &'static str,
+
+    // Source of the ROM (tests/roms/<rom>.8o); several goldens can run the same ROM
+    rom: &'static str,
+
+    mode: Mode,
+
+    // Quirks (`None` for those of `mode`)
+    quirks: Option<Quirks>,
+
+    // Key presses as (frame, key, frames held)
+    presses: &'static [(usize, u8, usize)],
+
+    frames: usize,
+
+    // The ROM recompiled to Rust
+    recompiled: Recompiled,
+}
+
+const GOLDENS: &'static [Golden] = &[Golden {
+                                         name: "chip8_alu",
+                                         rom: "chip8_alu",
+                                         mode: Mode::Chip8,
+                                         quirks: None,
+                                         presses: &[],
+                                         frames: 30,
+                                         recompiled: chip8_alu::PROGRAM,
+                                     },
+                                     Golden {
+                                         name: "chip8_flow",
+                                         rom: "chip8_flow",
+                                         mode: Mode::Chip8,
+                                         quirks: None,
+                                         presses: &[],
+                                         frames: 30,
+                                         recompiled: chip8_flow::PROGRAM,
+                                     },
+                                     Golden {
+                                         name: "chip8_memory",
+                                         rom: "chip8_memory",
+                                         mode: Mode::Chip8,
+                                         quirks: None,
+                                         presses: &[],
+                                         frames: 10,
+                                         recompiled: chip8_memory::PROGRAM,
+                                     },
+                                     Golden {
+                                         name: "chip8_display",
+                                         rom: "chip8_display",
+                                         mode: Mode::Chip8,
+                                         quirks: None,
+                                         presses: &[],
+                                         frames: 60,
+                                         recompiled: chip8_display::PROGRAM,
+                                     },
+                                     Golden {
+                                         name: "chip8_keys",
+                                         rom: "chip8_keys",
+                                         mode: Mode::Chip8,
+                                         quirks: None,
+                                         presses: &[(5, 0x5, 3)],
+                                         frames: 30,
+                                         recompiled: chip8_keys::PROGRAM,
+                                     },
+                                     Golden {
+                                         name: "schip",
+                                         rom: "schip",
+                                         mode: Mode::SuperChip,
+                                         quirks: None,
+                                         presses: &[],
+                                         frames: 30,
+                                         recompiled: schip::PROGRAM,
+                                     },
+                                     Golden {
+                                         name: "chip8x",
+                                         rom: "chip8x",
+                                         mode: Mode::Chip8x,
+                                         quirks: None,
+                                         presses: &[],
+                                         frames: 30,
+                                         recompiled: chip8x::PROGRAM,
+                                     },
+                                     Golden {
+                                         name: "quirks_vip",
+                                         rom: "quirks",
+                                         mode: Mode::Chip8,
+                                         quirks: Some(Quirks {
+                                             shift_vx: false,
+                                             load_store_keep_i: false,
+                                             jump_vx: false,
+                                             add_i_vf: false,
+                                         }),
+                                         presses: &[],
+                                         frames: 10,
+                                         recompiled: quirks::PROGRAM,
+                                     },
+                                     Golden {
+                                         name: "quirks_schip",
+                                         rom: "quirks",
+                                         mode: Mode::Chip8,
+                                         quirks: Some(Quirks {
+                                             shift_vx: true,
+                                             load_store_keep_i: true,
+                                             jump_vx: true,
+                                             add_i_vf: false,
+                                         }),
+                                         presses: &[],
+                                         frames: 10,
+                                         recompiled: quirks::PROGRAM,
+                                     },
+                                     Golden {
+                                         name: "quirks_default",
+                                         rom: "quirks",
+                                         mode: Mode::Chip8,
+                                         quirks: None,
+                                         presses: &[],
+                                         frames: 10,
+                                         recompiled: quirks::PROGRAM,
+                                     }];
+
+#[derive(Default)]
+struct ScriptedKeypad {
+    keys: [bool; 16],
+}
+
+impl Keypad for ScriptedKeypad {
+    fn key_state(&mut self, key: u8) -> bool {
+        self.keys[(key & 0xF) as usize]
+    }
+}
+
+fn path(directory: &str, filename: &str) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests");
+    path.push(directory);
+    path.push(filename);
+    path
+}
+
+fn read(path: &PathBuf) -> Option<String> {
+    let mut text = String::new();
+    File::open(path).and_then(|mut file| file.read_to_string(&mut text)).ok().map(|_| text)
+}
+
+fn assemble(golden: &Golden) -> xchip::Assembly {
+    let source = read(&path("roms", &format!("{}.8o", golden.rom)))
+        .unwrap_or_else(|| panic!("{}: missing ROM source", golden.name));
+
+    xchip::assemble(&source, golden.mode).unwrap_or_else(|err| panic!("{}: {}", golden.name, err))
+}
+
+// Run the ROM for `golden` (using its recompiled module if `recompiled`) and dump the
+// final machine state
+fn run(golden: &Golden, execution: Execution, recompiled: bool) -> String {
+    let assembly = assemble(golden);
+
+    let mut interpreter: Interpreter = Default::default();
+    interpreter.set_clock(Clock::External);
+    interpreter.insert_rom_bytes(&assembly.rom, golden.mode);
+    interpreter.set_quirks(golden.quirks);
+    interpreter.set_execution(execution);
+    interpreter.reset();
+
+    if recompiled {
+        interpreter.set_recompiled(Some(golden.recompiled));
+    }
+
+    let mut keypad: ScriptedKeypad = Default::default();
+
+    for frame in 0..golden.frames {
+        for (key, state) in keypad.keys.iter_mut().enumerate() {
+            *state = golden.presses.iter().any(|&(start, k, frames)| {
+                k as usize == key && frame >= start && frame < start + frames
+            });
+        }
+
+        interpreter.run(&mut keypad, IPF);
+
+        interpreter.tick();
+    }
+
+    let mut dump = String::new();
+    writeln!(dump, "mode={:?} frames={}", golden.mode, golden.frames).unwrap();
+
+    let (screen, width, _) = interpreter.screen();
+    for row in screen.chunks(width) {
+        let line: String = row.iter().map(|&dot| if dot { '#' } else { '.' }).collect();
+        writeln!(dump, "{}", line).unwrap();
+    }
+
+    // CHIP-8X colors every dot through the color lens; dump the color (palette index) of
+    // each dot of the framebuffer
+    if golden.mode == Mode::Chip8x {
+        let format = interpreter.pixel_format();
+        let colors: Vec<u32> = interpreter.palette()
+            .colors(Mode::Chip8x)
+            .iter()
+            .map(|&color| format.decode(&format.encode(color)))
+            .collect();
+
+        let (framebuffer, width, _) = interpreter.screen_as_framebuffer();
+        for row in framebuffer.chunks(width * format.bytes_per_pixel()) {
+            let line: String = row.chunks(format.bytes_per_pixel())
+                .map(|pixel| {
+                    let color = format.decode(pixel);
+                    let index = colors.iter().position(|&c| c == color).unwrap_or(8);
+
+                    (b'0' + index as u8) as char
+                })
+                .collect();
+
+            writeln!(dump, "{}", line).unwrap();
+        }
+    }
+
+    let registers = interpreter.registers();
+    for (x, v) in registers.v.iter().enumerate() {
+        write!(dump, "{}V{:X}={:02X}", if x == 0 { "" } else { " " }, x, v).unwrap();
+    }
+
+    writeln!(dump,
+             "\nI={:04X} PC={:04X} SP={:02X} DT={:02X} ST={:02X}",
+             registers.i,
+             registers.pc,
+             registers.sp,
+             registers.dt,
+             registers.st)
+        .unwrap();
+
+    // The framebuffer covers what the screen dump does not (eg. CHIP-8X colors); it is
+    // summarized as a 64-bit FNV-1a hash
+    let mut hash = 0xcbf29ce484222325u64;
+    for &b in interpreter.screen_as_framebuffer().0 {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    writeln!(dump, "framebuffer={:016x}", hash).unwrap();
+
+    if let Some(&results) = assembly.symbols.get("results") {
+        write!(dump, "results={:04X}:", results).unwrap();
+        for address in results..results + RESULTS_LEN {
+            write!(dump, " {:02X}", interpreter.read_memory(address)).unwrap();
+        }
+
+        writeln!(dump, "").unwrap();
+    }
+
+    dump
+}
+
+#[test]
+fn golden_screens() {
+    let bless = env::var("XCHIP_BLESS").is_ok();
+    let mut failures = Vec::new();
+
+    for golden in GOLDENS {
+        let actual = run(golden, Execution::Interpret, false);
+        let filename = path("golden", &format!("{}.txt", golden.name));
+
+        if bless {
+            File::create(&filename)
+                .and_then(|mut file| file.write_all(actual.as_bytes()))
+                .unwrap_or_else(|err| panic!("{}: {}", filename.display(), err));
+
+            continue;
+        }
+
+        match read(&filename) {
+            Some(ref expected) if *expected == actual => {}
+
+            Some(expected) => {
+                failures.push(format!("{}: output differs from {}\n--- expected\n{}--- actual\n{}",
+                                      golden.name,
+                                      filename.display(),
+                                      expected,
+                                      actual));
+            }
+
+            None => {
+                failures.push(format!("{}: missing {} (run with XCHIP_BLESS=1 to create it)",
+                                      golden.name,
+                                      filename.display()));
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!("{} golden test(s) failed\n\n{}", failures.len(), failures.join("\n"));
+    }
+}
+
+// Compiled blocks (checked against the interpreter as they run) end up in the same state
+#[test]
+fn golden_screens_compiled() {
+    for golden in GOLDENS {
+        assert_eq!(run(golden, Execution::Differential, false),
+                   run(golden, Execution::Interpret, false),
+                   "{}",
+                   golden.name);
+    }
+}
+
+// Disassembled to Octo source, every ROM assembles back to the same bytes
+#[test]
+fn disassembly_round_trip() {
+    for golden in GOLDENS {
+        let rom = assemble(golden).rom;
+        let source = xchip::disassemble(&rom, golden.mode, xchip::Syntax::Octo);
+
+        match xchip::assemble(&source, golden.mode) {
+            Ok(assembly) => assert!(assembly.rom == rom, "{}", golden.name),
+            Err(err) => panic!("{}: {}\n{}", golden.name, err, source),
+        }
+    }
+}
+
+// Recompiled modules are current, and (checked against the interpreter as they run) end
+// up in the same state
+#[test]
+fn golden_screens_recompiled() {
+    let bless = env::var("XCHIP_BLESS").is_ok();
+
+    for golden in GOLDENS {
+        let source = xchip::recompile(&assemble(golden).rom, golden.mode);
+        let filename = path("recompiled", &format!("{}.rs", golden.rom));
+
+        if bless {
+            File::create(&filename)
+                .and_then(|mut file| file.write_all(source.as_bytes()))
+                .unwrap_or_else(|err| panic!("{}: {}", filename.display(), err));
+
+            continue;
+        }
+
+        assert!(read(&filename).map_or(false, |expected| expected == source),
+                "{}: {} is out of date (run with XCHIP_BLESS=1 to update it)",
+                golden.name,
+                filename.display());
+
+        assert_eq!(run(golden, Execution::Differential, true),
+                   run(golden, Execution::Interpret, false),
+                   "{}",
+                   golden.name);
+    }
+}
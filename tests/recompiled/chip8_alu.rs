@@ -0,0 +1,635 @@
+// Recompiled from a 116-byte Chip8 ROM by `xchip::recompile`; do not edit
+
+use xchip::{Context, Mmu, Mode, Recompiled, RecompiledBlock};
+
+pub const PROGRAM: Recompiled = Recompiled {
+    mode: Mode::Chip8,
+    blocks: BLOCKS,
+};
+
+const BLOCKS: &[RecompiledBlock] = &[
+    RecompiledBlock { address: 0x0200, code: CODE_0200, run: block_0200 },
+    RecompiledBlock { address: 0x0272, code: CODE_0272, run: block_0272 },
+];
+
+const CODE_0200: &[u8] = &[
+    0xA2, 0x74, 0x6F, 0x55, 0x60, 0xF0, 0x70, 0x20, 0x81, 0xF0, 0xF1, 0x55,
+    0x62, 0x42, 0x80, 0x20, 0xF0, 0x55, 0x61, 0x0A, 0x60, 0x0C, 0x80, 0x11,
+    0xF0, 0x55, 0x60, 0x0C, 0x80, 0x12, 0xF0, 0x55, 0x60, 0x0C, 0x80, 0x13,
+    0xF0, 0x55, 0x60, 0xFF, 0x61, 0x02, 0x80, 0x14, 0x81, 0xF0, 0xF1, 0x55,
+    0x60, 0x10, 0x61, 0x20, 0x80, 0x14, 0x81, 0xF0, 0xF1, 0x55, 0x60, 0x30,
+    0x61, 0x10, 0x80, 0x15, 0x81, 0xF0, 0xF1, 0x55, 0x60, 0x10, 0x61, 0x30,
+    0x80, 0x15, 0x81, 0xF0, 0xF1, 0x55, 0x60, 0x10, 0x61, 0x30, 0x80, 0x17,
+    0x81, 0xF0, 0xF1, 0x55, 0x60, 0x30, 0x61, 0x10, 0x80, 0x17, 0x81, 0xF0,
+    0xF1, 0x55, 0x60, 0x81, 0x80, 0x06, 0x81, 0xF0, 0xF1, 0x55, 0x60, 0x81,
+    0x80, 0x0E, 0x81, 0xF0, 0xF1, 0x55, 0x12, 0x72,
+];
+
+fn block_0200(c: &mut Context, m: &mut Mmu, budget: usize) -> usize {
+    // $0200  LD I, $274
+    c.pc = 0x0202;
+    c.i = 0x0274;
+
+    // $0202  LD VF, $55
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0204;
+    c.v[15] = 0x55;
+
+    // $0204  LD V0, $F0
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0206;
+    c.v[0] = 0xF0;
+
+    // $0206  ADD V0, $20
+    if budget == 3 {
+        return 3;
+    }
+
+    c.pc = 0x0208;
+    c.v[0] = c.v[0].wrapping_add(0x20);
+
+    // $0208  LD V1, VF
+    if budget == 4 {
+        return 4;
+    }
+
+    c.pc = 0x020A;
+    c.v[1] = c.v[15];
+
+    // $020A  LD [I], V1
+    if budget == 5 {
+        return 5;
+    }
+
+    c.pc = 0x020C;
+    for j in 0..2 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 2;
+    }
+
+    if !m.matches(0x020C, &CODE_0200[12..]) {
+        return 6;
+    }
+
+    // $020C  LD V2, $42
+    if budget == 6 {
+        return 6;
+    }
+
+    c.pc = 0x020E;
+    c.v[2] = 0x42;
+
+    // $020E  LD V0, V2
+    if budget == 7 {
+        return 7;
+    }
+
+    c.pc = 0x0210;
+    c.v[0] = c.v[2];
+
+    // $0210  LD [I], V0
+    if budget == 8 {
+        return 8;
+    }
+
+    c.pc = 0x0212;
+    for j in 0..1 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 1;
+    }
+
+    if !m.matches(0x0212, &CODE_0200[18..]) {
+        return 9;
+    }
+
+    // $0212  LD V1, $0A
+    if budget == 9 {
+        return 9;
+    }
+
+    c.pc = 0x0214;
+    c.v[1] = 0x0A;
+
+    // $0214  LD V0, $0C
+    if budget == 10 {
+        return 10;
+    }
+
+    c.pc = 0x0216;
+    c.v[0] = 0x0C;
+
+    // $0216  OR V0, V1
+    if budget == 11 {
+        return 11;
+    }
+
+    c.pc = 0x0218;
+    c.v[0] |= c.v[1];
+
+    // $0218  LD [I], V0
+    if budget == 12 {
+        return 12;
+    }
+
+    c.pc = 0x021A;
+    for j in 0..1 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 1;
+    }
+
+    if !m.matches(0x021A, &CODE_0200[26..]) {
+        return 13;
+    }
+
+    // $021A  LD V0, $0C
+    if budget == 13 {
+        return 13;
+    }
+
+    c.pc = 0x021C;
+    c.v[0] = 0x0C;
+
+    // $021C  AND V0, V1
+    if budget == 14 {
+        return 14;
+    }
+
+    c.pc = 0x021E;
+    c.v[0] &= c.v[1];
+
+    // $021E  LD [I], V0
+    if budget == 15 {
+        return 15;
+    }
+
+    c.pc = 0x0220;
+    for j in 0..1 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 1;
+    }
+
+    if !m.matches(0x0220, &CODE_0200[32..]) {
+        return 16;
+    }
+
+    // $0220  LD V0, $0C
+    if budget == 16 {
+        return 16;
+    }
+
+    c.pc = 0x0222;
+    c.v[0] = 0x0C;
+
+    // $0222  XOR V0, V1
+    if budget == 17 {
+        return 17;
+    }
+
+    c.pc = 0x0224;
+    c.v[0] ^= c.v[1];
+
+    // $0224  LD [I], V0
+    if budget == 18 {
+        return 18;
+    }
+
+    c.pc = 0x0226;
+    for j in 0..1 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 1;
+    }
+
+    if !m.matches(0x0226, &CODE_0200[38..]) {
+        return 19;
+    }
+
+    // $0226  LD V0, $FF
+    if budget == 19 {
+        return 19;
+    }
+
+    c.pc = 0x0228;
+    c.v[0] = 0xFF;
+
+    // $0228  LD V1, $02
+    if budget == 20 {
+        return 20;
+    }
+
+    c.pc = 0x022A;
+    c.v[1] = 0x02;
+
+    // $022A  ADD V0, V1
+    if budget == 21 {
+        return 21;
+    }
+
+    c.pc = 0x022C;
+    let r = c.v[0] as u16 + c.v[1] as u16;
+    c.v[0] = r as u8;
+    c.v[0xF] = (r > 0xFF) as u8;
+
+    // $022C  LD V1, VF
+    if budget == 22 {
+        return 22;
+    }
+
+    c.pc = 0x022E;
+    c.v[1] = c.v[15];
+
+    // $022E  LD [I], V1
+    if budget == 23 {
+        return 23;
+    }
+
+    c.pc = 0x0230;
+    for j in 0..2 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 2;
+    }
+
+    if !m.matches(0x0230, &CODE_0200[48..]) {
+        return 24;
+    }
+
+    // $0230  LD V0, $10
+    if budget == 24 {
+        return 24;
+    }
+
+    c.pc = 0x0232;
+    c.v[0] = 0x10;
+
+    // $0232  LD V1, $20
+    if budget == 25 {
+        return 25;
+    }
+
+    c.pc = 0x0234;
+    c.v[1] = 0x20;
+
+    // $0234  ADD V0, V1
+    if budget == 26 {
+        return 26;
+    }
+
+    c.pc = 0x0236;
+    let r = c.v[0] as u16 + c.v[1] as u16;
+    c.v[0] = r as u8;
+    c.v[0xF] = (r > 0xFF) as u8;
+
+    // $0236  LD V1, VF
+    if budget == 27 {
+        return 27;
+    }
+
+    c.pc = 0x0238;
+    c.v[1] = c.v[15];
+
+    // $0238  LD [I], V1
+    if budget == 28 {
+        return 28;
+    }
+
+    c.pc = 0x023A;
+    for j in 0..2 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 2;
+    }
+
+    if !m.matches(0x023A, &CODE_0200[58..]) {
+        return 29;
+    }
+
+    // $023A  LD V0, $30
+    if budget == 29 {
+        return 29;
+    }
+
+    c.pc = 0x023C;
+    c.v[0] = 0x30;
+
+    // $023C  LD V1, $10
+    if budget == 30 {
+        return 30;
+    }
+
+    c.pc = 0x023E;
+    c.v[1] = 0x10;
+
+    // $023E  SUB V0, V1
+    if budget == 31 {
+        return 31;
+    }
+
+    c.pc = 0x0240;
+    let (vx, vy) = (c.v[0], c.v[1]);
+    c.v[0] = vx.wrapping_sub(vy);
+    c.v[0xF] = (vy <= vx) as u8;
+
+    // $0240  LD V1, VF
+    if budget == 32 {
+        return 32;
+    }
+
+    c.pc = 0x0242;
+    c.v[1] = c.v[15];
+
+    // $0242  LD [I], V1
+    if budget == 33 {
+        return 33;
+    }
+
+    c.pc = 0x0244;
+    for j in 0..2 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 2;
+    }
+
+    if !m.matches(0x0244, &CODE_0200[68..]) {
+        return 34;
+    }
+
+    // $0244  LD V0, $10
+    if budget == 34 {
+        return 34;
+    }
+
+    c.pc = 0x0246;
+    c.v[0] = 0x10;
+
+    // $0246  LD V1, $30
+    if budget == 35 {
+        return 35;
+    }
+
+    c.pc = 0x0248;
+    c.v[1] = 0x30;
+
+    // $0248  SUB V0, V1
+    if budget == 36 {
+        return 36;
+    }
+
+    c.pc = 0x024A;
+    let (vx, vy) = (c.v[0], c.v[1]);
+    c.v[0] = vx.wrapping_sub(vy);
+    c.v[0xF] = (vy <= vx) as u8;
+
+    // $024A  LD V1, VF
+    if budget == 37 {
+        return 37;
+    }
+
+    c.pc = 0x024C;
+    c.v[1] = c.v[15];
+
+    // $024C  LD [I], V1
+    if budget == 38 {
+        return 38;
+    }
+
+    c.pc = 0x024E;
+    for j in 0..2 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 2;
+    }
+
+    if !m.matches(0x024E, &CODE_0200[78..]) {
+        return 39;
+    }
+
+    // $024E  LD V0, $10
+    if budget == 39 {
+        return 39;
+    }
+
+    c.pc = 0x0250;
+    c.v[0] = 0x10;
+
+    // $0250  LD V1, $30
+    if budget == 40 {
+        return 40;
+    }
+
+    c.pc = 0x0252;
+    c.v[1] = 0x30;
+
+    // $0252  SUBN V0, V1
+    if budget == 41 {
+        return 41;
+    }
+
+    c.pc = 0x0254;
+    let (vx, vy) = (c.v[0], c.v[1]);
+    c.v[0] = vy.wrapping_sub(vx);
+    c.v[0xF] = (vx <= vy) as u8;
+
+    // $0254  LD V1, VF
+    if budget == 42 {
+        return 42;
+    }
+
+    c.pc = 0x0256;
+    c.v[1] = c.v[15];
+
+    // $0256  LD [I], V1
+    if budget == 43 {
+        return 43;
+    }
+
+    c.pc = 0x0258;
+    for j in 0..2 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 2;
+    }
+
+    if !m.matches(0x0258, &CODE_0200[88..]) {
+        return 44;
+    }
+
+    // $0258  LD V0, $30
+    if budget == 44 {
+        return 44;
+    }
+
+    c.pc = 0x025A;
+    c.v[0] = 0x30;
+
+    // $025A  LD V1, $10
+    if budget == 45 {
+        return 45;
+    }
+
+    c.pc = 0x025C;
+    c.v[1] = 0x10;
+
+    // $025C  SUBN V0, V1
+    if budget == 46 {
+        return 46;
+    }
+
+    c.pc = 0x025E;
+    let (vx, vy) = (c.v[0], c.v[1]);
+    c.v[0] = vy.wrapping_sub(vx);
+    c.v[0xF] = (vx <= vy) as u8;
+
+    // $025E  LD V1, VF
+    if budget == 47 {
+        return 47;
+    }
+
+    c.pc = 0x0260;
+    c.v[1] = c.v[15];
+
+    // $0260  LD [I], V1
+    if budget == 48 {
+        return 48;
+    }
+
+    c.pc = 0x0262;
+    for j in 0..2 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 2;
+    }
+
+    if !m.matches(0x0262, &CODE_0200[98..]) {
+        return 49;
+    }
+
+    // $0262  LD V0, $81
+    if budget == 49 {
+        return 49;
+    }
+
+    c.pc = 0x0264;
+    c.v[0] = 0x81;
+
+    // $0264  SHR V0, V0
+    if budget == 50 {
+        return 50;
+    }
+
+    c.pc = 0x0266;
+    let r = c.v[0];
+    c.v[0] = r >> 1;
+    c.v[0xF] = r & 1;
+
+    // $0266  LD V1, VF
+    if budget == 51 {
+        return 51;
+    }
+
+    c.pc = 0x0268;
+    c.v[1] = c.v[15];
+
+    // $0268  LD [I], V1
+    if budget == 52 {
+        return 52;
+    }
+
+    c.pc = 0x026A;
+    for j in 0..2 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 2;
+    }
+
+    if !m.matches(0x026A, &CODE_0200[106..]) {
+        return 53;
+    }
+
+    // $026A  LD V0, $81
+    if budget == 53 {
+        return 53;
+    }
+
+    c.pc = 0x026C;
+    c.v[0] = 0x81;
+
+    // $026C  SHL V0, V0
+    if budget == 54 {
+        return 54;
+    }
+
+    c.pc = 0x026E;
+    let r = c.v[0];
+    c.v[0] = r << 1;
+    c.v[0xF] = r >> 7;
+
+    // $026E  LD V1, VF
+    if budget == 55 {
+        return 55;
+    }
+
+    c.pc = 0x0270;
+    c.v[1] = c.v[15];
+
+    // $0270  LD [I], V1
+    if budget == 56 {
+        return 56;
+    }
+
+    c.pc = 0x0272;
+    for j in 0..2 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 2;
+    }
+
+    if !m.matches(0x0272, &CODE_0200[114..]) {
+        return 57;
+    }
+
+    // $0272  JP $272
+    if budget == 57 {
+        return 57;
+    }
+
+    c.pc = 0x0274;
+    c.pc = 0x0272;
+
+    58
+}
+
+const CODE_0272: &[u8] = &[
+    0x12, 0x72,
+];
+
+fn block_0272(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0272  JP $272
+    c.pc = 0x0274;
+    c.pc = 0x0272;
+
+    1
+}
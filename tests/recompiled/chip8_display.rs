@@ -0,0 +1,285 @@
+// Recompiled from a 54-byte Chip8 ROM by `xchip::recompile`; do not edit
+
+use xchip::{Context, Mmu, Mode, Recompiled, RecompiledBlock};
+
+pub const PROGRAM: Recompiled = Recompiled {
+    mode: Mode::Chip8,
+    blocks: BLOCKS,
+};
+
+const BLOCKS: &[RecompiledBlock] = &[
+    RecompiledBlock { address: 0x0202, code: CODE_0202, run: block_0202 },
+    RecompiledBlock { address: 0x0208, code: CODE_0208, run: block_0208 },
+    RecompiledBlock { address: 0x020C, code: CODE_020C, run: block_020c },
+    RecompiledBlock { address: 0x0210, code: CODE_0210, run: block_0210 },
+    RecompiledBlock { address: 0x0212, code: CODE_0212, run: block_0212 },
+    RecompiledBlock { address: 0x0216, code: CODE_0216, run: block_0216 },
+    RecompiledBlock { address: 0x021A, code: CODE_021A, run: block_021a },
+    RecompiledBlock { address: 0x021C, code: CODE_021C, run: block_021c },
+    RecompiledBlock { address: 0x0226, code: CODE_0226, run: block_0226 },
+    RecompiledBlock { address: 0x022A, code: CODE_022A, run: block_022a },
+    RecompiledBlock { address: 0x0232, code: CODE_0232, run: block_0232 },
+    RecompiledBlock { address: 0x0234, code: CODE_0234, run: block_0234 },
+];
+
+const CODE_0202: &[u8] = &[
+    0x60, 0x00, 0x61, 0x01, 0x62, 0x00, 0xF2, 0x29,
+];
+
+fn block_0202(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0202  LD V0, $00
+    c.pc = 0x0204;
+    c.v[0] = 0x00;
+
+    // $0204  LD V1, $01
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0206;
+    c.v[1] = 0x01;
+
+    // $0206  LD V2, $00
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0208;
+    c.v[2] = 0x00;
+
+    // $0208  LD [I], FONT V2
+    if budget == 3 {
+        return 3;
+    }
+
+    c.pc = 0x020A;
+    c.i = ((c.v[2] & 0xF) as usize) * 5;
+
+    4
+}
+
+const CODE_0208: &[u8] = &[
+    0xF2, 0x29,
+];
+
+fn block_0208(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0208  LD [I], FONT V2
+    c.pc = 0x020A;
+    c.i = ((c.v[2] & 0xF) as usize) * 5;
+
+    1
+}
+
+const CODE_020C: &[u8] = &[
+    0x70, 0x08, 0x30, 0x40,
+];
+
+fn block_020c(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $020C  ADD V0, $08
+    c.pc = 0x020E;
+    c.v[0] = c.v[0].wrapping_add(0x08);
+
+    // $020E  SE V0, $40
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0210;
+    if c.v[0] == 0x40 {
+        c.pc = 0x0212;
+    }
+
+    2
+}
+
+const CODE_0210: &[u8] = &[
+    0x12, 0x16,
+];
+
+fn block_0210(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0210  JP $216
+    c.pc = 0x0212;
+    c.pc = 0x0216;
+
+    1
+}
+
+const CODE_0212: &[u8] = &[
+    0x60, 0x00, 0x71, 0x07, 0x72, 0x01, 0x32, 0x10,
+];
+
+fn block_0212(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0212  LD V0, $00
+    c.pc = 0x0214;
+    c.v[0] = 0x00;
+
+    // $0214  ADD V1, $07
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0216;
+    c.v[1] = c.v[1].wrapping_add(0x07);
+
+    // $0216  ADD V2, $01
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0218;
+    c.v[2] = c.v[2].wrapping_add(0x01);
+
+    // $0218  SE V2, $10
+    if budget == 3 {
+        return 3;
+    }
+
+    c.pc = 0x021A;
+    if c.v[2] == 0x10 {
+        c.pc = 0x021C;
+    }
+
+    4
+}
+
+const CODE_0216: &[u8] = &[
+    0x72, 0x01, 0x32, 0x10,
+];
+
+fn block_0216(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0216  ADD V2, $01
+    c.pc = 0x0218;
+    c.v[2] = c.v[2].wrapping_add(0x01);
+
+    // $0218  SE V2, $10
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x021A;
+    if c.v[2] == 0x10 {
+        c.pc = 0x021C;
+    }
+
+    2
+}
+
+const CODE_021A: &[u8] = &[
+    0x12, 0x08,
+];
+
+fn block_021a(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $021A  JP $208
+    c.pc = 0x021C;
+    c.pc = 0x0208;
+
+    1
+}
+
+const CODE_021C: &[u8] = &[
+    0x60, 0x3D, 0x61, 0x1D, 0x62, 0x08, 0xF2, 0x29,
+];
+
+fn block_021c(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $021C  LD V0, $3D
+    c.pc = 0x021E;
+    c.v[0] = 0x3D;
+
+    // $021E  LD V1, $1D
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0220;
+    c.v[1] = 0x1D;
+
+    // $0220  LD V2, $08
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0222;
+    c.v[2] = 0x08;
+
+    // $0222  LD [I], FONT V2
+    if budget == 3 {
+        return 3;
+    }
+
+    c.pc = 0x0224;
+    c.i = ((c.v[2] & 0xF) as usize) * 5;
+
+    4
+}
+
+const CODE_0226: &[u8] = &[
+    0x85, 0xF0,
+];
+
+fn block_0226(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0226  LD V5, VF
+    c.pc = 0x0228;
+    c.v[5] = c.v[15];
+
+    1
+}
+
+const CODE_022A: &[u8] = &[
+    0x86, 0xF0, 0x60, 0x02, 0x61, 0x03,
+];
+
+fn block_022a(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $022A  LD V6, VF
+    c.pc = 0x022C;
+    c.v[6] = c.v[15];
+
+    // $022C  LD V0, $02
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x022E;
+    c.v[0] = 0x02;
+
+    // $022E  LD V1, $03
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0230;
+    c.v[1] = 0x03;
+
+    3
+}
+
+const CODE_0232: &[u8] = &[
+    0x87, 0xF0, 0x12, 0x34,
+];
+
+fn block_0232(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0232  LD V7, VF
+    c.pc = 0x0234;
+    c.v[7] = c.v[15];
+
+    // $0234  JP $234
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0236;
+    c.pc = 0x0234;
+
+    2
+}
+
+const CODE_0234: &[u8] = &[
+    0x12, 0x34,
+];
+
+fn block_0234(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0234  JP $234
+    c.pc = 0x0236;
+    c.pc = 0x0234;
+
+    1
+}
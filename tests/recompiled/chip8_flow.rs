@@ -0,0 +1,406 @@
+// Recompiled from a 66-byte Chip8 ROM by `xchip::recompile`; do not edit
+
+use xchip::{Context, Mmu, Mode, Recompiled, RecompiledBlock};
+
+pub const PROGRAM: Recompiled = Recompiled {
+    mode: Mode::Chip8,
+    blocks: BLOCKS,
+};
+
+const BLOCKS: &[RecompiledBlock] = &[
+    RecompiledBlock { address: 0x0200, code: CODE_0200, run: block_0200 },
+    RecompiledBlock { address: 0x0206, code: CODE_0206, run: block_0206 },
+    RecompiledBlock { address: 0x0208, code: CODE_0208, run: block_0208 },
+    RecompiledBlock { address: 0x020A, code: CODE_020A, run: block_020a },
+    RecompiledBlock { address: 0x020C, code: CODE_020C, run: block_020c },
+    RecompiledBlock { address: 0x0210, code: CODE_0210, run: block_0210 },
+    RecompiledBlock { address: 0x0212, code: CODE_0212, run: block_0212 },
+    RecompiledBlock { address: 0x0214, code: CODE_0214, run: block_0214 },
+    RecompiledBlock { address: 0x0216, code: CODE_0216, run: block_0216 },
+    RecompiledBlock { address: 0x021A, code: CODE_021A, run: block_021a },
+    RecompiledBlock { address: 0x021C, code: CODE_021C, run: block_021c },
+    RecompiledBlock { address: 0x021E, code: CODE_021E, run: block_021e },
+    RecompiledBlock { address: 0x0220, code: CODE_0220, run: block_0220 },
+    RecompiledBlock { address: 0x0222, code: CODE_0222, run: block_0222 },
+    RecompiledBlock { address: 0x0224, code: CODE_0224, run: block_0224 },
+    RecompiledBlock { address: 0x0226, code: CODE_0226, run: block_0226 },
+    RecompiledBlock { address: 0x023A, code: CODE_023A, run: block_023a },
+    RecompiledBlock { address: 0x023E, code: CODE_023E, run: block_023e },
+    RecompiledBlock { address: 0x0240, code: CODE_0240, run: block_0240 },
+];
+
+const CODE_0200: &[u8] = &[
+    0x60, 0x00, 0x61, 0x07, 0x41, 0x07,
+];
+
+fn block_0200(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0200  LD V0, $00
+    c.pc = 0x0202;
+    c.v[0] = 0x00;
+
+    // $0202  LD V1, $07
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0204;
+    c.v[1] = 0x07;
+
+    // $0204  SNE V1, $07
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0206;
+    if c.v[1] != 0x07 {
+        c.pc = 0x0208;
+    }
+
+    3
+}
+
+const CODE_0206: &[u8] = &[
+    0x70, 0x01, 0x31, 0x07,
+];
+
+fn block_0206(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0206  ADD V0, $01
+    c.pc = 0x0208;
+    c.v[0] = c.v[0].wrapping_add(0x01);
+
+    // $0208  SE V1, $07
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x020A;
+    if c.v[1] == 0x07 {
+        c.pc = 0x020C;
+    }
+
+    2
+}
+
+const CODE_0208: &[u8] = &[
+    0x31, 0x07,
+];
+
+fn block_0208(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0208  SE V1, $07
+    c.pc = 0x020A;
+    if c.v[1] == 0x07 {
+        c.pc = 0x020C;
+    }
+
+    1
+}
+
+const CODE_020A: &[u8] = &[
+    0x70, 0x10, 0x62, 0x07, 0x91, 0x20,
+];
+
+fn block_020a(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $020A  ADD V0, $10
+    c.pc = 0x020C;
+    c.v[0] = c.v[0].wrapping_add(0x10);
+
+    // $020C  LD V2, $07
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x020E;
+    c.v[2] = 0x07;
+
+    // $020E  SNE V1, V2
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0210;
+    if c.v[1] != c.v[2] {
+        c.pc = 0x0212;
+    }
+
+    3
+}
+
+const CODE_020C: &[u8] = &[
+    0x62, 0x07, 0x91, 0x20,
+];
+
+fn block_020c(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $020C  LD V2, $07
+    c.pc = 0x020E;
+    c.v[2] = 0x07;
+
+    // $020E  SNE V1, V2
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0210;
+    if c.v[1] != c.v[2] {
+        c.pc = 0x0212;
+    }
+
+    2
+}
+
+const CODE_0210: &[u8] = &[
+    0x70, 0x01, 0x51, 0x20,
+];
+
+fn block_0210(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0210  ADD V0, $01
+    c.pc = 0x0212;
+    c.v[0] = c.v[0].wrapping_add(0x01);
+
+    // $0212  SE V1, V2
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0214;
+    if c.v[1] == c.v[2] {
+        c.pc = 0x0216;
+    }
+
+    2
+}
+
+const CODE_0212: &[u8] = &[
+    0x51, 0x20,
+];
+
+fn block_0212(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0212  SE V1, V2
+    c.pc = 0x0214;
+    if c.v[1] == c.v[2] {
+        c.pc = 0x0216;
+    }
+
+    1
+}
+
+const CODE_0214: &[u8] = &[
+    0x70, 0x10, 0x62, 0x08, 0x51, 0x20,
+];
+
+fn block_0214(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0214  ADD V0, $10
+    c.pc = 0x0216;
+    c.v[0] = c.v[0].wrapping_add(0x10);
+
+    // $0216  LD V2, $08
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0218;
+    c.v[2] = 0x08;
+
+    // $0218  SE V1, V2
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x021A;
+    if c.v[1] == c.v[2] {
+        c.pc = 0x021C;
+    }
+
+    3
+}
+
+const CODE_0216: &[u8] = &[
+    0x62, 0x08, 0x51, 0x20,
+];
+
+fn block_0216(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0216  LD V2, $08
+    c.pc = 0x0218;
+    c.v[2] = 0x08;
+
+    // $0218  SE V1, V2
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x021A;
+    if c.v[1] == c.v[2] {
+        c.pc = 0x021C;
+    }
+
+    2
+}
+
+const CODE_021A: &[u8] = &[
+    0x70, 0x01, 0x91, 0x20,
+];
+
+fn block_021a(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $021A  ADD V0, $01
+    c.pc = 0x021C;
+    c.v[0] = c.v[0].wrapping_add(0x01);
+
+    // $021C  SNE V1, V2
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x021E;
+    if c.v[1] != c.v[2] {
+        c.pc = 0x0220;
+    }
+
+    2
+}
+
+const CODE_021C: &[u8] = &[
+    0x91, 0x20,
+];
+
+fn block_021c(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $021C  SNE V1, V2
+    c.pc = 0x021E;
+    if c.v[1] != c.v[2] {
+        c.pc = 0x0220;
+    }
+
+    1
+}
+
+const CODE_021E: &[u8] = &[
+    0x70, 0x10, 0x22, 0x3A,
+];
+
+fn block_021e(c: &mut Context, m: &mut Mmu, budget: usize) -> usize {
+    // $021E  ADD V0, $10
+    c.pc = 0x0220;
+    c.v[0] = c.v[0].wrapping_add(0x10);
+
+    // $0220  CALL $23A
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0222;
+    c.stack_push(m, 0x0222);
+    c.pc = 0x023A;
+
+    2
+}
+
+const CODE_0220: &[u8] = &[
+    0x22, 0x3A,
+];
+
+fn block_0220(c: &mut Context, m: &mut Mmu, _budget: usize) -> usize {
+    // $0220  CALL $23A
+    c.pc = 0x0222;
+    c.stack_push(m, 0x0222);
+    c.pc = 0x023A;
+
+    1
+}
+
+const CODE_0222: &[u8] = &[
+    0x22, 0x3A,
+];
+
+fn block_0222(c: &mut Context, m: &mut Mmu, _budget: usize) -> usize {
+    // $0222  CALL $23A
+    c.pc = 0x0224;
+    c.stack_push(m, 0x0224);
+    c.pc = 0x023A;
+
+    1
+}
+
+const CODE_0224: &[u8] = &[
+    0x22, 0x3E,
+];
+
+fn block_0224(c: &mut Context, m: &mut Mmu, _budget: usize) -> usize {
+    // $0224  CALL $23E
+    c.pc = 0x0226;
+    c.stack_push(m, 0x0226);
+    c.pc = 0x023E;
+
+    1
+}
+
+const CODE_0226: &[u8] = &[
+    0x83, 0x00, 0x60, 0x02, 0xB2, 0x2C,
+];
+
+fn block_0226(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0226  LD V3, V0
+    c.pc = 0x0228;
+    c.v[3] = c.v[0];
+
+    // $0228  LD V0, $02
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x022A;
+    c.v[0] = 0x02;
+
+    // $022A  JP V0, $22C
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x022C;
+    let v = c.v[if c.quirks.jump_vx { 2 } else { 0 }];
+    c.pc = (0x022Cu16.wrapping_add(v as u16)) as usize;
+
+    3
+}
+
+const CODE_023A: &[u8] = &[
+    0x70, 0x01, 0x00, 0xEE,
+];
+
+fn block_023a(c: &mut Context, m: &mut Mmu, budget: usize) -> usize {
+    // $023A  ADD V0, $01
+    c.pc = 0x023C;
+    c.v[0] = c.v[0].wrapping_add(0x01);
+
+    // $023C  RET
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x023E;
+    c.pc = c.stack_pop(m) as usize;
+
+    2
+}
+
+const CODE_023E: &[u8] = &[
+    0x22, 0x3A,
+];
+
+fn block_023e(c: &mut Context, m: &mut Mmu, _budget: usize) -> usize {
+    // $023E  CALL $23A
+    c.pc = 0x0240;
+    c.stack_push(m, 0x0240);
+    c.pc = 0x023A;
+
+    1
+}
+
+const CODE_0240: &[u8] = &[
+    0x00, 0xEE,
+];
+
+fn block_0240(c: &mut Context, m: &mut Mmu, _budget: usize) -> usize {
+    // $0240  RET
+    c.pc = 0x0242;
+    c.pc = c.stack_pop(m) as usize;
+
+    1
+}
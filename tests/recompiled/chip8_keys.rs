@@ -0,0 +1,142 @@
+// Recompiled from a 32-byte Chip8 ROM by `xchip::recompile`; do not edit
+
+use xchip::{Context, Mmu, Mode, Recompiled, RecompiledBlock};
+
+pub const PROGRAM: Recompiled = Recompiled {
+    mode: Mode::Chip8,
+    blocks: BLOCKS,
+};
+
+const BLOCKS: &[RecompiledBlock] = &[
+    RecompiledBlock { address: 0x0202, code: CODE_0202, run: block_0202 },
+    RecompiledBlock { address: 0x0206, code: CODE_0206, run: block_0206 },
+    RecompiledBlock { address: 0x020A, code: CODE_020A, run: block_020a },
+    RecompiledBlock { address: 0x020C, code: CODE_020C, run: block_020c },
+    RecompiledBlock { address: 0x0214, code: CODE_0214, run: block_0214 },
+    RecompiledBlock { address: 0x0216, code: CODE_0216, run: block_0216 },
+    RecompiledBlock { address: 0x021E, code: CODE_021E, run: block_021e },
+];
+
+const CODE_0202: &[u8] = &[
+    0x60, 0x05, 0x63, 0x00, 0x73, 0x01,
+];
+
+fn block_0202(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0202  LD V0, $05
+    c.pc = 0x0204;
+    c.v[0] = 0x05;
+
+    // $0204  LD V3, $00
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0206;
+    c.v[3] = 0x00;
+
+    // $0206  ADD V3, $01
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0208;
+    c.v[3] = c.v[3].wrapping_add(0x01);
+
+    3
+}
+
+const CODE_0206: &[u8] = &[
+    0x73, 0x01,
+];
+
+fn block_0206(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0206  ADD V3, $01
+    c.pc = 0x0208;
+    c.v[3] = c.v[3].wrapping_add(0x01);
+
+    1
+}
+
+const CODE_020A: &[u8] = &[
+    0x12, 0x06,
+];
+
+fn block_020a(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $020A  JP $206
+    c.pc = 0x020C;
+    c.pc = 0x0206;
+
+    1
+}
+
+const CODE_020C: &[u8] = &[
+    0x61, 0x04, 0xF0, 0x29,
+];
+
+fn block_020c(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $020C  LD V1, $04
+    c.pc = 0x020E;
+    c.v[1] = 0x04;
+
+    // $020E  LD [I], FONT V0
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0210;
+    c.i = ((c.v[0] & 0xF) as usize) * 5;
+
+    2
+}
+
+const CODE_0214: &[u8] = &[
+    0x12, 0x12,
+];
+
+fn block_0214(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0214  JP $212
+    c.pc = 0x0216;
+    c.pc = 0x0212;
+
+    1
+}
+
+const CODE_0216: &[u8] = &[
+    0x61, 0x0C, 0x62, 0x0A, 0xF2, 0x29,
+];
+
+fn block_0216(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0216  LD V1, $0C
+    c.pc = 0x0218;
+    c.v[1] = 0x0C;
+
+    // $0218  LD V2, $0A
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x021A;
+    c.v[2] = 0x0A;
+
+    // $021A  LD [I], FONT V2
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x021C;
+    c.i = ((c.v[2] & 0xF) as usize) * 5;
+
+    3
+}
+
+const CODE_021E: &[u8] = &[
+    0x12, 0x1E,
+];
+
+fn block_021e(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $021E  JP $21E
+    c.pc = 0x0220;
+    c.pc = 0x021E;
+
+    1
+}
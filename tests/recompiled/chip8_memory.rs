@@ -0,0 +1,253 @@
+// Recompiled from a 48-byte Chip8 ROM by `xchip::recompile`; do not edit
+
+use xchip::{Context, Mmu, Mode, Recompiled, RecompiledBlock};
+
+pub const PROGRAM: Recompiled = Recompiled {
+    mode: Mode::Chip8,
+    blocks: BLOCKS,
+};
+
+const BLOCKS: &[RecompiledBlock] = &[
+    RecompiledBlock { address: 0x0200, code: CODE_0200, run: block_0200 },
+    RecompiledBlock { address: 0x022A, code: CODE_022A, run: block_022a },
+    RecompiledBlock { address: 0x022E, code: CODE_022E, run: block_022e },
+];
+
+const CODE_0200: &[u8] = &[
+    0xA2, 0x30, 0x60, 0x7B, 0xF0, 0x33, 0x61, 0x03, 0xF1, 0x1E, 0x60, 0xAB,
+    0x61, 0xCD, 0x62, 0xEF, 0xF2, 0x55, 0xA2, 0x30, 0xF2, 0x65, 0xAF, 0xFF,
+    0x69, 0x02, 0xF9, 0x1E, 0x8A, 0xF0, 0x66, 0xFF, 0xC6, 0x00, 0x67, 0x1E,
+    0xF7, 0x15, 0xF7, 0x18,
+];
+
+fn block_0200(c: &mut Context, m: &mut Mmu, budget: usize) -> usize {
+    // $0200  LD I, $230
+    c.pc = 0x0202;
+    c.i = 0x0230;
+
+    // $0202  LD V0, $7B
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0204;
+    c.v[0] = 0x7B;
+
+    // $0204  LD [I], BCD V0
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0206;
+    let (r, i) = (c.v[0], c.i);
+    m.write(i, r / 100);
+    m.write(i + 1, (r % 100) / 10);
+    m.write(i + 2, r % 10);
+
+    if !m.matches(0x0206, &CODE_0200[6..]) {
+        return 3;
+    }
+
+    // $0206  LD V1, $03
+    if budget == 3 {
+        return 3;
+    }
+
+    c.pc = 0x0208;
+    c.v[1] = 0x03;
+
+    // $0208  ADD I, V1
+    if budget == 4 {
+        return 4;
+    }
+
+    c.pc = 0x020A;
+    let r = c.i as u32 + c.v[1] as u32;
+    let mask = (m.size() - 1) as u32;
+    c.i = (r & mask) as usize;
+    if c.quirks.add_i_vf {
+        c.v[0xF] = (r > mask) as u8;
+    }
+
+    // $020A  LD V0, $AB
+    if budget == 5 {
+        return 5;
+    }
+
+    c.pc = 0x020C;
+    c.v[0] = 0xAB;
+
+    // $020C  LD V1, $CD
+    if budget == 6 {
+        return 6;
+    }
+
+    c.pc = 0x020E;
+    c.v[1] = 0xCD;
+
+    // $020E  LD V2, $EF
+    if budget == 7 {
+        return 7;
+    }
+
+    c.pc = 0x0210;
+    c.v[2] = 0xEF;
+
+    // $0210  LD [I], V2
+    if budget == 8 {
+        return 8;
+    }
+
+    c.pc = 0x0212;
+    for j in 0..3 {
+        m.write(c.i + j, c.v[j]);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 3;
+    }
+
+    if !m.matches(0x0212, &CODE_0200[18..]) {
+        return 9;
+    }
+
+    // $0212  LD I, $230
+    if budget == 9 {
+        return 9;
+    }
+
+    c.pc = 0x0214;
+    c.i = 0x0230;
+
+    // $0214  LD V2, [I]
+    if budget == 10 {
+        return 10;
+    }
+
+    c.pc = 0x0216;
+    for j in 0..3 {
+        c.v[j] = m.read(c.i + j);
+    }
+    if !c.quirks.load_store_keep_i {
+        c.i += 3;
+    }
+
+    // $0216  LD I, $FFF
+    if budget == 11 {
+        return 11;
+    }
+
+    c.pc = 0x0218;
+    c.i = 0x0FFF;
+
+    // $0218  LD V9, $02
+    if budget == 12 {
+        return 12;
+    }
+
+    c.pc = 0x021A;
+    c.v[9] = 0x02;
+
+    // $021A  ADD I, V9
+    if budget == 13 {
+        return 13;
+    }
+
+    c.pc = 0x021C;
+    let r = c.i as u32 + c.v[9] as u32;
+    let mask = (m.size() - 1) as u32;
+    c.i = (r & mask) as usize;
+    if c.quirks.add_i_vf {
+        c.v[0xF] = (r > mask) as u8;
+    }
+
+    // $021C  LD VA, VF
+    if budget == 14 {
+        return 14;
+    }
+
+    c.pc = 0x021E;
+    c.v[10] = c.v[15];
+
+    // $021E  LD V6, $FF
+    if budget == 15 {
+        return 15;
+    }
+
+    c.pc = 0x0220;
+    c.v[6] = 0xFF;
+
+    // $0220  RND V6, $00
+    if budget == 16 {
+        return 16;
+    }
+
+    c.pc = 0x0222;
+    c.rng.next_u8();
+    c.v[6] = 0;
+
+    // $0222  LD V7, $1E
+    if budget == 17 {
+        return 17;
+    }
+
+    c.pc = 0x0224;
+    c.v[7] = 0x1E;
+
+    // $0224  LD DT, V7
+    if budget == 18 {
+        return 18;
+    }
+
+    c.pc = 0x0226;
+    c.dt = c.v[7];
+
+    // $0226  LD ST, V7
+    if budget == 19 {
+        return 19;
+    }
+
+    c.pc = 0x0228;
+    c.st = c.v[7];
+
+    20
+}
+
+const CODE_022A: &[u8] = &[
+    0x65, 0x0A, 0xF5, 0x29, 0x12, 0x2E,
+];
+
+fn block_022a(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $022A  LD V5, $0A
+    c.pc = 0x022C;
+    c.v[5] = 0x0A;
+
+    // $022C  LD [I], FONT V5
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x022E;
+    c.i = ((c.v[5] & 0xF) as usize) * 5;
+
+    // $022E  JP $22E
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0230;
+    c.pc = 0x022E;
+
+    3
+}
+
+const CODE_022E: &[u8] = &[
+    0x12, 0x2E,
+];
+
+fn block_022e(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $022E  JP $22E
+    c.pc = 0x0230;
+    c.pc = 0x022E;
+
+    1
+}
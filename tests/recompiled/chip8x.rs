@@ -0,0 +1,145 @@
+// Recompiled from a 40-byte Chip8x ROM by `xchip::recompile`; do not edit
+
+use xchip::{Context, Mmu, Mode, Recompiled, RecompiledBlock};
+
+pub const PROGRAM: Recompiled = Recompiled {
+    mode: Mode::Chip8x,
+    blocks: BLOCKS,
+};
+
+const BLOCKS: &[RecompiledBlock] = &[
+    RecompiledBlock { address: 0x0304, code: CODE_0304, run: block_0304 },
+    RecompiledBlock { address: 0x030C, code: CODE_030C, run: block_030c },
+    RecompiledBlock { address: 0x0312, code: CODE_0312, run: block_0312 },
+    RecompiledBlock { address: 0x0318, code: CODE_0318, run: block_0318 },
+    RecompiledBlock { address: 0x0322, code: CODE_0322, run: block_0322 },
+    RecompiledBlock { address: 0x0326, code: CODE_0326, run: block_0326 },
+];
+
+const CODE_0304: &[u8] = &[
+    0x60, 0x00, 0x61, 0x00, 0x62, 0x02,
+];
+
+fn block_0304(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0304  LD V0, $00
+    c.pc = 0x0306;
+    c.v[0] = 0x00;
+
+    // $0306  LD V1, $00
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0308;
+    c.v[1] = 0x00;
+
+    // $0308  LD V2, $02
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x030A;
+    c.v[2] = 0x02;
+
+    3
+}
+
+const CODE_030C: &[u8] = &[
+    0x60, 0x08, 0x62, 0x01,
+];
+
+fn block_030c(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $030C  LD V0, $08
+    c.pc = 0x030E;
+    c.v[0] = 0x08;
+
+    // $030E  LD V2, $01
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0310;
+    c.v[2] = 0x01;
+
+    2
+}
+
+const CODE_0312: &[u8] = &[
+    0x63, 0x17, 0x64, 0x37,
+];
+
+fn block_0312(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0312  LD V3, $17
+    c.pc = 0x0314;
+    c.v[3] = 0x17;
+
+    // $0314  LD V4, $37
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0316;
+    c.v[4] = 0x37;
+
+    2
+}
+
+const CODE_0318: &[u8] = &[
+    0x60, 0x00, 0x61, 0x00, 0x65, 0x0C, 0xF5, 0x29,
+];
+
+fn block_0318(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0318  LD V0, $00
+    c.pc = 0x031A;
+    c.v[0] = 0x00;
+
+    // $031A  LD V1, $00
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x031C;
+    c.v[1] = 0x00;
+
+    // $031C  LD V5, $0C
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x031E;
+    c.v[5] = 0x0C;
+
+    // $031E  LD [I], FONT V5
+    if budget == 3 {
+        return 3;
+    }
+
+    c.pc = 0x0320;
+    c.i = ((c.v[5] & 0xF) as usize) * 5;
+
+    4
+}
+
+const CODE_0322: &[u8] = &[
+    0x60, 0x08,
+];
+
+fn block_0322(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0322  LD V0, $08
+    c.pc = 0x0324;
+    c.v[0] = 0x08;
+
+    1
+}
+
+const CODE_0326: &[u8] = &[
+    0x13, 0x26,
+];
+
+fn block_0326(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $0326  JP $326
+    c.pc = 0x0328;
+    c.pc = 0x0326;
+
+    1
+}
@@ -0,0 +1,127 @@
+// Recompiled from a 86-byte Chip8 ROM by `xchip::recompile`; do not edit
+
+use xchip::{Context, Mmu, Mode, Recompiled, RecompiledBlock};
+
+pub const PROGRAM: Recompiled = Recompiled {
+    mode: Mode::Chip8,
+    blocks: BLOCKS,
+};
+
+const BLOCKS: &[RecompiledBlock] = &[
+    RecompiledBlock { address: 0x0200, code: CODE_0200, run: block_0200 },
+];
+
+const CODE_0200: &[u8] = &[
+    0x61, 0x81, 0x62, 0x04, 0x81, 0x26, 0x85, 0xF0, 0x8A, 0x10, 0x63, 0x40,
+    0x64, 0x81, 0x83, 0x4E, 0x86, 0xF0, 0x8B, 0x30, 0x60, 0x00, 0x62, 0x04,
+    0xB2, 0x1A,
+];
+
+fn block_0200(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0200  LD V1, $81
+    c.pc = 0x0202;
+    c.v[1] = 0x81;
+
+    // $0202  LD V2, $04
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0204;
+    c.v[2] = 0x04;
+
+    // $0204  SHR V1, V2
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0206;
+    let r = if c.quirks.shift_vx { c.v[1] } else { c.v[2] };
+    c.v[1] = r >> 1;
+    c.v[0xF] = r & 1;
+
+    // $0206  LD V5, VF
+    if budget == 3 {
+        return 3;
+    }
+
+    c.pc = 0x0208;
+    c.v[5] = c.v[15];
+
+    // $0208  LD VA, V1
+    if budget == 4 {
+        return 4;
+    }
+
+    c.pc = 0x020A;
+    c.v[10] = c.v[1];
+
+    // $020A  LD V3, $40
+    if budget == 5 {
+        return 5;
+    }
+
+    c.pc = 0x020C;
+    c.v[3] = 0x40;
+
+    // $020C  LD V4, $81
+    if budget == 6 {
+        return 6;
+    }
+
+    c.pc = 0x020E;
+    c.v[4] = 0x81;
+
+    // $020E  SHL V3, V4
+    if budget == 7 {
+        return 7;
+    }
+
+    c.pc = 0x0210;
+    let r = if c.quirks.shift_vx { c.v[3] } else { c.v[4] };
+    c.v[3] = r << 1;
+    c.v[0xF] = r >> 7;
+
+    // $0210  LD V6, VF
+    if budget == 8 {
+        return 8;
+    }
+
+    c.pc = 0x0212;
+    c.v[6] = c.v[15];
+
+    // $0212  LD VB, V3
+    if budget == 9 {
+        return 9;
+    }
+
+    c.pc = 0x0214;
+    c.v[11] = c.v[3];
+
+    // $0214  LD V0, $00
+    if budget == 10 {
+        return 10;
+    }
+
+    c.pc = 0x0216;
+    c.v[0] = 0x00;
+
+    // $0216  LD V2, $04
+    if budget == 11 {
+        return 11;
+    }
+
+    c.pc = 0x0218;
+    c.v[2] = 0x04;
+
+    // $0218  JP V0, $21A
+    if budget == 12 {
+        return 12;
+    }
+
+    c.pc = 0x021A;
+    let v = c.v[if c.quirks.jump_vx { 2 } else { 0 }];
+    c.pc = (0x021Au16.wrapping_add(v as u16)) as usize;
+
+    13
+}
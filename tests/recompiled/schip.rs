@@ -0,0 +1,251 @@
+// Recompiled from a 110-byte SuperChip ROM by `xchip::recompile`; do not edit
+
+use xchip::{Context, Mmu, Mode, Recompiled, RecompiledBlock};
+
+pub const PROGRAM: Recompiled = Recompiled {
+    mode: Mode::SuperChip,
+    blocks: BLOCKS,
+};
+
+const BLOCKS: &[RecompiledBlock] = &[
+    RecompiledBlock { address: 0x0204, code: CODE_0204, run: block_0204 },
+    RecompiledBlock { address: 0x020C, code: CODE_020C, run: block_020c },
+    RecompiledBlock { address: 0x021E, code: CODE_021E, run: block_021e },
+    RecompiledBlock { address: 0x0228, code: CODE_0228, run: block_0228 },
+    RecompiledBlock { address: 0x023A, code: CODE_023A, run: block_023a },
+    RecompiledBlock { address: 0x024C, code: CODE_024C, run: block_024c },
+];
+
+const CODE_0204: &[u8] = &[
+    0x60, 0x00, 0x61, 0x00, 0xA2, 0x4E,
+];
+
+fn block_0204(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0204  LD V0, $00
+    c.pc = 0x0206;
+    c.v[0] = 0x00;
+
+    // $0206  LD V1, $00
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0208;
+    c.v[1] = 0x00;
+
+    // $0208  LD I, $24E
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x020A;
+    c.i = 0x024E;
+
+    3
+}
+
+const CODE_020C: &[u8] = &[
+    0x60, 0x18, 0x62, 0x09,
+];
+
+fn block_020c(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $020C  LD V0, $18
+    c.pc = 0x020E;
+    c.v[0] = 0x18;
+
+    // $020E  LD V2, $09
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0210;
+    c.v[2] = 0x09;
+
+    2
+}
+
+const CODE_021E: &[u8] = &[
+    0x60, 0x04, 0x61, 0x14, 0x62, 0x07, 0xF2, 0x29,
+];
+
+fn block_021e(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $021E  LD V0, $04
+    c.pc = 0x0220;
+    c.v[0] = 0x04;
+
+    // $0220  LD V1, $14
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x0222;
+    c.v[1] = 0x14;
+
+    // $0222  LD V2, $07
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0224;
+    c.v[2] = 0x07;
+
+    // $0224  LD [I], FONT V2
+    if budget == 3 {
+        return 3;
+    }
+
+    c.pc = 0x0226;
+    c.i = ((c.v[2] & 0xF) as usize) * 5;
+
+    4
+}
+
+const CODE_0228: &[u8] = &[
+    0x60, 0x10, 0x61, 0x11, 0x62, 0x12, 0x63, 0x13, 0x64, 0x14, 0x65, 0x15,
+    0x66, 0x16, 0x67, 0x17,
+];
+
+fn block_0228(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $0228  LD V0, $10
+    c.pc = 0x022A;
+    c.v[0] = 0x10;
+
+    // $022A  LD V1, $11
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x022C;
+    c.v[1] = 0x11;
+
+    // $022C  LD V2, $12
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x022E;
+    c.v[2] = 0x12;
+
+    // $022E  LD V3, $13
+    if budget == 3 {
+        return 3;
+    }
+
+    c.pc = 0x0230;
+    c.v[3] = 0x13;
+
+    // $0230  LD V4, $14
+    if budget == 4 {
+        return 4;
+    }
+
+    c.pc = 0x0232;
+    c.v[4] = 0x14;
+
+    // $0232  LD V5, $15
+    if budget == 5 {
+        return 5;
+    }
+
+    c.pc = 0x0234;
+    c.v[5] = 0x15;
+
+    // $0234  LD V6, $16
+    if budget == 6 {
+        return 6;
+    }
+
+    c.pc = 0x0236;
+    c.v[6] = 0x16;
+
+    // $0236  LD V7, $17
+    if budget == 7 {
+        return 7;
+    }
+
+    c.pc = 0x0238;
+    c.v[7] = 0x17;
+
+    8
+}
+
+const CODE_023A: &[u8] = &[
+    0x60, 0x00, 0x61, 0x00, 0x62, 0x00, 0x63, 0x00, 0x64, 0x00, 0x65, 0x00,
+    0x66, 0x00, 0x67, 0x00,
+];
+
+fn block_023a(c: &mut Context, _m: &mut Mmu, budget: usize) -> usize {
+    // $023A  LD V0, $00
+    c.pc = 0x023C;
+    c.v[0] = 0x00;
+
+    // $023C  LD V1, $00
+    if budget == 1 {
+        return 1;
+    }
+
+    c.pc = 0x023E;
+    c.v[1] = 0x00;
+
+    // $023E  LD V2, $00
+    if budget == 2 {
+        return 2;
+    }
+
+    c.pc = 0x0240;
+    c.v[2] = 0x00;
+
+    // $0240  LD V3, $00
+    if budget == 3 {
+        return 3;
+    }
+
+    c.pc = 0x0242;
+    c.v[3] = 0x00;
+
+    // $0242  LD V4, $00
+    if budget == 4 {
+        return 4;
+    }
+
+    c.pc = 0x0244;
+    c.v[4] = 0x00;
+
+    // $0244  LD V5, $00
+    if budget == 5 {
+        return 5;
+    }
+
+    c.pc = 0x0246;
+    c.v[5] = 0x00;
+
+    // $0246  LD V6, $00
+    if budget == 6 {
+        return 6;
+    }
+
+    c.pc = 0x0248;
+    c.v[6] = 0x00;
+
+    // $0248  LD V7, $00
+    if budget == 7 {
+        return 7;
+    }
+
+    c.pc = 0x024A;
+    c.v[7] = 0x00;
+
+    8
+}
+
+const CODE_024C: &[u8] = &[
+    0x12, 0x4C,
+];
+
+fn block_024c(c: &mut Context, _m: &mut Mmu, _budget: usize) -> usize {
+    // $024C  JP $24C
+    c.pc = 0x024E;
+    c.pc = 0x024C;
+
+    1
+}